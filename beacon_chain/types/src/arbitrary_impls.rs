@@ -0,0 +1,106 @@
+/*
+ * Generation of arbitrary AttestationRecords for fuzzing, enabled with the "arbitrary" feature.
+ */
+use super::arbitrary::{
+    Arbitrary,
+    Result,
+    Unstructured,
+};
+use super::{
+    AttestationRecord,
+    Bitfield,
+    Hash256,
+};
+use super::bls::{
+    AggregateSignature,
+    BLS_AGG_SIG_BYTE_SIZE,
+};
+
+/// The maximum number of oblique parent hashes generated. This is the maximum cycle length, so
+/// it covers both permitted and excessive counts.
+const MAX_OBLIQUE_PARENT_HASHES: usize = 255;
+
+/// The maximum number of bits generated for an attester bitfield.
+const MAX_BITFIELD_BITS: usize = 1024;
+
+fn arbitrary_hash(u: &mut Unstructured) -> Result<Hash256> {
+    Ok(Hash256::from(u.bytes(32)?))
+}
+
+impl<'a> Arbitrary<'a> for AttestationRecord {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let slot = u.arbitrary()?;
+        let shard_id = u.arbitrary()?;
+
+        let oblique_count = u.int_in_range(0..=MAX_OBLIQUE_PARENT_HASHES)?;
+        let mut oblique_parent_hashes = Vec::with_capacity(oblique_count);
+        for _ in 0..oblique_count {
+            oblique_parent_hashes.push(arbitrary_hash(u)?);
+        }
+
+        let shard_block_hash = arbitrary_hash(u)?;
+
+        /*
+         * The number of bits is not related to any committee size, so the bitfield may be either
+         * a valid or an invalid length for some attester map.
+         *
+         * Each bit is set (to either true or false), so the bitfield is always
+         * `bit_count` bits long.
+         */
+        let bit_count = u.int_in_range(0..=MAX_BITFIELD_BITS)?;
+        let mut attester_bitfield = Bitfield::with_capacity(bit_count);
+        for i in 0..bit_count {
+            attester_bitfield.set_bit(i, u.arbitrary()?);
+        }
+
+        let justified_slot = u.arbitrary()?;
+        let justified_block_hash = arbitrary_hash(u)?;
+
+        /*
+         * Random bytes are unlikely to be a valid point, in which case an empty aggregate
+         * signature is used.
+         */
+        let aggregate_sig = AggregateSignature::from_bytes(u.bytes(BLS_AGG_SIG_BYTE_SIZE)?)
+            .unwrap_or_else(|_| AggregateSignature::new());
+
+        Ok(Self {
+            slot,
+            shard_id,
+            oblique_parent_hashes,
+            shard_block_hash,
+            attester_bitfield,
+            justified_slot,
+            justified_block_hash,
+            aggregate_sig,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_arbitrary_attestation_record() {
+        /*
+         * Generate records from some deterministic pseudo-random bytes.
+         */
+        let data: Vec<u8> = (0..1_000_000u64)
+            .map(|i| (i.wrapping_mul(6364136223846793005) >> 32) as u8)
+            .collect();
+        let mut u = Unstructured::new(&data);
+
+        let mut bitfield_lengths = HashSet::new();
+        while let Ok(record) = AttestationRecord::arbitrary(&mut u) {
+            assert!(record.oblique_parent_hashes.len() <= MAX_OBLIQUE_PARENT_HASHES);
+            assert!(record.attester_bitfield.len() <= MAX_BITFIELD_BITS);
+            bitfield_lengths.insert(record.attester_bitfield.num_bytes());
+
+            let (decoded, _) = AttestationRecord::ssz_decode_exact(&record.ssz_encode())
+                .unwrap();
+            assert_eq!(decoded.slot, record.slot);
+        }
+        assert!(bitfield_lengths.len() > 1);
+    }
+}
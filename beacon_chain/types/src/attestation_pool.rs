@@ -0,0 +1,195 @@
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+use super::{
+    AggregationError,
+    AttestationRecord,
+    Hash256,
+};
+
+/// The fields which must be identical for two AttestationRecords to be aggregated.
+///
+/// Within a single validation context the parent hashes signed by an attestation are derived
+/// from its slot and oblique parent hashes, so records sharing this key sign the same message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct AggregationKey {
+    shard_id: u16,
+    oblique_parent_hashes: Vec<Hash256>,
+    shard_block_hash: Hash256,
+    justified_slot: u64,
+    justified_block_hash: Hash256,
+}
+
+impl<'a> From<&'a AttestationRecord> for AggregationKey {
+    fn from(a: &'a AttestationRecord) -> Self {
+        Self {
+            shard_id: a.shard_id,
+            oblique_parent_hashes: a.oblique_parent_hashes.clone(),
+            shard_block_hash: a.shard_block_hash,
+            justified_slot: a.justified_slot,
+            justified_block_hash: a.justified_block_hash,
+        }
+    }
+}
+
+/// Collects validated AttestationRecords, aggregating records which sign the same message
+/// wherever their attesters do not overlap.
+///
+/// Records are not validated by the pool, they should be validated before insertion.
+#[derive(Debug, Clone, Default)]
+pub struct AttestationPool {
+    slots: BTreeMap<u64, HashMap<AggregationKey, Vec<AttestationRecord>>>,
+}
+
+impl AttestationPool {
+    pub fn new() -> Self {
+        Self {
+            slots: BTreeMap::new(),
+        }
+    }
+
+    /// Add a record to the pool.
+    ///
+    /// The record is merged into the first existing aggregate for the same message with which it
+    /// shares no attesters. Otherwise, if all of its attesters are already present in some
+    /// aggregate the record is discarded, else it is kept as a separate aggregate.
+    pub fn insert(&mut self, attestation: AttestationRecord) {
+        let aggregates = self.slots.entry(attestation.slot)
+            .or_default()
+            .entry(AggregationKey::from(&attestation))
+            .or_default();
+
+        for aggregate in aggregates.iter_mut() {
+            match aggregate.try_aggregate(&attestation) {
+                Ok(merged) => {
+                    *aggregate = merged;
+                    return;
+                }
+                Err(AggregationError::OverlappingBits) |
+                Err(AggregationError::MismatchedBitfieldLength) |
+                Err(AggregationError::BadAggregateSignature) |
+                Err(AggregationError::MismatchedFields) => (),
+            }
+        }
+
+        let is_duplicate = aggregates.iter().any(|aggregate| {
+            aggregate.attester_bitfield.num_bytes() == attestation.attester_bitfield.num_bytes() &&
+                attestation.attester_bitfield.iter_set_bits()
+                    .all(|i| aggregate.attester_bitfield.get(i))
+        });
+        if !is_duplicate {
+            aggregates.push(attestation);
+        }
+    }
+
+    /// Return all aggregates at the given slot, those with the most attesters first.
+    pub fn best_attestations_for_slot(&self, slot: u64) -> Vec<&AttestationRecord> {
+        let mut attestations: Vec<&AttestationRecord> = self.slots.get(&slot)
+            .map(|keys| keys.values().flat_map(|aggregates| aggregates.iter()).collect())
+            .unwrap_or_default();
+        attestations.sort_by(|a, b| {
+            b.attester_bitfield.num_true_bits().cmp(&a.attester_bitfield.num_true_bits())
+        });
+        attestations
+    }
+
+    /// Remove all aggregates at slots lower than the given slot.
+    pub fn remove_before(&mut self, slot: u64) {
+        self.slots = self.slots.split_off(&slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::bls::{
+        Keypair,
+        Signature,
+    };
+
+    fn signed_record(slot: u64, signers: &[(usize, &Keypair)]) -> AttestationRecord {
+        let mut record = AttestationRecord::zero();
+        record.slot = slot;
+        for (i, keypair) in signers {
+            record.attester_bitfield.set_bit(*i, true);
+            record.aggregate_sig.add(&Signature::new(&[42], &keypair.sk));
+        }
+        record
+    }
+
+    fn voters(a: &AttestationRecord) -> Vec<usize> {
+        a.attester_bitfield.iter_set_bits().collect()
+    }
+
+    #[test]
+    fn test_attestation_pool_merge() {
+        let keypairs: Vec<Keypair> = (0..3).map(|_| Keypair::random()).collect();
+        let mut pool = AttestationPool::new();
+
+        pool.insert(signed_record(1, &[(0, &keypairs[0])]));
+        pool.insert(signed_record(1, &[(2, &keypairs[2])]));
+        pool.insert(signed_record(1, &[(1, &keypairs[1])]));
+
+        let best = pool.best_attestations_for_slot(1);
+        assert_eq!(best.len(), 1);
+        assert_eq!(voters(best[0]), vec![0, 1, 2]);
+
+        /*
+         * A record whose attesters are all present is discarded.
+         */
+        pool.insert(signed_record(1, &[(0, &keypairs[0]), (2, &keypairs[2])]));
+        assert_eq!(pool.best_attestations_for_slot(1).len(), 1);
+    }
+
+    #[test]
+    fn test_attestation_pool_overlap() {
+        let keypairs: Vec<Keypair> = (0..3).map(|_| Keypair::random()).collect();
+        let mut pool = AttestationPool::new();
+
+        pool.insert(signed_record(1, &[(0, &keypairs[0]), (1, &keypairs[1])]));
+        pool.insert(signed_record(1, &[(1, &keypairs[1]), (2, &keypairs[2])]));
+
+        let best = pool.best_attestations_for_slot(1);
+        assert_eq!(best.len(), 2);
+
+        /*
+         * A record for some other shard block is never merged.
+         */
+        let mut different_block = signed_record(1, &[(0, &keypairs[0])]);
+        different_block.shard_block_hash = Hash256::from(&vec![1; 32][..]);
+        pool.insert(different_block);
+
+        /*
+         * A record which overlaps the first aggregate but not the second is merged into the
+         * second, rather than being discarded as a duplicate.
+         */
+        pool.insert(signed_record(1, &[(0, &keypairs[0])]));
+
+        let best = pool.best_attestations_for_slot(1);
+        assert_eq!(best.len(), 3);
+        assert_eq!(voters(best[0]), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_attestation_pool_retrieval() {
+        let keypairs: Vec<Keypair> = (0..3).map(|_| Keypair::random()).collect();
+        let mut pool = AttestationPool::new();
+
+        let mut other_shard = signed_record(2, &[(0, &keypairs[0])]);
+        other_shard.shard_id = 1;
+        pool.insert(other_shard);
+        pool.insert(signed_record(2, &[(0, &keypairs[0]), (1, &keypairs[1])]));
+        pool.insert(signed_record(3, &[(0, &keypairs[0])]));
+
+        let best = pool.best_attestations_for_slot(2);
+        assert_eq!(best.len(), 2);
+        assert_eq!(voters(best[0]), vec![0, 1]);
+        assert_eq!(best[1].shard_id, 1);
+        assert!(pool.best_attestations_for_slot(4).is_empty());
+
+        pool.remove_before(3);
+        assert!(pool.best_attestations_for_slot(2).is_empty());
+        assert_eq!(pool.best_attestations_for_slot(3).len(), 1);
+    }
+}
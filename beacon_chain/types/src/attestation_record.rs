@@ -3,6 +3,7 @@ use super::bls::{
     AggregateSignature,
     BLS_AGG_SIG_BYTE_SIZE,
 };
+use super::hashing::canonical_hash;
 use super::ssz::{
     Encodable,
     Decodable,
@@ -22,6 +23,33 @@ pub const MIN_SSZ_ATTESTION_RECORD_LENGTH: usize = {
     4 + BLS_AGG_SIG_BYTE_SIZE    // aggregate sig (two 256 bit points)
 };
 
+#[derive(Debug, PartialEq)]
+pub enum AggregationError {
+    /// Some field other than the bitfield or signature differs between the records.
+    MismatchedFields,
+    /// The bitfields are not the same number of bytes.
+    MismatchedBitfieldLength,
+    /// Some validator is present in both bitfields.
+    OverlappingBits,
+    /// An aggregate signature could not be combined with the other.
+    BadAggregateSignature,
+}
+
+/// The shard block for which an attestation votes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VoteTarget {
+    pub shard_id: u16,
+    pub shard_block_hash: Hash256,
+    pub slot: u64,
+}
+
+/// A justified block, identified by both its slot and its hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Checkpoint {
+    pub slot: u64,
+    pub block_hash: Hash256,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct AttestationRecord {
     pub slot: u64,
@@ -43,7 +71,7 @@ impl Encodable for AttestationRecord {
         s.append_vec(&self.attester_bitfield.to_be_vec());
         s.append(&self.justified_slot);
         s.append(&self.justified_block_hash);
-        s.append_vec(&self.aggregate_sig.as_bytes());
+        s.append_vec(&self.aggregate_sig.to_bytes());
     }
 }
 
@@ -78,6 +106,100 @@ impl Decodable for AttestationRecord {
 }
 
 impl AttestationRecord {
+    /// Return the SSZ encoding of this record.
+    ///
+    /// Fields are encoded in the order they are declared, with the bitfield encoded big-endian.
+    pub fn ssz_encode(&self) -> Vec<u8> {
+        let mut ssz_stream = SszStream::new();
+        ssz_stream.append(self);
+        ssz_stream.drain()
+    }
+
+    /// Decode a record from SSZ which must occupy the entirety of `bytes`.
+    ///
+    /// Returns the record and the number of bytes consumed. Returns `DecodeError::TooLong` if
+    /// there are bytes remaining after the record.
+    pub fn ssz_decode_exact(bytes: &[u8])
+        -> Result<(Self, usize), DecodeError>
+    {
+        let (record, i) = Self::ssz_decode(bytes, 0)?;
+        if i != bytes.len() {
+            return Err(DecodeError::TooLong);
+        }
+        Ok((record, i))
+    }
+
+    /// Return the shard block for which this record votes.
+    pub fn vote_target(&self) -> VoteTarget {
+        VoteTarget {
+            shard_id: self.shard_id,
+            shard_block_hash: self.shard_block_hash,
+            slot: self.slot,
+        }
+    }
+
+    /// Return the justified block referenced by this record.
+    pub fn justified_checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            slot: self.justified_slot,
+            block_hash: self.justified_block_hash,
+        }
+    }
+
+    /// Return the canonical hash of this record, computed over its SSZ encoding.
+    ///
+    /// As the hash is over the raw bytes, records which differ only in their bitfield padding
+    /// bits will produce different hashes.
+    pub fn canonical_hash(&self) -> Hash256 {
+        Hash256::from(&canonical_hash(&self.ssz_encode())[..])
+    }
+
+    /// Combine this record with another record for the same (slot, shard_id, oblique parent
+    /// hashes, shard_block_hash, justified_slot, justified_block_hash) into a single record.
+    ///
+    /// The bitfields are merged and the signatures aggregated. The signatures themselves are not
+    /// verified. Returns an error if any validator is present in both records.
+    pub fn try_aggregate(&self, other: &AttestationRecord)
+        -> Result<AttestationRecord, AggregationError>
+    {
+        if self.slot != other.slot ||
+            self.shard_id != other.shard_id ||
+            self.oblique_parent_hashes != other.oblique_parent_hashes ||
+            self.shard_block_hash != other.shard_block_hash ||
+            self.justified_slot != other.justified_slot ||
+            self.justified_block_hash != other.justified_block_hash
+        {
+            return Err(AggregationError::MismatchedFields);
+        }
+
+        if self.attester_bitfield.num_bytes() != other.attester_bitfield.num_bytes() {
+            return Err(AggregationError::MismatchedBitfieldLength);
+        }
+
+        let mut attester_bitfield = self.attester_bitfield.clone();
+        for i in other.attester_bitfield.iter_set_bits() {
+            if attester_bitfield.get(i) {
+                return Err(AggregationError::OverlappingBits);
+            }
+            attester_bitfield.set_bit(i, true);
+        }
+
+        let mut aggregate_sig = self.aggregate_sig.clone();
+        aggregate_sig.add_aggregate(&other.aggregate_sig)
+            .map_err(|_| AggregationError::BadAggregateSignature)?;
+
+        Ok(AttestationRecord {
+            slot: self.slot,
+            shard_id: self.shard_id,
+            oblique_parent_hashes: self.oblique_parent_hashes.clone(),
+            shard_block_hash: self.shard_block_hash,
+            attester_bitfield,
+            justified_slot: self.justified_slot,
+            justified_block_hash: self.justified_block_hash,
+            aggregate_sig,
+        })
+    }
+
     pub fn zero() -> Self {
         Self {
             slot: 0,
@@ -97,6 +219,33 @@ impl AttestationRecord {
 mod tests {
     use super::*;
     use super::super::ssz::SszStream;
+    use super::super::bls::{
+        AggregatePublicKey,
+        Keypair,
+        Signature,
+    };
+
+    #[test]
+    fn test_attestation_record_vote_target() {
+        let mut a = AttestationRecord::zero();
+        a.slot = 42;
+        a.shard_id = 3;
+        a.shard_block_hash = Hash256::from(7);
+
+        let target = a.vote_target();
+        assert_eq!(target, VoteTarget {
+            shard_id: 3,
+            shard_block_hash: Hash256::from(7),
+            slot: 42,
+        });
+
+        /*
+         * The target does not depend on the justified block or the attesters.
+         */
+        a.justified_slot = 41;
+        a.attester_bitfield.set_bit(0, true);
+        assert_eq!(a.vote_target(), target);
+    }
 
     #[test]
     pub fn test_attestation_record_min_ssz_length() {
@@ -134,4 +283,140 @@ mod tests {
         assert_eq!(original.justified_slot, decoded.justified_slot);
         assert_eq!(original.justified_block_hash, decoded.justified_block_hash);
     }
+
+    #[test]
+    pub fn test_attestation_record_ssz_encode_decode_exact() {
+        let mut attester_bitfield = Bitfield::new();
+        attester_bitfield.set_bit(0, true);
+        attester_bitfield.set_bit(9, true);
+        attester_bitfield.set_bit(17, true);
+
+        let original = AttestationRecord {
+            slot: 7,
+            shard_id: 9,
+            oblique_parent_hashes: vec![
+                Hash256::from(&vec![14; 32][..]),
+                Hash256::from(&vec![42; 32][..]),
+            ],
+            shard_block_hash: Hash256::from(&vec![15; 32][..]),
+            attester_bitfield,
+            justified_slot: 19,
+            justified_block_hash: Hash256::from(&vec![16; 32][..]),
+            aggregate_sig: AggregateSignature::new(),
+        };
+
+        let ssz = original.ssz_encode();
+        assert_eq!(ssz.len(), MIN_SSZ_ATTESTION_RECORD_LENGTH + 2 * 32 + 2);
+        assert_eq!(&ssz[0..8], &[0, 0, 0, 0, 0, 0, 0, 7]);
+        assert_eq!(&ssz[8..10], &[0, 9]);
+        assert_eq!(&ssz[10..14], &[0, 0, 0, 64]);
+
+        let (decoded, i) = AttestationRecord::ssz_decode_exact(&ssz).unwrap();
+        assert_eq!(i, ssz.len());
+        assert_eq!(decoded.slot, original.slot);
+        assert_eq!(decoded.shard_id, original.shard_id);
+        assert_eq!(decoded.oblique_parent_hashes, original.oblique_parent_hashes);
+        assert_eq!(decoded.shard_block_hash, original.shard_block_hash);
+        assert_eq!(decoded.attester_bitfield.to_vec(), original.attester_bitfield.to_vec());
+        assert_eq!(decoded.justified_slot, original.justified_slot);
+        assert_eq!(decoded.justified_block_hash, original.justified_block_hash);
+        assert_eq!(decoded.ssz_encode(), ssz);
+    }
+
+    #[test]
+    pub fn test_attestation_record_ssz_decode_exact_trailing_bytes() {
+        let mut ssz = AttestationRecord::zero().ssz_encode();
+        ssz.push(42);
+
+        assert_eq!(AttestationRecord::ssz_decode_exact(&ssz), Err(DecodeError::TooLong));
+        assert_eq!(AttestationRecord::ssz_decode_exact(&ssz[..ssz.len() - 2]),
+                   Err(DecodeError::TooShort));
+    }
+
+    #[test]
+    pub fn test_attestation_record_canonical_hash() {
+        let original = AttestationRecord {
+            slot: 7,
+            shard_id: 9,
+            oblique_parent_hashes: vec![Hash256::from(&vec![14; 32][..])],
+            shard_block_hash: Hash256::from(&vec![15; 32][..]),
+            attester_bitfield: Bitfield::from(&vec![17; 2][..]),
+            justified_slot: 19,
+            justified_block_hash: Hash256::from(&vec![16; 32][..]),
+            aggregate_sig: AggregateSignature::new(),
+        };
+        let hash = original.canonical_hash();
+        assert_eq!(original.clone().canonical_hash(), hash);
+
+        let mutations: Vec<fn(&mut AttestationRecord)> = vec![
+            |a| a.slot += 1,
+            |a| a.shard_id += 1,
+            |a| a.oblique_parent_hashes.push(Hash256::zero()),
+            |a| a.shard_block_hash = Hash256::zero(),
+            |a| a.attester_bitfield.set_bit(15, true),
+            |a| a.justified_slot += 1,
+            |a| a.justified_block_hash = Hash256::zero(),
+            |a| a.aggregate_sig.add(&Signature::new(&[42], &Keypair::random().sk)),
+        ];
+        for mutate in mutations {
+            let mut mutated = original.clone();
+            mutate(&mut mutated);
+            assert_ne!(mutated.canonical_hash(), hash);
+        }
+    }
+
+    fn signed_record(message: &[u8], signers: &[(usize, &Keypair)]) -> AttestationRecord {
+        let mut record = AttestationRecord::zero();
+        for (i, keypair) in signers {
+            record.attester_bitfield.set_bit(*i, true);
+            record.aggregate_sig.add(&Signature::new(message, &keypair.sk));
+        }
+        record
+    }
+
+    #[test]
+    pub fn test_attestation_record_try_aggregate() {
+        let message = "cats".as_bytes();
+        let keypairs: Vec<Keypair> = (0..3).map(|_| Keypair::random()).collect();
+
+        let a = signed_record(&message, &[(0, &keypairs[0]), (2, &keypairs[2])]);
+        let b = signed_record(&message, &[(1, &keypairs[1])]);
+
+        let aggregate = a.try_aggregate(&b).unwrap();
+        assert_eq!(aggregate.attester_bitfield.iter_set_bits().collect::<Vec<usize>>(),
+                   vec![0, 1, 2]);
+
+        let mut agg_pub_key = AggregatePublicKey::new();
+        for keypair in &keypairs {
+            agg_pub_key.add(&keypair.pk);
+        }
+        assert!(aggregate.aggregate_sig.verify(&message, &agg_pub_key));
+    }
+
+    #[test]
+    pub fn test_attestation_record_try_aggregate_mismatched_fields() {
+        let a = AttestationRecord::zero();
+        let mut b = AttestationRecord::zero();
+        b.shard_id = 1;
+        assert_eq!(a.try_aggregate(&b), Err(AggregationError::MismatchedFields));
+
+        let mut b = AttestationRecord::zero();
+        b.justified_block_hash = Hash256::from(&vec![1; 32][..]);
+        assert_eq!(a.try_aggregate(&b), Err(AggregationError::MismatchedFields));
+
+        let mut b = AttestationRecord::zero();
+        b.attester_bitfield.set_bit(8, true);
+        assert_eq!(a.try_aggregate(&b), Err(AggregationError::MismatchedBitfieldLength));
+    }
+
+    #[test]
+    pub fn test_attestation_record_try_aggregate_overlapping_bits() {
+        let message = "cats".as_bytes();
+        let keypairs: Vec<Keypair> = (0..2).map(|_| Keypair::random()).collect();
+
+        let a = signed_record(&message, &[(0, &keypairs[0]), (1, &keypairs[1])]);
+        let b = signed_record(&message, &[(1, &keypairs[1])]);
+
+        assert_eq!(a.try_aggregate(&b), Err(AggregationError::OverlappingBits));
+    }
 }
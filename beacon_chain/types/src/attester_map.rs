@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+/// Maps a (slot, shard_id) to attestation_indices.
+///
+/// The attestation_indices of a committee are the canonical indices of its validators, ordered by
+/// their position in the attester bitfield. Bit `i` of a bitfield refers to
+/// `attestation_indices[i]`, so the canonical indices need not be contiguous or sorted (e.g., a
+/// committee of `[3, 7, 100]` after exits have left gaps in the validator registry).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AttesterMap {
+    map: BTreeMap<(u64, u16), Vec<usize>>,
+}
+
+impl AttesterMap {
+    pub fn new() -> Self {
+        Self {
+            map: BTreeMap::new(),
+        }
+    }
+
+    /// Return the attestation_indices for some (slot, shard_id), if any.
+    pub fn get(&self, key: &(u64, u16)) -> Option<&Vec<usize>> {
+        self.map.get(key)
+    }
+
+    /// Set the attestation_indices for some (slot, shard_id), returning the previous
+    /// attestation_indices, if any.
+    pub fn insert(&mut self, slot: u64, shard_id: u16, attestation_indices: Vec<usize>)
+        -> Option<Vec<usize>>
+    {
+        self.map.insert((slot, shard_id), attestation_indices)
+    }
+
+    /// Remove the attestation_indices for all shards at slots lower than the given slot.
+    ///
+    /// Allows the map to be pruned as the chain advances, rather than being rebuilt.
+    pub fn remove_before(&mut self, slot: u64) {
+        self.map = self.map.split_off(&(slot, 0));
+    }
+
+    /// Returns true if the map contains attestation_indices for any shard at the given slot.
+    pub fn contains_slot(&self, slot: u64) -> bool {
+        self.map.range((slot, 0)..=(slot, u16::max_value())).next().is_some()
+    }
+
+    /// Return the shard_ids with attestation_indices at the given slot, in ascending order.
+    pub fn shards_for_slot(&self, slot: u64) -> Vec<u16> {
+        self.map.range((slot, 0)..=(slot, u16::max_value()))
+            .map(|((_, shard_id), _)| *shard_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attester_map_get() {
+        let mut map = AttesterMap::new();
+        map.insert(1, 2, vec![3, 4]);
+
+        assert_eq!(map.get(&(1, 2)), Some(&vec![3, 4]));
+        assert_eq!(map.get(&(1, 3)), None);
+        assert_eq!(map.get(&(2, 2)), None);
+    }
+
+    #[test]
+    fn test_attester_map_slots_and_shards() {
+        let mut map = AttesterMap::new();
+        map.insert(0, u16::max_value(), vec![0]);
+        map.insert(5, 9, vec![1]);
+        map.insert(5, 0, vec![2]);
+        map.insert(5, 3, vec![3]);
+        map.insert(6, 1, vec![4]);
+
+        assert!(map.contains_slot(0));
+        assert!(map.contains_slot(5));
+        assert!(!map.contains_slot(4));
+        assert!(!map.contains_slot(u64::max_value()));
+
+        assert_eq!(map.shards_for_slot(0), vec![u16::max_value()]);
+        assert_eq!(map.shards_for_slot(5), vec![0, 3, 9]);
+        assert_eq!(map.shards_for_slot(7), vec![]);
+    }
+
+    #[test]
+    fn test_attester_map_remove_before() {
+        let mut map = AttesterMap::new();
+        map.insert(0, 1, vec![0]);
+        map.insert(3, u16::max_value(), vec![1]);
+        map.insert(4, 0, vec![2]);
+        map.insert(4, 2, vec![3]);
+        map.insert(9, 1, vec![4]);
+
+        map.remove_before(4);
+
+        assert_eq!(map.get(&(0, 1)), None);
+        assert_eq!(map.get(&(3, u16::max_value())), None);
+        assert_eq!(map.get(&(4, 0)), Some(&vec![2]));
+        assert_eq!(map.get(&(4, 2)), Some(&vec![3]));
+        assert_eq!(map.get(&(9, 1)), Some(&vec![4]));
+
+        /*
+         * Pruned entries may be replaced incrementally.
+         */
+        map.insert(10, 1, vec![5]);
+        map.remove_before(10);
+        assert!(!map.contains_slot(9));
+        assert_eq!(map.get(&(10, 1)), Some(&vec![5]));
+    }
+}
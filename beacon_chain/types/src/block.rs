@@ -1,6 +1,12 @@
 use super::Hash256;
 use super::attestation_record::AttestationRecord;
-use super::ssz::{ Encodable, SszStream };
+use super::ssz::{
+    Decodable,
+    DecodeError,
+    Encodable,
+    SszStream,
+    decode_ssz_list,
+};
 
 pub const MIN_SSZ_BLOCK_LENGTH: usize = {
     32 +    // parent_hash
@@ -50,6 +56,31 @@ impl Encodable for Block {
     }
 }
 
+impl Decodable for Block {
+    fn ssz_decode(bytes: &[u8], i: usize)
+        -> Result<(Self, usize), DecodeError>
+    {
+        let (parent_hash, i) = Hash256::ssz_decode(bytes, i)?;
+        let (slot_number, i) = u64::ssz_decode(bytes, i)?;
+        let (randao_reveal, i) = Hash256::ssz_decode(bytes, i)?;
+        let (attestations, i) = decode_ssz_list(bytes, i)?;
+        let (pow_chain_ref, i) = Hash256::ssz_decode(bytes, i)?;
+        let (active_state_root, i) = Hash256::ssz_decode(bytes, i)?;
+        let (crystallized_state_root, i) = Hash256::ssz_decode(bytes, i)?;
+
+        let block = Self {
+            parent_hash,
+            slot_number,
+            randao_reveal,
+            attestations,
+            pow_chain_ref,
+            active_state_root,
+            crystallized_state_root,
+        };
+        Ok((block, i))
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -67,6 +98,23 @@ mod tests {
         assert!(b.crystallized_state_root.is_zero());
     }
 
+    #[test]
+    fn test_block_ssz_round_trip() {
+        let mut b = Block::zero();
+        b.parent_hash = Hash256::from(1);
+        b.slot_number = 42;
+        b.attestations = vec![super::super::AttestationRecord::zero(); 2];
+        b.crystallized_state_root = Hash256::from(2);
+
+        let mut ssz_stream = SszStream::new();
+        ssz_stream.append(&b);
+        let ssz = ssz_stream.drain();
+
+        let (decoded, i) = Block::ssz_decode(&ssz, 0).unwrap();
+        assert_eq!(decoded, b);
+        assert_eq!(i, ssz.len());
+    }
+
     #[test]
     pub fn test_block_min_ssz_length() {
         let b = Block::zero();
@@ -0,0 +1,63 @@
+/// The number of slots in a cycle, which is never zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CycleLength(u8);
+
+impl CycleLength {
+    /// Returns `None` if `cycle_length` is zero.
+    pub fn new(cycle_length: u8) -> Option<Self> {
+        if cycle_length == 0 {
+            None
+        } else {
+            Some(CycleLength(cycle_length))
+        }
+    }
+
+    pub fn get(self) -> u8 {
+        self.0
+    }
+
+    /// The maximum number of slots that an attestation slot may be behind the parent of the block
+    /// that contains it (i.e., `cycle_length + 1`).
+    pub fn max_parent_slot_distance(self) -> u64 {
+        /*
+         * Cannot overflow as cycle_length is at most u8::max_value().
+         */
+        u64::from(self.0) + 1
+    }
+}
+
+impl From<CycleLength> for u8 {
+    fn from(cycle_length: CycleLength) -> u8 {
+        cycle_length.0
+    }
+}
+
+impl From<CycleLength> for u64 {
+    fn from(cycle_length: CycleLength) -> u64 {
+        u64::from(cycle_length.0)
+    }
+}
+
+impl From<CycleLength> for usize {
+    fn from(cycle_length: CycleLength) -> usize {
+        usize::from(cycle_length.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_length_new() {
+        assert_eq!(CycleLength::new(0), None);
+        assert_eq!(CycleLength::new(1).map(u8::from), Some(1));
+        assert_eq!(CycleLength::new(64).map(CycleLength::get), Some(64));
+    }
+
+    #[test]
+    fn test_cycle_length_max_parent_slot_distance() {
+        assert_eq!(CycleLength::new(64).unwrap().max_parent_slot_distance(), 65);
+        assert_eq!(CycleLength::new(u8::max_value()).unwrap().max_parent_slot_distance(), 256);
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+use super::CycleLength;
+
+/// Parameters of the chain which may change at a hard fork, by the slot from which they apply.
+///
+/// Allows a historical attestation to be validated with the parameters in effect at its slot,
+/// rather than those in effect now.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochParams {
+    genesis_cycle_length: CycleLength,
+    /// Each change of the cycle length, by the first slot at which it applies.
+    cycle_length_changes: BTreeMap<u64, CycleLength>,
+}
+
+impl EpochParams {
+    /// Parameters which apply from genesis until the first change.
+    pub fn new(genesis_cycle_length: CycleLength) -> Self {
+        Self {
+            genesis_cycle_length,
+            cycle_length_changes: BTreeMap::new(),
+        }
+    }
+
+    /// Apply `cycle_length` from `slot` until the next change, if any.
+    pub fn set_cycle_length(&mut self, slot: u64, cycle_length: CycleLength) {
+        self.cycle_length_changes.insert(slot, cycle_length);
+    }
+
+    /// The cycle length in effect at `slot`.
+    pub fn cycle_length_at(&self, slot: u64) -> CycleLength {
+        self.cycle_length_changes.range(..=slot)
+            .next_back()
+            .map(|(_, cycle_length)| *cycle_length)
+            .unwrap_or(self.genesis_cycle_length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epoch_params_cycle_length_at() {
+        let cycle_length = |n| CycleLength::new(n).unwrap();
+
+        let mut params = EpochParams::new(cycle_length(64));
+        assert_eq!(params.cycle_length_at(0), cycle_length(64));
+        assert_eq!(params.cycle_length_at(1_000_000), cycle_length(64));
+
+        params.set_cycle_length(100, cycle_length(32));
+        params.set_cycle_length(200, cycle_length(16));
+        assert_eq!(params.cycle_length_at(99), cycle_length(64));
+        assert_eq!(params.cycle_length_at(100), cycle_length(32));
+        assert_eq!(params.cycle_length_at(199), cycle_length(32));
+        assert_eq!(params.cycle_length_at(200), cycle_length(16));
+        assert_eq!(params.cycle_length_at(1_000_000), cycle_length(16));
+    }
+}
@@ -0,0 +1,109 @@
+use std::fmt;
+use super::Hash256;
+
+/// The number of bytes in a Hash256.
+const HASH256_BYTES: usize = 32;
+
+#[derive(Debug, PartialEq)]
+pub enum Hash256ParseError {
+    /// The input was not exactly 32 bytes long. Holds the number of bytes supplied.
+    InvalidLength(usize),
+    /// The input contained a character which is not a hex digit, or an odd number of digits.
+    InvalidHex,
+}
+
+impl fmt::Display for Hash256ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Hash256ParseError::InvalidLength(len) =>
+                write!(f, "expected 32 byte hash, got {} bytes", len),
+            Hash256ParseError::InvalidHex =>
+                write!(f, "invalid hex string"),
+        }
+    }
+}
+
+/// Length-checked constructors for a Hash256, for bytes which come from the network or from JSON.
+///
+/// `Hash256::from_slice` (inherent to `H256`) panics if the slice is not 32 bytes and takes
+/// precedence over any trait method of the same name, hence `try_from_slice`.
+pub trait Hash256Parse: Sized {
+    /// Copy exactly 32 bytes into a hash.
+    fn try_from_slice(bytes: &[u8]) -> Result<Self, Hash256ParseError>;
+
+    /// Parse 64 hex digits, optionally prefixed with "0x", into a hash.
+    fn from_hex(s: &str) -> Result<Self, Hash256ParseError>;
+}
+
+impl Hash256Parse for Hash256 {
+    fn try_from_slice(bytes: &[u8]) -> Result<Self, Hash256ParseError> {
+        if bytes.len() != HASH256_BYTES {
+            return Err(Hash256ParseError::InvalidLength(bytes.len()));
+        }
+        Ok(Hash256::from_slice(bytes))
+    }
+
+    fn from_hex(s: &str) -> Result<Self, Hash256ParseError> {
+        let s = s.strip_prefix("0x").unwrap_or(s);
+        let bytes = s.as_bytes()
+            .chunks(2)
+            .map(|pair| match *pair {
+                [high, low] => Ok((hex_digit(high)? << 4) | hex_digit(low)?),
+                _ => Err(Hash256ParseError::InvalidHex),
+            })
+            .collect::<Result<Vec<u8>, Hash256ParseError>>()?;
+        Self::try_from_slice(&bytes)
+    }
+}
+
+/// Return the value of a single (ASCII) hex digit.
+fn hex_digit(c: u8) -> Result<u8, Hash256ParseError> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(Hash256ParseError::InvalidHex),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash256_zero() {
+        assert!(Hash256::zero().is_zero());
+        assert_eq!(Hash256::zero(), Hash256::try_from_slice(&[0; 32]).unwrap());
+
+        let mut bytes = [0; 32];
+        bytes[31] = 1;
+        assert!(!Hash256::try_from_slice(&bytes).unwrap().is_zero());
+    }
+
+    #[test]
+    fn test_hash256_try_from_slice() {
+        let bytes: Vec<u8> = (0..32).collect();
+        let hash = Hash256::try_from_slice(&bytes).unwrap();
+        assert_eq!(&hash[..], &bytes[..]);
+
+        assert_eq!(Hash256::try_from_slice(&bytes[..31]),
+                   Err(Hash256ParseError::InvalidLength(31)));
+        assert_eq!(Hash256::try_from_slice(&[0; 33]), Err(Hash256ParseError::InvalidLength(33)));
+        assert_eq!(Hash256::try_from_slice(&[]), Err(Hash256ParseError::InvalidLength(0)));
+    }
+
+    #[test]
+    fn test_hash256_from_hex() {
+        let hex = "000102030405060708090a0b0c0d0e0f101112131415161718191A1B1C1D1E1F";
+        let bytes: Vec<u8> = (0..32).collect();
+        assert_eq!(&Hash256::from_hex(hex).unwrap()[..], &bytes[..]);
+        assert_eq!(Hash256::from_hex(&format!("0x{}", hex)), Hash256::from_hex(hex));
+
+        assert_eq!(Hash256::from_hex(&hex[..62]), Err(Hash256ParseError::InvalidLength(31)));
+        assert_eq!(Hash256::from_hex(&format!("{}00", hex)),
+                   Err(Hash256ParseError::InvalidLength(33)));
+        assert_eq!(Hash256::from_hex(&hex[..63]), Err(Hash256ParseError::InvalidHex));
+        assert_eq!(Hash256::from_hex(&format!("zz{}", &hex[2..])),
+                   Err(Hash256ParseError::InvalidHex));
+    }
+}
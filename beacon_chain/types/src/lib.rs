@@ -1,16 +1,33 @@
 extern crate ethereum_types;
 extern crate bls;
 extern crate boolean_bitfield;
+extern crate hashing;
 extern crate ssz;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
 pub mod active_state;
+pub mod attestation_pool;
 pub mod attestation_record;
+pub mod attester_map;
 pub mod crystallized_state;
+pub mod cycle_length;
 pub mod chain_config;
 pub mod block;
 pub mod crosslink_record;
+pub mod epoch_params;
+pub mod hash256;
 pub mod shard_and_committee;
+pub mod shard_id;
 pub mod validator_record;
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
+#[cfg(feature = "serde")]
+mod serde_impls;
 
 use self::ethereum_types::{
     H256,
@@ -21,21 +38,35 @@ use self::boolean_bitfield::BooleanBitfield;
 use std::collections::HashMap;
 
 pub use active_state::ActiveState;
-pub use attestation_record::AttestationRecord;
+pub use attestation_pool::AttestationPool;
+pub use attestation_record::{
+    AggregationError,
+    AttestationRecord,
+    Checkpoint,
+    VoteTarget,
+};
+pub use attester_map::AttesterMap;
 pub use crystallized_state::CrystallizedState;
+pub use cycle_length::CycleLength;
 pub use chain_config::ChainConfig;
 pub use block::Block;
 pub use crosslink_record::CrosslinkRecord;
+pub use boolean_bitfield::RleDecodeError;
+pub use epoch_params::EpochParams;
+pub use hash256::{
+    Hash256Parse,
+    Hash256ParseError,
+};
 pub use shard_and_committee::ShardAndCommittee;
+pub use shard_id::ShardId;
 pub use validator_record::ValidatorRecord;
 
+/// A 32 byte hash. `H256` provides the canonical zero hash as `Hash256::zero()` and the null
+/// check as `is_zero()`.
 pub type Hash256 = H256;
 pub type Address = H160;
 pub type EthBalance = U256;
 pub type Bitfield = BooleanBitfield;
 
-/// Maps a (slot, shard_id) to attestation_indices.
-pub type AttesterMap = HashMap<(u64, u16), Vec<usize>>;
-
 /// Maps a slot to a block proposer.
 pub type ProposerMap = HashMap<u64, usize>;
@@ -0,0 +1,327 @@
+/*
+ * Serde support for AttestationRecord, enabled with the "serde" feature.
+ *
+ * Hashes and signatures are serialized as 0x-prefixed hex strings. The bitfield is
+ * serialized as its length and big-endian bytes so that any padding bits survive a
+ * round-trip.
+ */
+use std::fmt;
+use std::str;
+use super::serde::{
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+};
+use super::serde::de::{
+    self,
+    IgnoredAny,
+    MapAccess,
+    Visitor,
+};
+use super::serde::ser::SerializeStruct;
+use super::{
+    AttestationRecord,
+    Bitfield,
+    Hash256,
+    Hash256Parse,
+};
+use super::bls::AggregateSignature;
+
+const ATTESTATION_RECORD_FIELDS: &[&str] = &[
+    "slot",
+    "shard_id",
+    "oblique_parent_hashes",
+    "shard_block_hash",
+    "attester_bitfield",
+    "justified_slot",
+    "justified_block_hash",
+    "aggregate_sig",
+];
+
+const BITFIELD_FIELDS: &[&str] = &[
+    "len",
+    "bytes",
+];
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(2 + bytes.len() * 2);
+    s.push_str("0x");
+    for byte in bytes {
+        s.push_str(&format!("{:02x}", byte));
+    }
+    s
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    let s = if s.starts_with("0x") { &s[2..] } else { s };
+    if s.len() % 2 != 0 {
+        return Err("hex string has an odd length".to_string());
+    }
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let pair = str::from_utf8(pair).map_err(|e| e.to_string())?;
+            u8::from_str_radix(pair, 16).map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+fn hash_from_hex(s: &str) -> Result<Hash256, String> {
+    Hash256::from_hex(s).map_err(|e| e.to_string())
+}
+
+fn aggregate_sig_from_hex(s: &str) -> Result<AggregateSignature, String> {
+    let bytes = from_hex(s)?;
+    AggregateSignature::from_bytes(&bytes)
+        .map_err(|_| "invalid aggregate signature".to_string())
+}
+
+/// Serializes a Bitfield as its length and big-endian bytes.
+struct SerBitfield<'a>(&'a Bitfield);
+
+impl<'a> Serialize for SerBitfield<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let mut state = serializer.serialize_struct("Bitfield", BITFIELD_FIELDS.len())?;
+        state.serialize_field("len", &self.0.len())?;
+        state.serialize_field("bytes", &to_hex(&self.0.to_be_vec()))?;
+        state.end()
+    }
+}
+
+/// Deserializes a Bitfield from the format written by `SerBitfield`.
+struct DeBitfield(Bitfield);
+
+impl<'de> Deserialize<'de> for DeBitfield {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_struct("Bitfield", BITFIELD_FIELDS, BitfieldVisitor)
+    }
+}
+
+struct BitfieldVisitor;
+
+impl<'de> Visitor<'de> for BitfieldVisitor {
+    type Value = DeBitfield;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct Bitfield")
+    }
+
+    fn visit_map<V>(self, mut map: V) -> Result<DeBitfield, V::Error>
+        where V: MapAccess<'de>
+    {
+        let mut len: Option<usize> = None;
+        let mut bytes: Option<Vec<u8>> = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "len" => len = Some(map.next_value()?),
+                "bytes" => {
+                    let hex: String = map.next_value()?;
+                    bytes = Some(from_hex(&hex).map_err(de::Error::custom)?);
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                    return Err(de::Error::unknown_field(&key, BITFIELD_FIELDS));
+                }
+            }
+        }
+
+        let len = len.ok_or_else(|| de::Error::missing_field("len"))?;
+        let mut bytes = bytes.ok_or_else(|| de::Error::missing_field("bytes"))?;
+        /*
+         * The bytes are serialized big-endian, however the bitfield stores them little-endian.
+         */
+        bytes.reverse();
+        Bitfield::from_raw_parts(bytes, len)
+            .map(DeBitfield)
+            .ok_or_else(|| de::Error::custom("bitfield length exceeds bytes"))
+    }
+}
+
+impl Serialize for AttestationRecord {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let oblique_parent_hashes: Vec<String> = self.oblique_parent_hashes.iter()
+            .map(|hash| to_hex(hash))
+            .collect();
+
+        let mut state = serializer.serialize_struct(
+            "AttestationRecord",
+            ATTESTATION_RECORD_FIELDS.len())?;
+        state.serialize_field("slot", &self.slot)?;
+        state.serialize_field("shard_id", &self.shard_id)?;
+        state.serialize_field("oblique_parent_hashes", &oblique_parent_hashes)?;
+        state.serialize_field("shard_block_hash", &to_hex(&self.shard_block_hash))?;
+        state.serialize_field("attester_bitfield", &SerBitfield(&self.attester_bitfield))?;
+        state.serialize_field("justified_slot", &self.justified_slot)?;
+        state.serialize_field("justified_block_hash", &to_hex(&self.justified_block_hash))?;
+        state.serialize_field("aggregate_sig", &to_hex(&self.aggregate_sig.to_bytes()))?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for AttestationRecord {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_struct(
+            "AttestationRecord",
+            ATTESTATION_RECORD_FIELDS,
+            AttestationRecordVisitor)
+    }
+}
+
+struct AttestationRecordVisitor;
+
+impl<'de> Visitor<'de> for AttestationRecordVisitor {
+    type Value = AttestationRecord;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct AttestationRecord")
+    }
+
+    fn visit_map<V>(self, mut map: V) -> Result<AttestationRecord, V::Error>
+        where V: MapAccess<'de>
+    {
+        let mut slot = None;
+        let mut shard_id = None;
+        let mut oblique_parent_hashes = None;
+        let mut shard_block_hash = None;
+        let mut attester_bitfield = None;
+        let mut justified_slot = None;
+        let mut justified_block_hash = None;
+        let mut aggregate_sig = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "slot" => slot = Some(map.next_value()?),
+                "shard_id" => shard_id = Some(map.next_value()?),
+                "oblique_parent_hashes" => {
+                    let hex: Vec<String> = map.next_value()?;
+                    let hashes = hex.iter()
+                        .map(|s| hash_from_hex(s))
+                        .collect::<Result<Vec<Hash256>, String>>()
+                        .map_err(de::Error::custom)?;
+                    oblique_parent_hashes = Some(hashes);
+                }
+                "shard_block_hash" => {
+                    let hex: String = map.next_value()?;
+                    shard_block_hash = Some(hash_from_hex(&hex).map_err(de::Error::custom)?);
+                }
+                "attester_bitfield" => {
+                    let bitfield: DeBitfield = map.next_value()?;
+                    attester_bitfield = Some(bitfield.0);
+                }
+                "justified_slot" => justified_slot = Some(map.next_value()?),
+                "justified_block_hash" => {
+                    let hex: String = map.next_value()?;
+                    justified_block_hash = Some(hash_from_hex(&hex).map_err(de::Error::custom)?);
+                }
+                "aggregate_sig" => {
+                    let hex: String = map.next_value()?;
+                    aggregate_sig = Some(aggregate_sig_from_hex(&hex).map_err(de::Error::custom)?);
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                    return Err(de::Error::unknown_field(&key, ATTESTATION_RECORD_FIELDS));
+                }
+            }
+        }
+
+        Ok(AttestationRecord {
+            slot: slot
+                .ok_or_else(|| de::Error::missing_field("slot"))?,
+            shard_id: shard_id
+                .ok_or_else(|| de::Error::missing_field("shard_id"))?,
+            oblique_parent_hashes: oblique_parent_hashes
+                .ok_or_else(|| de::Error::missing_field("oblique_parent_hashes"))?,
+            shard_block_hash: shard_block_hash
+                .ok_or_else(|| de::Error::missing_field("shard_block_hash"))?,
+            attester_bitfield: attester_bitfield
+                .ok_or_else(|| de::Error::missing_field("attester_bitfield"))?,
+            justified_slot: justified_slot
+                .ok_or_else(|| de::Error::missing_field("justified_slot"))?,
+            justified_block_hash: justified_block_hash
+                .ok_or_else(|| de::Error::missing_field("justified_block_hash"))?,
+            aggregate_sig: aggregate_sig
+                .ok_or_else(|| de::Error::missing_field("aggregate_sig"))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::serde_json;
+    use super::super::bls::{
+        Keypair,
+        Signature,
+    };
+
+    fn record_with_bitfield(attester_bitfield: Bitfield) -> AttestationRecord {
+        let keypair = Keypair::random();
+        let mut aggregate_sig = AggregateSignature::new();
+        aggregate_sig.add(&Signature::new(&[42], &keypair.sk));
+
+        AttestationRecord {
+            slot: 7,
+            shard_id: 9,
+            oblique_parent_hashes: vec![Hash256::from(&vec![14; 32][..])],
+            shard_block_hash: Hash256::from(&vec![15; 32][..]),
+            attester_bitfield,
+            justified_slot: 19,
+            justified_block_hash: Hash256::from(&vec![16; 32][..]),
+            aggregate_sig,
+        }
+    }
+
+    #[test]
+    fn test_attestation_record_serde_round_trip() {
+        let mut bitfield = Bitfield::new();
+        bitfield.set_bit(0, true);
+        bitfield.set_bit(9, true);
+        let original = record_with_bitfield(bitfield);
+
+        let json = serde_json::to_string(&original).unwrap();
+        let decoded: AttestationRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, original);
+    }
+
+    #[test]
+    fn test_attestation_record_serde_round_trip_padding_bits() {
+        /*
+         * A bit which was set and then unset extends the length of the bitfield without
+         * changing its bytes. This must survive a round-trip.
+         */
+        let mut bitfield = Bitfield::new();
+        bitfield.set_bit(2, true);
+        bitfield.set_bit(14, true);
+        bitfield.set_bit(14, false);
+        let original = record_with_bitfield(bitfield);
+
+        let json = serde_json::to_string(&original).unwrap();
+        let decoded: AttestationRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.attester_bitfield.len(), 15);
+        assert_eq!(decoded, original);
+    }
+
+    #[test]
+    fn test_attestation_record_serde_hex() {
+        let original = record_with_bitfield(Bitfield::new());
+
+        let json = serde_json::to_value(&original).unwrap();
+        assert_eq!(json["shard_block_hash"], format!("0x{}", "0f".repeat(32)));
+        assert_eq!(json["attester_bitfield"]["bytes"], "0x00");
+
+        let mut json = json;
+        json["shard_block_hash"] = serde_json::Value::from("0x0f");
+        let result: Result<AttestationRecord, _> = serde_json::from_value(json);
+        assert!(result.is_err());
+    }
+}
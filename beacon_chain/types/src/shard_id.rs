@@ -0,0 +1,43 @@
+/// The id of a shard, which is always less than the number of shards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShardId(u16);
+
+impl ShardId {
+    /// Returns `None` if `shard_id` is not less than `shard_count`.
+    pub fn new(shard_id: u16, shard_count: u16) -> Option<Self> {
+        if shard_id < shard_count {
+            Some(ShardId(shard_id))
+        } else {
+            None
+        }
+    }
+
+    pub fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl From<ShardId> for u16 {
+    fn from(shard_id: ShardId) -> u16 {
+        shard_id.0
+    }
+}
+
+impl From<ShardId> for usize {
+    fn from(shard_id: ShardId) -> usize {
+        usize::from(shard_id.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_id_new() {
+        assert_eq!(ShardId::new(0, 0), None);
+        assert_eq!(ShardId::new(0, 64).map(u16::from), Some(0));
+        assert_eq!(ShardId::new(63, 64).map(ShardId::get), Some(63));
+        assert_eq!(ShardId::new(64, 64), None);
+    }
+}
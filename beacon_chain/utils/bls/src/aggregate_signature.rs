@@ -0,0 +1,111 @@
+use super::bls_aggregates::AggregateSignature as RawAggregateSignature;
+use super::{
+    AggregatePublicKey,
+    Signature,
+};
+
+/// The bytes given to `AggregateSignature::from_bytes` are not a valid aggregate signature.
+#[derive(Debug, PartialEq)]
+pub struct DecodeError;
+
+/// An aggregate of BLS signatures across a single message.
+///
+/// Wraps the aggregate signature of the underlying BLS library so that the rest of the client
+/// depends only upon this interface.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AggregateSignature(RawAggregateSignature);
+
+impl AggregateSignature {
+    /// Create an aggregate of no signatures.
+    pub fn new() -> Self {
+        AggregateSignature(RawAggregateSignature::new())
+    }
+
+    /// Add a single signature to the aggregate.
+    pub fn add(&mut self, signature: &Signature) {
+        self.0.add(signature)
+    }
+
+    /// Add all of the signatures of another aggregate to this one.
+    ///
+    /// An aggregate signature shares its encoding with a single signature, allowing the other
+    /// aggregate to be added as if it were one signature.
+    pub fn add_aggregate(&mut self, other: &AggregateSignature) -> Result<(), DecodeError> {
+        let signature = Signature::from_bytes(&other.to_bytes())
+            .map_err(|_| DecodeError)?;
+        self.add(&signature);
+        Ok(())
+    }
+
+    /// Verify the aggregate against a message and the aggregate of the public keys of the
+    /// signers.
+    pub fn verify(&self, message: &[u8], aggregate_public_key: &AggregatePublicKey) -> bool {
+        self.0.verify(message, aggregate_public_key)
+    }
+
+    /// Decode an aggregate signature of exactly `BLS_AGG_SIG_BYTE_SIZE` bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        RawAggregateSignature::from_bytes(bytes)
+            .map(AggregateSignature)
+            .map_err(|_| DecodeError)
+    }
+
+    /// Encode the aggregate signature as `BLS_AGG_SIG_BYTE_SIZE` bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.as_bytes()
+    }
+}
+
+impl Default for AggregateSignature {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{
+        Keypair,
+        BLS_AGG_SIG_BYTE_SIZE,
+    };
+
+    #[test]
+    fn test_aggregate_signature_add() {
+        let message = b"message";
+        let keypairs: Vec<Keypair> = (0..3).map(|_| Keypair::random()).collect();
+
+        let mut aggregate_sig = AggregateSignature::new();
+        let mut aggregate_public_key = AggregatePublicKey::new();
+        for keypair in &keypairs {
+            aggregate_sig.add(&Signature::new(message, &keypair.sk));
+            aggregate_public_key.add(&keypair.pk);
+        }
+        assert!(aggregate_sig.verify(message, &aggregate_public_key));
+        assert!(!aggregate_sig.verify(b"other message", &aggregate_public_key));
+
+        /*
+         * Adding two partial aggregates is equivalent to adding each signature.
+         */
+        let mut first = AggregateSignature::new();
+        first.add(&Signature::new(message, &keypairs[0].sk));
+        let mut second = AggregateSignature::new();
+        second.add(&Signature::new(message, &keypairs[1].sk));
+        second.add(&Signature::new(message, &keypairs[2].sk));
+        first.add_aggregate(&second).unwrap();
+        assert!(first.verify(message, &aggregate_public_key));
+    }
+
+    #[test]
+    fn test_aggregate_signature_bytes() {
+        let keypair = Keypair::random();
+        let mut aggregate_sig = AggregateSignature::new();
+        aggregate_sig.add(&Signature::new(b"message", &keypair.sk));
+
+        let bytes = aggregate_sig.to_bytes();
+        assert_eq!(bytes.len(), BLS_AGG_SIG_BYTE_SIZE);
+        assert_eq!(AggregateSignature::from_bytes(&bytes), Ok(aggregate_sig));
+
+        assert_eq!(AggregateSignature::from_bytes(&bytes[1..]), Err(DecodeError));
+    }
+}
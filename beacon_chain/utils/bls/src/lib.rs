@@ -1,6 +1,11 @@
 extern crate bls_aggregates;
 
-pub use self::bls_aggregates::AggregateSignature;
+mod aggregate_signature;
+
+pub use self::aggregate_signature::{
+    AggregateSignature,
+    DecodeError,
+};
 pub use self::bls_aggregates::AggregatePublicKey;
 pub use self::bls_aggregates::Signature;
 pub use self::bls_aggregates::Keypair;
@@ -8,8 +8,12 @@
  */
 extern crate ssz;
 
+pub mod rle;
+
 use std::cmp::max;
 
+pub use rle::RleDecodeError;
+
 #[derive(Eq, Clone, Default, Debug)]
 pub struct BooleanBitfield{
     len: usize,
@@ -35,6 +39,44 @@ impl BooleanBitfield {
         }
     }
 
+    /// Create a bitfield from an underlying byte array (as returned by
+    /// `to_vec()`) and a length (as returned by `len()`).
+    ///
+    /// Returns `None` if the byte array is empty or too short to hold
+    /// `len` bits.
+    pub fn from_raw_parts(vec: Vec<u8>, len: usize) -> Option<Self> {
+        if vec.is_empty() || len > vec.len() * 8 {
+            None
+        } else {
+            Some(Self {
+                len,
+                vec
+            })
+        }
+    }
+
+    /// Create a bitfield of `committee_size` bits in which exactly the bits at `indices` are set
+    /// (i.e., the inverse of `iter_set_bits`).
+    ///
+    /// The underlying byte array is the fewest bytes which hold `committee_size` bits (at least
+    /// one byte), zero-padded, so no bit beyond `committee_size` is set.
+    ///
+    /// # Panics
+    ///
+    /// If an index is not less than `committee_size`.
+    pub fn from_indices(indices: &[usize], committee_size: usize) -> Self {
+        let mut bitfield = Self {
+            len: committee_size,
+            vec: vec![0; committee_size.saturating_sub(1) / 8 + 1]
+        };
+        for &i in indices {
+            assert!(i < committee_size,
+                    "index {} is outside a committee of {}", i, committee_size);
+            bitfield.set_bit(i, true);
+        }
+        bitfield
+    }
+
     /// Read the value of a bit.
     ///
     /// Will return `true` if the bit has been set to `true`
@@ -50,6 +92,32 @@ impl BooleanBitfield {
         }
     }
 
+    /// Read the value of a bit. Equivalent to `get_bit`.
+    pub fn get(&self, i: usize) -> bool {
+        self.get_bit(i)
+    }
+
+    /// Iterate, in ascending order, through the index of each bit
+    /// which is set to `true`.
+    ///
+    /// Bit `i` is the bit read by `get_bit(i)`, so an index maps
+    /// directly to a position in a list (e.g., attestation indices).
+    pub fn iter_set_bits<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        (0..self.vec.len() * 8).filter(move |i| self.get_bit(*i))
+    }
+
+    /// Return the index of the highest bit which is set to `true`,
+    /// or `None` if no bits are set.
+    ///
+    /// Note: this is distinct from `len()`, which also counts bits
+    /// that have been set and then unset.
+    pub fn highest_set_bit(&self) -> Option<usize> {
+        match BooleanBitfield::compute_length(&self.vec) {
+            0 => None,
+            len => Some(len - 1),
+        }
+    }
+
     /// Set the value of a bit.
     ///
     /// If this bit is larger than the length of the underlying byte
@@ -317,6 +385,94 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_bitfield_iter_set_bits() {
+        /*
+         * Empty bitfield
+         */
+        let b = BooleanBitfield::new();
+        assert_eq!(b.iter_set_bits().count(), 0);
+        assert_eq!(b.highest_set_bit(), None);
+
+        /*
+         * Fully-set bitfield
+         */
+        let b = BooleanBitfield::from(&[255, 255][..]);
+        assert_eq!(b.iter_set_bits().collect::<Vec<usize>>(), (0..16).collect::<Vec<usize>>());
+        assert_eq!(b.highest_set_bit(), Some(15));
+        assert!((0..16).all(|i| b.get(i)));
+
+        /*
+         * Bits are indexed from the end of the big-endian encoding.
+         */
+        let b = BooleanBitfield::from(&[1, 128][..]);
+        assert_eq!(b.iter_set_bits().collect::<Vec<usize>>(), vec![7, 8]);
+        assert_eq!(b.highest_set_bit(), Some(8));
+    }
+
+    #[test]
+    fn test_bitfield_highest_set_bit_ignores_padding() {
+        /*
+         * A bit which is set and then unset extends the length, but
+         * not the highest set bit.
+         */
+        let mut b = BooleanBitfield::new();
+        b.set_bit(2, true);
+        b.set_bit(12, true);
+        b.set_bit(12, false);
+        assert_eq!(b.len(), 13);
+        assert_eq!(b.num_bytes(), 2);
+        assert_eq!(b.highest_set_bit(), Some(2));
+        assert_eq!(b.iter_set_bits().collect::<Vec<usize>>(), vec![2]);
+        assert_eq!(b.get(12), false);
+    }
+
+    #[test]
+    fn test_bitfield_from_raw_parts() {
+        let mut b = BooleanBitfield::new();
+        b.set_bit(3, true);
+        b.set_bit(12, true);
+        b.set_bit(12, false);
+
+        let rebuilt = BooleanBitfield::from_raw_parts(b.to_vec(), b.len())
+            .unwrap();
+        assert_eq!(rebuilt, b);
+
+        assert_eq!(BooleanBitfield::from_raw_parts(vec![], 0), None);
+        assert_eq!(BooleanBitfield::from_raw_parts(vec![0, 0], 17), None);
+    }
+
+    #[test]
+    fn test_bitfield_from_indices() {
+        for &(indices, committee_size, num_bytes) in &[
+            (&[][..], 0, 1),
+            (&[0][..], 1, 1),
+            (&[0, 7][..], 8, 1),
+            (&[3, 8][..], 9, 2),
+            (&[1, 2, 17, 22][..], 23, 3),
+        ] {
+            let b = BooleanBitfield::from_indices(indices, committee_size);
+            assert_eq!(b.iter_set_bits().collect::<Vec<usize>>(), indices.to_vec());
+            assert_eq!(b.len(), committee_size);
+            assert_eq!(b.num_bytes(), num_bytes);
+            if let Some(i) = b.highest_set_bit() {
+                assert!(i < committee_size);
+            }
+        }
+
+        /*
+         * Indices may be unordered or repeated.
+         */
+        let b = BooleanBitfield::from_indices(&[5, 1, 5], 6);
+        assert_eq!(b.iter_set_bits().collect::<Vec<usize>>(), vec![1, 5]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bitfield_from_indices_outside_committee() {
+        BooleanBitfield::from_indices(&[8], 8);
+    }
+
     #[test]
     fn test_bitfield_num_true_bits() {
         let mut b = BooleanBitfield::new();
@@ -0,0 +1,196 @@
+/*
+ * Run-length encoding of a bitfield, for peers which optimize for bandwidth.
+ *
+ * An encoded bitfield is a sequence of zero or more runs, without any header or terminator. Each
+ * run is:
+ *
+ * - One byte, the value of every bit in the run: `0x00` for false or `0x01` for true.
+ * - The number of bits in the run, which must be at least one, as an unsigned LEB128 varint of at
+ *   most `MAX_RLE_LENGTH_BYTES` bytes. That is, seven bits per byte with the least significant
+ *   group first, and the high bit of each byte set if and only if another byte follows.
+ *   Non-minimal varints (e.g., `0x81 0x00` for one) are accepted.
+ *
+ * The runs describe the bits in ascending order of index, starting from bit zero, and the length
+ * of the bitfield (i.e., `len()`) is the sum of the lengths of the runs. Adjacent runs may have
+ * the same value. The length may not exceed `MAX_RLE_BITS`.
+ *
+ * For example, `[0x00, 0x03, 0x01, 0x82, 0x01]` is three false bits followed by 130 true bits.
+ */
+use super::BooleanBitfield;
+
+/// The maximum length of a run-length encoded bitfield, in bits.
+pub const MAX_RLE_BITS: usize = 1 << 24;
+
+/// The maximum number of bytes in the varint length of a single run.
+pub const MAX_RLE_LENGTH_BYTES: usize = 4;
+
+#[derive(Debug, PartialEq)]
+pub enum RleDecodeError {
+    /// The input ends part way through a run.
+    TooShort,
+    /// The value byte of a run is neither zero nor one. Holds the byte.
+    InvalidBitValue(u8),
+    /// The length of a run is zero.
+    EmptyRun,
+    /// The length of a run is encoded in more than `MAX_RLE_LENGTH_BYTES` bytes.
+    LengthTooLong,
+    /// The sum of the lengths of the runs exceeds `MAX_RLE_BITS`.
+    TooManyBits,
+}
+
+impl BooleanBitfield {
+    /// Decode a run-length encoded bitfield (see the `rle` module) into the standard
+    /// representation.
+    pub fn from_rle(bytes: &[u8]) -> Result<Self, RleDecodeError> {
+        let mut bitfield = BooleanBitfield::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let value = match bytes[i] {
+                0 => false,
+                1 => true,
+                b => return Err(RleDecodeError::InvalidBitValue(b)),
+            };
+            let (run, read) = decode_length(&bytes[i + 1..])?;
+            i += 1 + read;
+
+            if run == 0 {
+                return Err(RleDecodeError::EmptyRun);
+            }
+            let start = bitfield.len();
+            let end = start.checked_add(run)
+                .filter(|end| *end <= MAX_RLE_BITS)
+                .ok_or(RleDecodeError::TooManyBits)?;
+            if value {
+                for bit in start..end {
+                    bitfield.set_bit(bit, true);
+                }
+            } else {
+                bitfield.set_bit(end - 1, false);
+            }
+        }
+        Ok(bitfield)
+    }
+
+    /// Run-length encode the first `len()` bits of the bitfield (see the `rle` module).
+    pub fn to_rle(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut start = 0;
+        while start < self.len {
+            let value = self.get_bit(start);
+            let end = (start..self.len)
+                .find(|i| self.get_bit(*i) != value)
+                .unwrap_or(self.len);
+            bytes.push(value as u8);
+            encode_length(end - start, &mut bytes);
+            start = end;
+        }
+        bytes
+    }
+}
+
+/// Decode a varint from the start of `bytes`, returning its value and the number of bytes read.
+fn decode_length(bytes: &[u8]) -> Result<(usize, usize), RleDecodeError> {
+    let mut length = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        if i == MAX_RLE_LENGTH_BYTES {
+            return Err(RleDecodeError::LengthTooLong);
+        }
+        length |= usize::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((length, i + 1));
+        }
+    }
+    Err(RleDecodeError::TooShort)
+}
+
+fn encode_length(mut length: usize, bytes: &mut Vec<u8>) {
+    while length >= 0x80 {
+        bytes.push((length & 0x7f) as u8 | 0x80);
+        length >>= 7;
+    }
+    bytes.push(length as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rle_encoding() {
+        let mut b = BooleanBitfield::new();
+        b.set_bit(1, true);
+        b.set_bit(2, true);
+        b.set_bit(4, false);
+        assert_eq!(b.to_rle(), vec![0, 1, 1, 2, 0, 2]);
+
+        let b = BooleanBitfield::from_rle(&[0x00, 0x03, 0x01, 0x82, 0x01]).unwrap();
+        assert_eq!(b.len(), 133);
+        assert_eq!(b.iter_set_bits().collect::<Vec<usize>>(), (3..133).collect::<Vec<usize>>());
+
+        /*
+         * Adjacent runs of the same value and non-minimal lengths are accepted.
+         */
+        let b = BooleanBitfield::from_rle(&[0x01, 0x81, 0x00, 0x01, 0x02]).unwrap();
+        assert_eq!(b.len(), 3);
+        assert_eq!(b.num_true_bits(), 3);
+    }
+
+    #[test]
+    fn test_rle_round_trip() {
+        let mut bitfields = vec![BooleanBitfield::new()];
+
+        let mut b = BooleanBitfield::new();
+        b.set_bit(0, true);
+        bitfields.push(b);
+
+        /*
+         * Trailing false bits count towards the length.
+         */
+        let mut b = BooleanBitfield::new();
+        b.set_bit(3, true);
+        b.set_bit(20, false);
+        bitfields.push(b);
+
+        /*
+         * Runs with multi-byte lengths.
+         */
+        let mut b = BooleanBitfield::new();
+        for i in (0..5000).filter(|i| i % 1000 < 300) {
+            b.set_bit(i, true);
+        }
+        b.set_bit(70_000, true);
+        bitfields.push(b);
+
+        for b in bitfields {
+            assert_eq!(BooleanBitfield::from_rle(&b.to_rle()), Ok(b));
+        }
+    }
+
+    #[test]
+    fn test_rle_malformed() {
+        assert_eq!(BooleanBitfield::from_rle(&[]), Ok(BooleanBitfield::new()));
+
+        assert_eq!(BooleanBitfield::from_rle(&[1]), Err(RleDecodeError::TooShort));
+        assert_eq!(BooleanBitfield::from_rle(&[1, 0x81]), Err(RleDecodeError::TooShort));
+        assert_eq!(BooleanBitfield::from_rle(&[1, 1, 0]), Err(RleDecodeError::TooShort));
+        assert_eq!(BooleanBitfield::from_rle(&[2, 1]), Err(RleDecodeError::InvalidBitValue(2)));
+        assert_eq!(BooleanBitfield::from_rle(&[1, 1, 255, 1]),
+                   Err(RleDecodeError::InvalidBitValue(255)));
+        assert_eq!(BooleanBitfield::from_rle(&[0, 0]), Err(RleDecodeError::EmptyRun));
+        assert_eq!(BooleanBitfield::from_rle(&[0, 0x80, 0x80, 0x80, 0x80, 0x01]),
+                   Err(RleDecodeError::LengthTooLong));
+
+        /*
+         * The limit applies to the total length, not only to each run.
+         */
+        let mut bytes = vec![0];
+        encode_length(MAX_RLE_BITS + 1, &mut bytes);
+        assert_eq!(BooleanBitfield::from_rle(&bytes), Err(RleDecodeError::TooManyBits));
+
+        let mut bytes = vec![0];
+        encode_length(MAX_RLE_BITS, &mut bytes);
+        assert_eq!(BooleanBitfield::from_rle(&bytes).map(|b| b.len()), Ok(MAX_RLE_BITS));
+        bytes.extend_from_slice(&[1, 1]);
+        assert_eq!(BooleanBitfield::from_rle(&bytes), Err(RleDecodeError::TooManyBits));
+    }
+}
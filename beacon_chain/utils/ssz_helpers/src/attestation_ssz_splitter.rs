@@ -51,6 +51,33 @@ pub fn split_one_attestation(full_ssz: &[u8], index: usize)
     Ok((&full_ssz[index..(index + len)], index + len))
 }
 
+/// Given the ssz of exactly one AttestationRecord, return a slice pointing to the bytes of its
+/// aggregate signature (without the length prefix).
+///
+/// The signature bytes are not decoded, so they may be of any length.
+pub fn split_attestation_signature(attestation_ssz: &[u8])
+    -> Result<&[u8], AttestationSplitError>
+{
+    let hashes_len = length_at(attestation_ssz, 10)?;
+    let bitfield_len = length_at(attestation_ssz, hashes_len + 46)?;
+    let sig_len_index = hashes_len + bitfield_len + 90;
+    let sig_len = length_at(attestation_ssz, sig_len_index)?;
+
+    let sig_index = sig_len_index + LENGTH_BYTES;
+    attestation_ssz.get(sig_index..sig_index + sig_len)
+        .ok_or(AttestationSplitError::TooShort)
+}
+
+/// Decode the length prefix at `index`, checking that it is within `ssz`.
+fn length_at(ssz: &[u8], index: usize)
+    -> Result<usize, AttestationSplitError>
+{
+    let length_bytes = ssz.get(index..index + LENGTH_BYTES)
+        .ok_or(AttestationSplitError::TooShort)?;
+    decode_length(length_bytes, 0, LENGTH_BYTES)
+        .map_err(|_| AttestationSplitError::TooShort)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,5 +162,22 @@ mod tests {
         let ssz = &ssz[0..ssz.len() - 1];
         assert!(split_all_attestations(&ssz, 0).is_err());
     }
+
+    #[test]
+    fn test_attestation_signature_split() {
+        let a = get_two_records()[0].clone();
+        let mut ssz_stream = SszStream::new();
+        ssz_stream.append(&a);
+        let ssz = ssz_stream.drain();
+
+        let sig_ssz = split_attestation_signature(&ssz).unwrap();
+        assert_eq!(sig_ssz, &a.aggregate_sig.to_bytes()[..]);
+        assert_eq!(&ssz[ssz.len() - sig_ssz.len()..], sig_ssz);
+
+        assert_eq!(split_attestation_signature(&ssz[0..ssz.len() - 1]),
+                   Err(AttestationSplitError::TooShort));
+        assert_eq!(split_attestation_signature(&ssz[0..20]),
+                   Err(AttestationSplitError::TooShort));
+    }
 }
 
@@ -0,0 +1,32 @@
+use super::bls::PublicKey;
+use super::db::DBError;
+use super::db::stores::ValidatorStoreError;
+use super::futures::Future;
+use super::types::Hash256;
+
+/// A boxed future which resolves to `T` or fails with a database error.
+pub type DBFuture<T> = Box<dyn Future<Item = T, Error = DBError> + Send>;
+
+/// An asynchronous interface to the blocks and validators required to validate an attestation.
+///
+/// Allows `validate_attestation_async` to await database reads rather than blocking the runtime
+/// upon them. Each method is equivalent to the method of the same name on `BlockStore` or
+/// `ValidatorStore`.
+pub trait AsyncClientDB: Send + Sync {
+    /// Resolve to true if a block with the given hash is known.
+    fn block_exists(&self, hash: &Hash256) -> DBFuture<bool>;
+
+    /// Resolve to the slot of the block with the given hash, if it is known.
+    fn get_block_slot(&self, hash: &Hash256) -> DBFuture<Option<u64>>;
+
+    /// Resolve to the hash of the block at `slot` in the chain of the block with `head_hash`, if
+    /// there is one.
+    fn block_hash_at_slot(&self, head_hash: &Hash256, slot: u64) -> DBFuture<Option<Vec<u8>>>;
+
+    /// Resolve to true if the validator at `index` was active at `slot`.
+    fn is_active_at_slot(&self, index: usize, slot: u64) -> DBFuture<bool>;
+
+    /// Resolve to the public key of the validator at `index`, if it is known.
+    fn get_public_key_by_index(&self, index: usize)
+        -> Box<dyn Future<Item = Option<PublicKey>, Error = ValidatorStoreError> + Send>;
+}
@@ -0,0 +1,119 @@
+use super::attestation_validation::ValidatedAttestation;
+use super::types::AttestationRecord;
+
+/// The number of bytes of a single oblique parent hash.
+const OBLIQUE_HASH_BYTES: usize = 32;
+
+/// Return the total number of oblique parent hashes across each of `records`.
+pub fn total_oblique_hashes(records: &[AttestationRecord]) -> usize {
+    records.iter()
+        .map(|a| a.oblique_parent_hashes.len())
+        .sum()
+}
+
+/// Selects the attestations to be included in a block such that the oblique parent hashes of
+/// those attestations occupy no more than some number of bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockAttestationBudget {
+    /// The maximum number of bytes of oblique parent hashes across all selected attestations.
+    pub max_oblique_bytes: usize,
+}
+
+impl BlockAttestationBudget {
+    pub fn new(max_oblique_bytes: usize) -> Self {
+        Self {
+            max_oblique_bytes,
+        }
+    }
+
+    /// Greedily select from `attestations` in order of their priority for inclusion in a block
+    /// (i.e., most voters first, see `ValidatedAttestation`), skipping any attestation which would
+    /// exceed the budget.
+    ///
+    /// The selected attestations are returned in the order in which they were selected. An
+    /// attestation without oblique parent hashes is always selected.
+    pub fn select(&self, mut attestations: Vec<ValidatedAttestation>)
+        -> Vec<ValidatedAttestation>
+    {
+        attestations.sort_by(|a, b| b.cmp(a));
+
+        let mut remaining = self.max_oblique_bytes;
+        attestations.into_iter()
+            .filter(|a| {
+                let bytes = a.record.oblique_parent_hashes.len() * OBLIQUE_HASH_BYTES;
+                match remaining.checked_sub(bytes) {
+                    Some(r) => {
+                        remaining = r;
+                        true
+                    }
+                    None => false,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::Hash256;
+    use std::collections::HashSet;
+
+    fn attestation(slot: u64, oblique_hashes: usize, voters: usize) -> ValidatedAttestation {
+        let mut record = AttestationRecord::zero();
+        record.slot = slot;
+        record.oblique_parent_hashes = vec![Hash256::zero(); oblique_hashes];
+        ValidatedAttestation::new(record, (0..voters).collect::<HashSet<usize>>())
+    }
+
+    #[test]
+    fn test_total_oblique_hashes() {
+        let records: Vec<AttestationRecord> = [3, 0, 2].iter()
+            .map(|&n| attestation(0, n, 1).record)
+            .collect();
+        assert_eq!(total_oblique_hashes(&records), 5);
+        assert_eq!(total_oblique_hashes(&[]), 0);
+    }
+
+    #[test]
+    fn test_block_attestation_budget() {
+        let attestations = vec![
+            attestation(1, 2, 5),
+            attestation(2, 3, 10),
+            attestation(3, 0, 1),
+            attestation(4, 1, 4),
+        ];
+
+        /*
+         * The attestation with the most voters uses three hashes of the budget of four, so the
+         * attestation with two hashes is skipped in favour of the one with fewer voters but a
+         * single hash.
+         */
+        let budget = BlockAttestationBudget::new(4 * OBLIQUE_HASH_BYTES);
+        let selected = budget.select(attestations.clone());
+        let slots: Vec<u64> = selected.iter().map(|a| a.record.slot).collect();
+        assert_eq!(slots, vec![2, 4, 3]);
+
+        let records: Vec<AttestationRecord> = selected.into_iter().map(|a| a.record).collect();
+        assert!(total_oblique_hashes(&records) * OBLIQUE_HASH_BYTES <= budget.max_oblique_bytes);
+
+        /*
+         * A zero budget selects only the attestation without oblique parent hashes.
+         */
+        let selected = BlockAttestationBudget::new(0).select(attestations.clone());
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].record.slot, 3);
+
+        /*
+         * A budget of exactly every hash selects every attestation, one byte less skips the last
+         * attestation with oblique parent hashes.
+         */
+        let selected = BlockAttestationBudget::new(6 * OBLIQUE_HASH_BYTES)
+            .select(attestations.clone());
+        let slots: Vec<u64> = selected.iter().map(|a| a.record.slot).collect();
+        assert_eq!(slots, vec![2, 1, 4, 3]);
+        let selected = BlockAttestationBudget::new(6 * OBLIQUE_HASH_BYTES - 1).select(attestations);
+        let slots: Vec<u64> = selected.iter().map(|a| a.record.slot).collect();
+        assert_eq!(slots, vec![2, 1, 3]);
+    }
+}
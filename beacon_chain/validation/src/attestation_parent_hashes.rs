@@ -1,10 +1,21 @@
-use super::types::Hash256;
+use super::parent_hash_provider::ParentHashProvider;
+use super::structural_checks::max_oblique_hashes;
+use super::types::{
+    CycleLength,
+    Hash256,
+};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum ParentHashesError {
+    /// The current hashes are neither two cycles long nor (in the first two cycles of the chain)
+    /// one hash per slot since genesis, or do not cover the required range.
     BadCurrentHashes,
+    /// There are more oblique hashes than the cycle length, or an oblique hash would replace the
+    /// hash of a slot prior to the cycle preceding the block slot.
     BadObliqueHashes,
+    /// The attestation slot is not less than the block slot.
     SlotTooHigh,
+    /// The attestation slot is more than a cycle below the block slot.
     SlotTooLow,
     IntWrapping,
 }
@@ -20,22 +31,79 @@ pub enum ParentHashesError {
 ///
 /// See this slide for more information:
 /// https://tinyurl.com/ybzn2spw
-pub fn attestation_parent_hashes(
-    cycle_length: u8,
+///
+/// # Contract
+///
+/// - `current_hashes` must contain exactly `2 * cycle_length` hashes, the last of which is the
+///   hash of the block at `block_slot - 1`. That is, the hash at index `i` is of the block at slot
+///   `block_slot - 2 * cycle_length + i`.
+/// - Alternatively, if `block_slot` is less than `2 * cycle_length`, `current_hashes` may contain
+///   exactly `block_slot` hashes, one for each slot since genesis. The slots of the window prior
+///   to genesis are then taken to have `genesis_hash`.
+/// - `attestation_slot` must be less than `block_slot` and no more than `cycle_length` below it.
+/// - `oblique_hashes` may contain at most `max_oblique_hashes(cycle_length)` hashes, and at most
+///   `cycle_length - (block_slot - attestation_slot)` hashes. That is, an oblique hash may only
+///   replace the hash of a slot within the cycle preceding `block_slot` (as per
+///   `check_oblique_hash_count`), the hashes of earlier slots are fixed.
+///
+/// The result contains the hashes of the `cycle_length` slots preceding `attestation_slot`,
+/// oldest first, with the last `oblique_hashes.len()` of them replaced by `oblique_hashes`.
+///
+/// # Example
+///
+/// ```
+/// extern crate types;
+/// extern crate validation;
+///
+/// use types::{CycleLength, Hash256};
+/// use validation::attestation_parent_hashes::attestation_parent_hashes;
+///
+/// # fn main() {
+/// let cycle_length = CycleLength::new(4).unwrap();
+/// // The hash of the block at slot `i` is `Hash256::from(i)`, for slots 2 to 9 inclusive.
+/// let current_hashes: Vec<Hash256> = (2..10).map(Hash256::from).collect();
+/// let oblique_hashes = vec![Hash256::from(100)];
+///
+/// let hashes = attestation_parent_hashes(
+///     cycle_length,
+///     10,
+///     8,
+///     &current_hashes,
+///     &oblique_hashes,
+///     &Hash256::zero()).unwrap();
+///
+/// // The hashes of slots 4 to 7, with the last replaced by the oblique hash.
+/// let expected: Vec<Hash256> = vec![4, 5, 6, 100].into_iter().map(Hash256::from).collect();
+/// assert_eq!(hashes, expected);
+/// # }
+/// ```
+pub fn attestation_parent_hashes<P>(
+    cycle_length: CycleLength,
     block_slot: u64,
     attestation_slot: u64,
-    current_hashes: &[Hash256],
-    oblique_hashes: &[Hash256])
+    current_hashes: &P,
+    oblique_hashes: &[Hash256],
+    genesis_hash: &Hash256)
     -> Result<Vec<Hash256>, ParentHashesError>
+    where P: ParentHashProvider + ?Sized
 {
-    // This cast places a limit on cycle_length. If you change it, check math
+    let max_oblique_hashes = max_oblique_hashes(u8::from(cycle_length));
+
+    // CycleLength places a limit on cycle_length. If you change it, check math
     // for overflow.
     let cycle_length: u64 = u64::from(cycle_length);
 
-    if current_hashes.len() as u64 != (cycle_length * 2) {
-        return Err(ParentHashesError::BadCurrentHashes);
-    }
-    if oblique_hashes.len() as u64 > cycle_length {
+    /*
+     * The number of slots at the start of the window which are prior to genesis, for which no
+     * hash is provided.
+     */
+    let window = cycle_length * 2;
+    let pre_genesis_slots = match current_hashes.len() as u64 {
+        len if len == window => 0,
+        len if block_slot < window && len == block_slot => window - block_slot,
+        _ => return Err(ParentHashesError::BadCurrentHashes),
+    };
+    if oblique_hashes.len() > max_oblique_hashes {
         return Err(ParentHashesError::BadObliqueHashes);
     }
     if attestation_slot >= block_slot {
@@ -52,6 +120,17 @@ pub fn attestation_parent_hashes(
         return Err(ParentHashesError::SlotTooLow);
     }
 
+    /*
+     * Oblique hashes replace the most recent hashes of the window. Only the hashes of the last
+     * cycle of `current_hashes` may differ between forks, so an oblique hash which would replace
+     * the hash of an earlier slot is rejected rather than silently overriding a fixed hash.
+     *
+     * Cannot underflow because attestation_distance cannot be larger than cycle_length.
+     */
+    if oblique_hashes.len() as u64 > cycle_length - attestation_distance {
+        return Err(ParentHashesError::BadObliqueHashes);
+    }
+
     /*
      * Cannot underflow because attestation_distance cannot
      * be larger than cycle_length.
@@ -70,9 +149,18 @@ pub fn attestation_parent_hashes(
         .ok_or(ParentHashesError::IntWrapping)?;
 
 
-    let mut hashes = Vec::new();
-    hashes.extend_from_slice(
-        &current_hashes[(start as usize)..(end as usize)]);
+    /*
+     * Hashes for the window indices prior to genesis are padded, the remainder are offset into
+     * `current_hashes` by the padding.
+     */
+    let first_known = start.max(pre_genesis_slots);
+    let current_hashes = current_hashes.get_range(
+        (first_known - pre_genesis_slots) as usize,
+        end.saturating_sub(first_known) as usize)
+        .ok_or(ParentHashesError::BadCurrentHashes)?;
+
+    let mut hashes = vec![*genesis_hash; first_known.min(end).saturating_sub(start) as usize];
+    hashes.extend_from_slice(&current_hashes);
     hashes.extend_from_slice(oblique_hashes);
 
     Ok(hashes)
@@ -96,7 +184,7 @@ mod tests {
         /*
          * Two oblique hashes.
          */
-        let cycle_length: u8 = 8;
+        let cycle_length = CycleLength::new(8).unwrap();
         let block_slot: u64 = 19;
         let attestation_slot: u64 = 15;
         let current_hashes = get_range_of_hashes(3, 19);
@@ -106,10 +194,11 @@ mod tests {
             block_slot,
             attestation_slot,
             &current_hashes,
-            &oblique_hashes);
+            &oblique_hashes,
+            &Hash256::zero());
         assert!(result.is_ok());
         let result = result.unwrap();
-        assert_eq!(result.len(), cycle_length as usize);
+        assert_eq!(result.len(), usize::from(cycle_length));
 
         let mut expected_result = get_range_of_hashes(7, 13);
         expected_result.append(&mut get_range_of_hashes(100, 102));
@@ -119,9 +208,10 @@ mod tests {
     #[test]
     fn test_get_signed_hashes_oblique_scenario_2() {
         /*
-         * All oblique hashes.
+         * All oblique hashes, which replace the hashes of the fixed slots 7 to 10 as well as those
+         * of the cycle preceding the block slot.
          */
-        let cycle_length: u8 = 8;
+        let cycle_length = CycleLength::new(8).unwrap();
         let block_slot: u64 = 19;
         let attestation_slot: u64 = 15;
         let current_hashes = get_range_of_hashes(3, 19);
@@ -131,13 +221,50 @@ mod tests {
             block_slot,
             attestation_slot,
             &current_hashes,
-            &oblique_hashes);
-        assert!(result.is_ok());
-        let result = result.unwrap();
-        assert_eq!(result.len(), cycle_length as usize);
+            &oblique_hashes,
+            &Hash256::zero());
+        assert_eq!(result, Err(ParentHashesError::BadObliqueHashes));
+    }
 
-        let expected_result = get_range_of_hashes(100, 108);
-        assert_eq!(result, expected_result);
+    #[test]
+    fn test_get_signed_hashes_oblique_fixed_positions() {
+        /*
+         * Slots 11 to 18 are the cycle preceding the block slot, so an attestation at slot 15 may
+         * replace the hashes of slots 11 to 14 and no earlier.
+         */
+        let cycle_length = CycleLength::new(8).unwrap();
+        let block_slot: u64 = 19;
+        let current_hashes = get_range_of_hashes(3, 19);
+        let parent_hashes = |attestation_slot, oblique_hashes: &[Hash256]| {
+            attestation_parent_hashes(
+                cycle_length,
+                block_slot,
+                attestation_slot,
+                &current_hashes,
+                oblique_hashes,
+                &Hash256::zero())
+        };
+
+        let oblique_hashes = get_range_of_hashes(100, 104);
+        let mut expected_result = get_range_of_hashes(7, 11);
+        expected_result.append(&mut oblique_hashes.clone());
+        assert_eq!(parent_hashes(15, &oblique_hashes).unwrap(), expected_result);
+
+        let oblique_hashes = get_range_of_hashes(100, 105);
+        assert_eq!(parent_hashes(15, &oblique_hashes), Err(ParentHashesError::BadObliqueHashes));
+
+        /*
+         * An attestation a full cycle behind the block slot signs only fixed hashes, whereas one
+         * at the slot below the block slot may replace all but the oldest.
+         */
+        assert_eq!(parent_hashes(11, &get_range_of_hashes(100, 101)),
+                   Err(ParentHashesError::BadObliqueHashes));
+        let oblique_hashes = get_range_of_hashes(100, 107);
+        let mut expected_result = get_range_of_hashes(10, 11);
+        expected_result.append(&mut oblique_hashes.clone());
+        assert_eq!(parent_hashes(18, &oblique_hashes).unwrap(), expected_result);
+        assert_eq!(parent_hashes(18, &get_range_of_hashes(100, 108)),
+                   Err(ParentHashesError::BadObliqueHashes));
     }
 
     #[test]
@@ -146,7 +273,7 @@ mod tests {
          * Google Slides example.
          * https://tinyurl.com/ybzn2spw
          */
-        let cycle_length: u8 = 8;
+        let cycle_length = CycleLength::new(8).unwrap();
         let block_slot: u64 = 19;
         let attestation_slot: u64 = 15;
         let current_hashes = get_range_of_hashes(3, 19);
@@ -156,20 +283,49 @@ mod tests {
             block_slot,
             attestation_slot,
             &current_hashes,
-            &oblique_hashes);
+            &oblique_hashes,
+            &Hash256::zero());
         assert!(result.is_ok());
         let result = result.unwrap();
-        assert_eq!(result.len(), cycle_length as usize);
+        assert_eq!(result.len(), usize::from(cycle_length));
         let expected_result = get_range_of_hashes(7, 15);
         assert_eq!(result, expected_result);
     }
 
+    #[test]
+    fn test_get_signed_hashes_near_u64_max() {
+        /*
+         * The result depends only on the distance between the slots.
+         */
+        let cycle_length = CycleLength::new(8).unwrap();
+        let block_slot = u64::max_value();
+        let current_hashes = get_range_of_hashes(3, 19);
+        let oblique_hashes = vec![];
+        let result = attestation_parent_hashes(
+            cycle_length,
+            block_slot,
+            block_slot - 4,
+            &current_hashes,
+            &oblique_hashes,
+            &Hash256::zero());
+        assert_eq!(result.unwrap(), get_range_of_hashes(7, 15));
+
+        let result = attestation_parent_hashes(
+            cycle_length,
+            block_slot,
+            0,
+            &current_hashes,
+            &oblique_hashes,
+            &Hash256::zero());
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_get_signed_hashes_scenario_2() {
         /*
          * Block 1, attestation 0.
          */
-        let cycle_length: u8 = 8;
+        let cycle_length = CycleLength::new(8).unwrap();
         let block_slot: u64 = 1;
         let attestation_slot: u64 = 0;
         let current_hashes = get_range_of_hashes(0, 16);
@@ -179,9 +335,10 @@ mod tests {
             block_slot,
             attestation_slot,
             &current_hashes,
-            &oblique_hashes);
+            &oblique_hashes,
+            &Hash256::zero());
         let result = result.unwrap();
-        assert_eq!(result.len(), cycle_length as usize);
+        assert_eq!(result.len(), usize::from(cycle_length));
         let expected_result = get_range_of_hashes(7, 15);
         assert_eq!(result, expected_result);
     }
@@ -191,7 +348,7 @@ mod tests {
         /*
          * attestation_slot too large
          */
-        let cycle_length: u8 = 8;
+        let cycle_length = CycleLength::new(8).unwrap();
         let block_slot: u64 = 100;
         let attestation_slot: u64 = 100;
         let current_hashes = get_range_of_hashes(0, 16);
@@ -201,16 +358,143 @@ mod tests {
             block_slot,
             attestation_slot,
             &current_hashes,
-            &oblique_hashes);
+            &oblique_hashes,
+            &Hash256::zero());
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_get_signed_hashes_attestation_slot_equals_block_slot() {
+        let cycle_length = CycleLength::new(8).unwrap();
+        let current_hashes = get_range_of_hashes(0, 16);
+        let result = attestation_parent_hashes(
+            cycle_length,
+            16,
+            16,
+            &current_hashes,
+            &[],
+            &Hash256::zero());
+        assert_eq!(result, Err(ParentHashesError::SlotTooHigh));
+
+        /*
+         * The slot immediately below the block slot is the highest permitted.
+         */
+        let result = attestation_parent_hashes(
+            cycle_length,
+            16,
+            15,
+            &current_hashes,
+            &[],
+            &Hash256::zero());
+        assert_eq!(result.unwrap(), get_range_of_hashes(7, 15));
+    }
+
+    #[test]
+    fn test_get_signed_hashes_maximum_oblique() {
+        /*
+         * A full cycle of oblique hashes would replace the hash of the fixed slot at the start of
+         * the window, regardless of the attestation slot.
+         */
+        let cycle_length = CycleLength::new(8).unwrap();
+        let current_hashes = get_range_of_hashes(0, 16);
+        let oblique_hashes = get_range_of_hashes(100, 108);
+        for attestation_slot in 8..16 {
+            let result = attestation_parent_hashes(
+                cycle_length,
+                16,
+                attestation_slot,
+                &current_hashes,
+                &oblique_hashes,
+            &Hash256::zero());
+            assert_eq!(result, Err(ParentHashesError::BadObliqueHashes));
+        }
+
+        let oblique_hashes = get_range_of_hashes(100, 109);
+        let result = attestation_parent_hashes(
+            cycle_length,
+            16,
+            15,
+            &current_hashes,
+            &oblique_hashes,
+            &Hash256::zero());
+        assert_eq!(result, Err(ParentHashesError::BadObliqueHashes));
+    }
+
+    #[test]
+    fn test_get_signed_hashes_near_genesis() {
+        /*
+         * Block 8 of a chain with one hash per slot since genesis. Slots prior to genesis take
+         * the genesis hash.
+         */
+        let cycle_length = CycleLength::new(8).unwrap();
+        let genesis_hash = get_hash(&[200]);
+        let current_hashes = get_range_of_hashes(0, 8);
+        let parent_hashes = |attestation_slot, oblique_hashes: &[Hash256]| {
+            attestation_parent_hashes(
+                cycle_length,
+                8,
+                attestation_slot,
+                &current_hashes,
+                oblique_hashes,
+                &genesis_hash)
+        };
+
+        assert_eq!(parent_hashes(0, &[]).unwrap(), vec![genesis_hash; 8]);
+
+        let mut expected_result = vec![genesis_hash; 7];
+        expected_result.append(&mut get_range_of_hashes(0, 1));
+        assert_eq!(parent_hashes(1, &[]).unwrap(), expected_result);
+
+        let mut expected_result = vec![genesis_hash; 1];
+        expected_result.append(&mut get_range_of_hashes(0, 7));
+        assert_eq!(parent_hashes(7, &[]).unwrap(), expected_result);
+
+        let oblique_hashes = get_range_of_hashes(100, 102);
+        let mut expected_result = vec![genesis_hash; 1];
+        expected_result.append(&mut get_range_of_hashes(0, 5));
+        expected_result.append(&mut oblique_hashes.clone());
+        assert_eq!(parent_hashes(7, &oblique_hashes).unwrap(), expected_result);
+
+        /*
+         * Hashes prior to genesis are fixed.
+         */
+        assert_eq!(parent_hashes(0, &oblique_hashes), Err(ParentHashesError::BadObliqueHashes));
+    }
+
+    #[test]
+    fn test_get_signed_hashes_near_genesis_block_1() {
+        let cycle_length = CycleLength::new(8).unwrap();
+        let genesis_hash = get_hash(&[200]);
+        let current_hashes = get_range_of_hashes(0, 1);
+        let result = attestation_parent_hashes(
+            cycle_length,
+            1,
+            0,
+            &current_hashes,
+            &[],
+            &genesis_hash);
+        assert_eq!(result.unwrap(), vec![genesis_hash; 8]);
+
+        /*
+         * A chain this young must provide exactly one hash per slot.
+         */
+        let current_hashes = get_range_of_hashes(0, 2);
+        let result = attestation_parent_hashes(
+            cycle_length,
+            1,
+            0,
+            &current_hashes,
+            &[],
+            &genesis_hash);
+        assert_eq!(result, Err(ParentHashesError::BadCurrentHashes));
+    }
+
     #[test]
     fn test_get_signed_hashes_scenario_4() {
         /*
          * Current hashes too small
          */
-        let cycle_length: u8 = 8;
+        let cycle_length = CycleLength::new(8).unwrap();
         let block_slot: u64 = 100;
         let attestation_slot: u64 = 99;
         let current_hashes = get_range_of_hashes(0, 15);
@@ -220,7 +504,8 @@ mod tests {
             block_slot,
             attestation_slot,
             &current_hashes,
-            &oblique_hashes);
+            &oblique_hashes,
+            &Hash256::zero());
         assert!(result.is_err());
     }
 }
@@ -1,79 +1,890 @@
-use std::collections::HashSet;
-use std::sync::Arc;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{
+    hash_map::Entry,
+    HashMap,
+    HashSet,
+};
+use std::error::Error;
+use std::fmt;
+use std::slice;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
 use super::types::{
     AttestationRecord,
     AttesterMap,
+    Bitfield,
+    Checkpoint,
+    CycleLength,
+    EpochParams,
+    ShardId,
+    VoteTarget,
+};
+use super::bls::{
+    AggregateSignature,
+    Signature,
 };
+use super::parent_hash_provider::ParentHashProvider;
 use super::attestation_parent_hashes::{
     attestation_parent_hashes,
     ParentHashesError,
 };
+#[cfg(feature = "tokio")]
+use super::async_db::AsyncClientDB;
 use super::db::{
     ClientDB,
     DBError
 };
 use super::db::stores::{
-    BlockStore,
     BlockAtSlotError,
+    BlockReader,
+    BlockStore,
     ValidatorStore,
 };
 use super::types::{
     Hash256,
 };
 use super::message_generation::generate_signed_message;
+#[cfg(feature = "tokio")]
+use super::futures::{
+    future,
+    Future,
+};
+#[cfg(feature = "parallel")]
+use super::rayon::prelude::*;
+use super::structural_checks::{
+    check_attestation_slot,
+    check_bitfield_bits,
+    check_bitfield_shape,
+    check_justified_slot,
+    check_oblique_hash_count,
+    StructuralError,
+};
+use super::validation_metrics::ValidationMetrics;
 use super::signature_verification::{
+    BlsScheme,
+    SignatureCheck,
+    SignatureScheme,
+    voter_public_keys_for_indices,
     verify_aggregate_signature_for_indices,
+    verify_aggregate_signature_for_indices_count,
     SignatureVerificationError,
 };
 
 #[derive(Debug,PartialEq)]
 pub enum AttestationValidationError {
-    ParentSlotTooHigh,
-    ParentSlotTooLow,
+    ParentSlotTooHigh {
+        attestation_slot: u64,
+        parent_block_slot: u64,
+    },
+    ParentSlotTooLow {
+        attestation_slot: u64,
+        min_slot: u64,
+    },
     BlockSlotTooHigh,
     BlockSlotTooLow,
     JustifiedSlotIncorrect,
     InvalidJustifiedBlockHash,
+    /// The justified block is not at the justified slot of the attestation.
+    JustifiedBlockSlotMismatch {
+        justified_slot: u64,
+        block_slot: u64,
+    },
     TooManyObliqueHashes,
+    /// The attestation has oblique parent hashes, which are forbidden by the context.
+    ObliqueHashesForbidden,
+    /// There are more oblique parent hashes than slots between the start of the cycle preceding
+    /// the block and the attestation slot.
+    ObliqueHashesExceedSlotDistance {
+        oblique_hashes: usize,
+        max_oblique_hashes: usize,
+    },
+    /// The attestation is at the block slot, where there are no parent hashes for oblique parent
+    /// hashes to replace, yet it has oblique parent hashes.
+    ObliqueHashesNotPermittedAtHead,
+    DuplicateObliqueHashes,
     BadCurrentHashes,
     BadObliqueHashes,
-    BadAttesterMap,
+    BadAttesterMap {
+        slot: u64,
+        shard_id: u16,
+    },
+    /// The attester map entry for the slot and shard is an empty committee, so there is no
+    /// validator who could have signed the attestation.
+    EmptyCommittee {
+        slot: u64,
+        shard_id: u16,
+    },
+    /// The shard id is not less than the number of shards in the context.
+    ShardIdOutOfRange {
+        shard_id: u16,
+        shard_count: u16,
+    },
+    /// There are more attestation indices for the slot and shard than are permitted by the
+    /// context.
+    TooManyAttesters {
+        attesters: usize,
+        max_attesters: usize,
+    },
+    /// The committee for the slot and shard is larger than the maximum committee size of the
+    /// context for that shard.
+    CommitteeTooLarge {
+        shard_id: u16,
+        size: usize,
+        max: usize,
+    },
+    /// The attestation index (bitfield position) has no entry in the attestation indices.
+    ValidatorIndexOutOfBounds(usize),
+    /// The validator with this canonical index voted but was not active at the attestation slot.
+    InactiveValidator(usize),
+    /// The validator with this canonical index voted but is in the blacklist of the context.
+    BlacklistedValidator(usize),
+    /// The fraction of attesters who voted is below the minimum participation of the context.
+    LowParticipation {
+        voters: usize,
+        attesters: usize,
+    },
     IntWrapping,
-    PublicKeyCorrupt,
+    /// The committee for the slot and shard has more members than `MAX_COMMITTEE_BITS`, so the
+    /// attester map is malformed.
+    CommitteeExceedsMaxBits(usize),
+    PublicKeyCorrupt(usize),
     NoPublicKeyForValidator,
     BadBitfieldLength,
     InvalidBitfield,
     InvalidBitfieldEndBits,
+    /// No bit of the attester bitfield is set, so the attestation has no voters.
     NoSignatures,
+    /// A bit beyond the length of the attester bitfield (i.e., in the padding of its final byte)
+    /// is set.
     NonZeroTrailingBits,
+    /// The bytes of the aggregate signature are not a valid BLS signature.
+    MalformedAggregateSignature,
     BadAggregateSignature,
-    DBError(String),
+    DBError(DBError),
+}
+
+impl fmt::Display for AttestationValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::AttestationValidationError::*;
+
+        match *self {
+            ParentSlotTooHigh { attestation_slot, parent_block_slot } =>
+                write!(f, "attestation slot {} exceeds parent block slot {}",
+                       attestation_slot, parent_block_slot),
+            ParentSlotTooLow { attestation_slot, min_slot } =>
+                write!(f, "attestation slot {} is below minimum slot {}",
+                       attestation_slot, min_slot),
+            BlockSlotTooHigh =>
+                write!(f, "attestation slot is not below the block slot"),
+            BlockSlotTooLow =>
+                write!(f, "attestation slot is too far below the block slot"),
+            JustifiedSlotIncorrect =>
+                write!(f, "justified slot exceeds the last justified slot"),
+            InvalidJustifiedBlockHash =>
+                write!(f, "justified block is not in the chain at the justified slot"),
+            JustifiedBlockSlotMismatch { justified_slot, block_slot } =>
+                write!(f, "justified block is at slot {}, not justified slot {}",
+                       block_slot, justified_slot),
+            TooManyObliqueHashes =>
+                write!(f, "more oblique parent hashes than the cycle length"),
+            ObliqueHashesForbidden =>
+                write!(f, "oblique parent hashes are forbidden"),
+            ObliqueHashesExceedSlotDistance { oblique_hashes, max_oblique_hashes } =>
+                write!(f, "{} oblique parent hashes exceeds the {} permitted at this slot",
+                       oblique_hashes, max_oblique_hashes),
+            ObliqueHashesNotPermittedAtHead =>
+                write!(f, "oblique parent hashes are not permitted at the block slot"),
+            DuplicateObliqueHashes =>
+                write!(f, "duplicate oblique parent hashes"),
+            BadCurrentHashes =>
+                write!(f, "parent hashes of the context do not cover the attestation slot"),
+            BadObliqueHashes =>
+                write!(f, "oblique parent hashes are invalid"),
+            BadAttesterMap { slot, shard_id } =>
+                write!(f, "no attester map entry for slot {} and shard {}", slot, shard_id),
+            EmptyCommittee { slot, shard_id } =>
+                write!(f, "committee for slot {} and shard {} is empty", slot, shard_id),
+            ShardIdOutOfRange { shard_id, shard_count } =>
+                write!(f, "shard id {} is not less than shard count {}", shard_id, shard_count),
+            TooManyAttesters { attesters, max_attesters } =>
+                write!(f, "{} attesters exceeds maximum of {}", attesters, max_attesters),
+            CommitteeTooLarge { shard_id, size, max } =>
+                write!(f, "committee of {} for shard {} exceeds maximum of {}", size, shard_id,
+                       max),
+            ValidatorIndexOutOfBounds(i) =>
+                write!(f, "bitfield position {} has no attestation index", i),
+            InactiveValidator(validator) =>
+                write!(f, "validator {} was not active at the attestation slot", validator),
+            BlacklistedValidator(validator) =>
+                write!(f, "validator {} is blacklisted", validator),
+            LowParticipation { voters, attesters } =>
+                write!(f, "{} of {} attesters voted, below the minimum participation",
+                       voters, attesters),
+            IntWrapping =>
+                write!(f, "integer overflow"),
+            CommitteeExceedsMaxBits(size) =>
+                write!(f, "committee of {} exceeds the maximum bitfield size", size),
+            PublicKeyCorrupt(validator) =>
+                write!(f, "public key of validator {} is corrupt", validator),
+            NoPublicKeyForValidator =>
+                write!(f, "no public key for a voting validator"),
+            BadBitfieldLength =>
+                write!(f, "attester bitfield length does not match the committee size"),
+            InvalidBitfield =>
+                write!(f, "attester bitfield is invalid"),
+            InvalidBitfieldEndBits =>
+                write!(f, "attester bitfield has bits set beyond the committee size"),
+            NoSignatures =>
+                write!(f, "attester bitfield has no bits set"),
+            NonZeroTrailingBits =>
+                write!(f, "attester bitfield has non-zero trailing bits"),
+            MalformedAggregateSignature =>
+                write!(f, "aggregate signature is not a valid BLS signature"),
+            BadAggregateSignature =>
+                write!(f, "aggregate signature is invalid"),
+            DBError(ref e) =>
+                write!(f, "database error: {}", e.message),
+        }
+    }
+}
+
+impl Error for AttestationValidationError {}
+
+/// A coarse classification of an `AttestationValidationError`, suitable for reporting by an API.
+///
+/// Each variant of `AttestationValidationError` maps to exactly one category, new variants will
+/// be added to an existing category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValidationErrorCategory {
+    /// The attestation is invalid regardless of the state of the chain.
+    Malformed,
+    /// A slot of the attestation is not permitted in the context.
+    SlotOutOfRange,
+    /// The attestation refers to a block, committee or validator which is unknown or unsuitable.
+    UnknownReference,
+    /// The aggregate signature of the attestation is invalid.
+    SignatureInvalid,
+    /// The attestation could not be validated due to a fault of the context or database.
+    Internal,
+}
+
+impl AttestationValidationError {
+    /// Classify this error.
+    pub fn category(&self) -> ValidationErrorCategory {
+        use self::AttestationValidationError::*;
+        use self::ValidationErrorCategory::*;
+
+        match *self {
+            ParentSlotTooHigh { .. } |
+            ParentSlotTooLow { .. } |
+            BlockSlotTooHigh |
+            BlockSlotTooLow |
+            JustifiedSlotIncorrect => SlotOutOfRange,
+            TooManyObliqueHashes |
+            ObliqueHashesForbidden |
+            ObliqueHashesExceedSlotDistance { .. } |
+            ObliqueHashesNotPermittedAtHead |
+            DuplicateObliqueHashes |
+            BadObliqueHashes |
+            ShardIdOutOfRange { .. } |
+            TooManyAttesters { .. } |
+            CommitteeTooLarge { .. } |
+            ValidatorIndexOutOfBounds(_) |
+            LowParticipation { .. } |
+            IntWrapping |
+            BadBitfieldLength |
+            InvalidBitfield |
+            InvalidBitfieldEndBits |
+            NoSignatures |
+            NonZeroTrailingBits |
+            MalformedAggregateSignature => Malformed,
+            InvalidJustifiedBlockHash |
+            JustifiedBlockSlotMismatch { .. } |
+            BadAttesterMap { .. } |
+            EmptyCommittee { .. } |
+            InactiveValidator(_) |
+            BlacklistedValidator(_) => UnknownReference,
+            BadAggregateSignature => SignatureInvalid,
+            BadCurrentHashes |
+            CommitteeExceedsMaxBits(_) |
+            PublicKeyCorrupt(_) |
+            NoPublicKeyForValidator |
+            DBError(_) => Internal,
+        }
+    }
+}
+
+/// The validators of an attestation committee, split by whether or not they signed the
+/// attestation.
+#[derive(Debug, PartialEq)]
+pub struct AttestationParticipation {
+    /// Canonical indices of validators who signed the attestation.
+    pub voted: HashSet<usize>,
+    /// Canonical indices of validators in the committee who did not sign the attestation.
+    pub not_voted: HashSet<usize>,
+}
+
+/// Everything fork choice requires of an accepted attestation.
+#[derive(Debug, PartialEq)]
+pub struct ValidatedVote {
+    /// Canonical indices of validators who signed the attestation.
+    pub voters: HashSet<usize>,
+    /// The justified block referenced by the attestation.
+    pub justified: Checkpoint,
+    /// The shard block for which the attestation votes.
+    pub target: VoteTarget,
+}
+
+/// An accepted attestation and its voters, ordered by their priority for inclusion in a block.
+///
+/// An attestation with more voters is greater than one with fewer. Amongst attestations with the
+/// same number of voters, the attestation with the lower slot is greater. Therefore a
+/// `BinaryHeap` pops the attestation with the most voters first (the oldest, if tied) and sorting
+/// in descending order (e.g., `sort_by(|a, b| b.cmp(a))`) gives voter count descending, then slot
+/// ascending.
+///
+/// Equality is consistent with the ordering: two attestations are equal if they have the same
+/// number of voters and the same slot, regardless of their other fields.
+#[derive(Debug, Clone)]
+pub struct ValidatedAttestation {
+    pub record: AttestationRecord,
+    /// Canonical indices of validators who signed the attestation.
+    pub voters: HashSet<usize>,
+}
+
+impl ValidatedAttestation {
+    pub fn new(record: AttestationRecord, voters: HashSet<usize>) -> Self {
+        Self {
+            record,
+            voters,
+        }
+    }
+
+    pub fn voter_count(&self) -> usize {
+        self.voters.len()
+    }
+}
+
+impl Ord for ValidatedAttestation {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.voter_count().cmp(&other.voter_count())
+            .then_with(|| other.record.slot.cmp(&self.record.slot))
+    }
+}
+
+impl PartialOrd for ValidatedAttestation {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ValidatedAttestation {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ValidatedAttestation {}
+
+/// The state of one of several candidate forks against which an attestation may be validated
+/// (see `validate_attestation_on_forks`).
+#[derive(Clone)]
+pub struct ForkContext {
+    /// The parent hashes of the head of the fork.
+    pub parent_hashes: Arc<dyn ParentHashProvider>,
+    /// The last justified slot of the fork.
+    pub last_justified_slot: u64,
+}
+
+impl ForkContext {
+    pub fn new<H>(parent_hashes: H, last_justified_slot: u64) -> Self
+        where H: Into<Arc<Vec<Hash256>>>
+    {
+        let parent_hashes: Arc<Vec<Hash256>> = parent_hashes.into();
+        Self {
+            parent_hashes,
+            last_justified_slot,
+        }
+    }
+}
+
+/// Lookups which may be shared between attestations validated against the same context.
+#[derive(Default)]
+struct ValidationCache<'a> {
+    /// Maps (slot, shard_id) to the attestation indices found in the attester map.
+    attestation_indices: HashMap<(u64, u16), Option<&'a Vec<usize>>>,
+    /// Maps (latest_parent_hash, justified_slot) to the hash of the block found at that slot.
+    justified_block_hashes: HashMap<(Hash256, u64), Option<Vec<u8>>>,
+    /// Maps a justified block hash to whether or not it exists in the block store.
+    justified_block_exists: HashMap<Hash256, bool>,
+    /// Maps a justified block hash to the slot of that block, if it is known.
+    justified_block_slots: HashMap<Hash256, Option<u64>>,
+    /// If true, the existence of any justified block hash missing from `justified_block_exists`
+    /// will be read from the block store and memoized.
+    memoize_block_exists: bool,
+    /// If set, the time spent in each phase of validation is added to these timings.
+    timings: Option<ValidationTimings>,
+}
+
+impl<'a> ValidationCache<'a> {
+    /// Returns the current instant, only if timings are being recorded.
+    fn start_timer(&self) -> Option<Instant> {
+        self.timings.as_ref().map(|_| Instant::now())
+    }
+
+    /// Add the time elapsed since `start` to the phase of `self.timings` selected by `phase`.
+    fn record_timing<F>(&mut self, start: Option<Instant>, phase: F)
+        where F: FnOnce(&mut ValidationTimings) -> &mut Duration
+    {
+        if let (Some(timings), Some(start)) = (self.timings.as_mut(), start) {
+            *phase(timings) += start.elapsed();
+        }
+    }
+}
+
+/// The time spent in each phase of validating an attestation.
+///
+/// A phase which was not reached (e.g., because an earlier check failed) has a zero duration.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ValidationTimings {
+    /// Deriving the parent hashes from the context and the oblique parent hashes.
+    pub parent_hashes: Duration,
+    /// Reading the justified block from the block store.
+    pub block_store: Duration,
+    /// Loading public keys and verifying the aggregate signature.
+    pub signature: Duration,
+}
+
+/// Transforms a clone of each attestation before it is validated (see
+/// `AttestationValidationContext::preprocessor`).
+pub type AttestationPreprocessor = dyn Fn(&mut AttestationRecord) + Send + Sync;
+
+/// Memoized parent hashes, keyed by block slot, attestation slot and oblique parent hashes.
+pub type ParentHashesCache = HashMap<(u64, u64, Vec<Hash256>), Arc<Vec<Hash256>>>;
+
+/// Values computed during structural validation which are required to verify the signature.
+struct AttestationStructure<'a> {
+    parent_hashes: Arc<Vec<Hash256>>,
+    attestation_indices: &'a Vec<usize>,
 }
 
 /// The context against which some attestation should be validated.
-pub struct AttestationValidationContext<T>
-    where T: ClientDB + Sized
+///
+/// Blocks are read from a `BlockStore` unless some other `BlockReader` (e.g., a
+/// `CachingBlockStore`) is supplied.
+pub struct AttestationValidationContext<T, B = BlockStore<T>>
+    where T: ClientDB + Sized,
+          B: BlockReader
 {
     /// The slot as determined by the system time.
+    ///
+    /// An attestation must be for a slot strictly below the block slot, an attestation for the
+    /// block slot itself is rejected with `BlockSlotTooHigh`.
     pub block_slot: u64,
     /// The slot of the parent of the block that contained this attestation.
     pub parent_block_slot: u64,
-    /// The cycle_length as determined by the chain configuration.
-    pub cycle_length: u8,
+    /// The cycle_length as determined by the chain configuration. Ignored if `epoch_params` is
+    /// set.
+    pub cycle_length: CycleLength,
     /// The last justified slot as per the client's view of the canonical chain.
     pub last_justified_slot: u64,
-    /// A vec of the hashes of the blocks preceeding the present slot.
-    pub parent_hashes: Arc<Vec<Hash256>>,
+    /// The hashes of the blocks preceeding the present slot.
+    pub parent_hashes: Arc<dyn ParentHashProvider>,
+    /// The hash given to slots prior to genesis when `parent_hashes` holds fewer than two cycles
+    /// of hashes (i.e., when the chain is younger than two cycles).
+    pub genesis_hash: Hash256,
     /// The store containing block information.
-    pub block_store: Arc<BlockStore<T>>,
+    pub block_store: Arc<B>,
     /// The store containing validator information.
     pub validator_store: Arc<ValidatorStore<T>>,
     /// A map of (slot, shard_id) to the attestation set of validation indices.
     pub attester_map: Arc<AttesterMap>,
+    /// The number of slots past the `parent_block_slot` which an attestation slot may be.
+    ///
+    /// Must be zero for consensus. Non-zero values are only intended for relaxed simulation
+    /// contexts. An attestation slot must still be less than `block_slot`.
+    pub max_future_slots: u64,
+    /// If set, the maximum number of attestation indices for the slot and shard of an
+    /// attestation.
+    pub max_attesters: Option<usize>,
+    /// If set, the maximum number of attestation indices for the slot and shard of an
+    /// attestation, by shard id. Shards without an entry are not limited.
+    pub max_committee_sizes: Option<HashMap<u16, usize>>,
+    /// If set, receives the outcome of each validated attestation.
+    pub metrics: Option<Arc<dyn ValidationMetrics>>,
+    /// If set, the number of shards. Attestations for a shard id which is not less than this are
+    /// rejected.
+    pub shard_count: Option<u16>,
+    /// If set, the justified slot of an attestation must be a member of this set (e.g., the last
+    /// two justified slots) instead of being no greater than `last_justified_slot`.
+    pub allowed_justified_slots: Option<HashSet<u64>>,
+    /// If true, each validator who voted must be active at the attestation slot according to
+    /// the `validator_store`. Only set this if the store tracks validator activity.
+    pub require_active_validators: bool,
+    /// If set, the minimum fraction (e.g., 0.5) of the attestation indices for the slot and
+    /// shard which must have voted.
+    pub min_participation: Option<f64>,
+    /// If true, attestations with any oblique parent hashes are rejected.
+    pub forbid_oblique: bool,
+    /// If set, memoizes the parent hashes derived for each block slot, attestation slot and set
+    /// of oblique parent hashes.
+    ///
+    /// The cache must be cleared if `parent_hashes` are modified. A clone of the context starts
+    /// with an empty cache.
+    pub parent_hashes_cache: Option<Mutex<ParentHashesCache>>,
+    /// The scheme used to verify aggregate signatures.
+    pub signature_scheme: Arc<dyn SignatureScheme>,
+    /// Mixed into the signed message so that an attestation signed for one network (e.g., a
+    /// testnet) is not valid on another. Zero for no domain.
+    pub attestation_domain: u64,
+    /// If set, is applied to a clone of each attestation before it is validated (e.g., to
+    /// normalize a different wire encoding). The record supplied by the caller is never modified.
+    pub preprocessor: Option<Arc<AttestationPreprocessor>>,
+    /// If set, attestations with a vote from any of these validators (e.g., validators known to
+    /// have corrupt public keys) are rejected before any signature verification.
+    pub blacklist: Option<HashSet<usize>>,
+    /// If set, each attestation is validated with the cycle length in effect at its slot (e.g.,
+    /// when re-validating attestations from before a hard fork) instead of `cycle_length`.
+    pub epoch_params: Option<Arc<EpochParams>>,
+}
+
+/*
+ * Implemented manually as `#[derive(Clone)]` would require `T: Clone` and `B: Clone`, however only
+ * `Arc`s of the stores are cloned.
+ */
+impl<T, B> Clone for AttestationValidationContext<T, B>
+    where T: ClientDB,
+          B: BlockReader
+{
+    fn clone(&self) -> Self {
+        Self {
+            block_slot: self.block_slot,
+            parent_block_slot: self.parent_block_slot,
+            cycle_length: self.cycle_length,
+            last_justified_slot: self.last_justified_slot,
+            parent_hashes: self.parent_hashes.clone(),
+            genesis_hash: self.genesis_hash,
+            block_store: self.block_store.clone(),
+            validator_store: self.validator_store.clone(),
+            attester_map: self.attester_map.clone(),
+            max_future_slots: self.max_future_slots,
+            max_attesters: self.max_attesters,
+            max_committee_sizes: self.max_committee_sizes.clone(),
+            metrics: self.metrics.clone(),
+            shard_count: self.shard_count,
+            allowed_justified_slots: self.allowed_justified_slots.clone(),
+            require_active_validators: self.require_active_validators,
+            min_participation: self.min_participation,
+            forbid_oblique: self.forbid_oblique,
+            parent_hashes_cache: self.parent_hashes_cache.as_ref()
+                .map(|_| Mutex::new(HashMap::new())),
+            signature_scheme: self.signature_scheme.clone(),
+            attestation_domain: self.attestation_domain,
+            preprocessor: self.preprocessor.clone(),
+            blacklist: self.blacklist.clone(),
+            epoch_params: self.epoch_params.clone(),
+        }
+    }
 }
 
-impl<T> AttestationValidationContext<T>
+#[derive(Debug, PartialEq)]
+pub enum BuildError {
+    MissingField(&'static str),
+}
+
+/// Builds an AttestationValidationContext, wrapping values in an `Arc` where required.
+///
+/// `max_future_slots` defaults to zero, `genesis_hash` to the zero hash, `max_attesters`,
+/// `max_committee_sizes`, `metrics`, `shard_count`, `allowed_justified_slots`,
+/// `min_participation`, `blacklist` and `epoch_params` default to none,
+/// `require_active_validators` and `forbid_oblique` default to false, parent hashes are not
+/// cached, `signature_scheme` defaults to `BlsScheme`, `attestation_domain` to zero and
+/// `preprocessor` to none, all other fields must be set.
+pub struct AttestationValidationContextBuilder<T, B = BlockStore<T>>
+    where T: ClientDB + Sized,
+          B: BlockReader
+{
+    block_slot: Option<u64>,
+    parent_block_slot: Option<u64>,
+    cycle_length: Option<CycleLength>,
+    last_justified_slot: Option<u64>,
+    parent_hashes: Option<Arc<dyn ParentHashProvider>>,
+    genesis_hash: Hash256,
+    block_store: Option<Arc<B>>,
+    validator_store: Option<Arc<ValidatorStore<T>>>,
+    attester_map: Option<Arc<AttesterMap>>,
+    max_future_slots: u64,
+    max_attesters: Option<usize>,
+    max_committee_sizes: Option<HashMap<u16, usize>>,
+    metrics: Option<Arc<dyn ValidationMetrics>>,
+    shard_count: Option<u16>,
+    allowed_justified_slots: Option<HashSet<u64>>,
+    require_active_validators: bool,
+    min_participation: Option<f64>,
+    forbid_oblique: bool,
+    cache_parent_hashes: bool,
+    signature_scheme: Arc<dyn SignatureScheme>,
+    attestation_domain: u64,
+    preprocessor: Option<Arc<AttestationPreprocessor>>,
+    blacklist: Option<HashSet<usize>>,
+    epoch_params: Option<Arc<EpochParams>>,
+}
+
+impl<T> AttestationValidationContextBuilder<T>
     where T: ClientDB
+{
+    /// Create a builder for a context which reads blocks from a `BlockStore`.
+    ///
+    /// Use `default()` to build a context with some other `BlockReader`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, B> AttestationValidationContextBuilder<T, B>
+    where T: ClientDB,
+          B: BlockReader
+{
+    pub fn block_slot(mut self, block_slot: u64) -> Self {
+        self.block_slot = Some(block_slot);
+        self
+    }
+
+    pub fn parent_block_slot(mut self, parent_block_slot: u64) -> Self {
+        self.parent_block_slot = Some(parent_block_slot);
+        self
+    }
+
+    pub fn cycle_length(mut self, cycle_length: CycleLength) -> Self {
+        self.cycle_length = Some(cycle_length);
+        self
+    }
+
+    pub fn last_justified_slot(mut self, last_justified_slot: u64) -> Self {
+        self.last_justified_slot = Some(last_justified_slot);
+        self
+    }
+
+    /// Accepts either a `Vec<Hash256>` or an `Arc<Vec<Hash256>>`.
+    pub fn parent_hashes<H>(mut self, parent_hashes: H) -> Self
+        where H: Into<Arc<Vec<Hash256>>>
+    {
+        let parent_hashes: Arc<Vec<Hash256>> = parent_hashes.into();
+        self.parent_hashes = Some(parent_hashes);
+        self
+    }
+
+    /// Use some `ParentHashProvider` other than a `Vec` for the parent hashes.
+    pub fn parent_hash_provider(mut self, parent_hashes: Arc<dyn ParentHashProvider>) -> Self {
+        self.parent_hashes = Some(parent_hashes);
+        self
+    }
+
+    pub fn genesis_hash(mut self, genesis_hash: Hash256) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
+
+    /// Accepts either a `BlockReader` (e.g., a `BlockStore`) or an `Arc` of one.
+    pub fn block_store<S>(mut self, block_store: S) -> Self
+        where S: Into<Arc<B>>
+    {
+        self.block_store = Some(block_store.into());
+        self
+    }
+
+    /// Accepts either a `ValidatorStore` or an `Arc<ValidatorStore>`.
+    pub fn validator_store<S>(mut self, validator_store: S) -> Self
+        where S: Into<Arc<ValidatorStore<T>>>
+    {
+        self.validator_store = Some(validator_store.into());
+        self
+    }
+
+    /// Accepts either an `AttesterMap` or an `Arc<AttesterMap>`.
+    pub fn attester_map<M>(mut self, attester_map: M) -> Self
+        where M: Into<Arc<AttesterMap>>
+    {
+        self.attester_map = Some(attester_map.into());
+        self
+    }
+
+    pub fn max_future_slots(mut self, max_future_slots: u64) -> Self {
+        self.max_future_slots = max_future_slots;
+        self
+    }
+
+    pub fn max_attesters(mut self, max_attesters: usize) -> Self {
+        self.max_attesters = Some(max_attesters);
+        self
+    }
+
+    pub fn metrics(mut self, metrics: Arc<dyn ValidationMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Limit the committee size of each shard in `max_committee_sizes`, given as
+    /// (shard_id, max_committee_size) pairs.
+    pub fn max_committee_sizes<I>(mut self, max_committee_sizes: I) -> Self
+        where I: IntoIterator<Item = (u16, usize)>
+    {
+        self.max_committee_sizes = Some(max_committee_sizes.into_iter().collect());
+        self
+    }
+
+    pub fn shard_count(mut self, shard_count: u16) -> Self {
+        self.shard_count = Some(shard_count);
+        self
+    }
+
+    pub fn allowed_justified_slots<I>(mut self, allowed_justified_slots: I) -> Self
+        where I: IntoIterator<Item = u64>
+    {
+        self.allowed_justified_slots = Some(allowed_justified_slots.into_iter().collect());
+        self
+    }
+
+    pub fn require_active_validators(mut self, require_active_validators: bool) -> Self {
+        self.require_active_validators = require_active_validators;
+        self
+    }
+
+    pub fn min_participation(mut self, min_participation: f64) -> Self {
+        self.min_participation = Some(min_participation);
+        self
+    }
+
+    pub fn forbid_oblique(mut self, forbid_oblique: bool) -> Self {
+        self.forbid_oblique = forbid_oblique;
+        self
+    }
+
+    /// Enable or disable the memoization of derived parent hashes (see `parent_hashes_cache`).
+    ///
+    /// Caching is worthwhile when many attestations share a slot and oblique parent hashes.
+    pub fn cache_parent_hashes(mut self, cache_parent_hashes: bool) -> Self {
+        self.cache_parent_hashes = cache_parent_hashes;
+        self
+    }
+
+    /// Verify aggregate signatures with some scheme other than `BlsScheme`.
+    pub fn signature_scheme(mut self, signature_scheme: Arc<dyn SignatureScheme>) -> Self {
+        self.signature_scheme = signature_scheme;
+        self
+    }
+
+    pub fn attestation_domain(mut self, attestation_domain: u64) -> Self {
+        self.attestation_domain = attestation_domain;
+        self
+    }
+
+    pub fn preprocessor(mut self, preprocessor: Arc<AttestationPreprocessor>) -> Self {
+        self.preprocessor = Some(preprocessor);
+        self
+    }
+
+    pub fn blacklist<I>(mut self, blacklist: I) -> Self
+        where I: IntoIterator<Item = usize>
+    {
+        self.blacklist = Some(blacklist.into_iter().collect());
+        self
+    }
+
+    pub fn epoch_params<P>(mut self, epoch_params: P) -> Self
+        where P: Into<Arc<EpochParams>>
+    {
+        self.epoch_params = Some(epoch_params.into());
+        self
+    }
+
+    /// Build the context, returning an error if any required field has not been set.
+    pub fn build(self) -> Result<AttestationValidationContext<T, B>, BuildError> {
+        Ok(AttestationValidationContext {
+            block_slot: self.block_slot
+                .ok_or(BuildError::MissingField("block_slot"))?,
+            parent_block_slot: self.parent_block_slot
+                .ok_or(BuildError::MissingField("parent_block_slot"))?,
+            cycle_length: self.cycle_length
+                .ok_or(BuildError::MissingField("cycle_length"))?,
+            last_justified_slot: self.last_justified_slot
+                .ok_or(BuildError::MissingField("last_justified_slot"))?,
+            parent_hashes: self.parent_hashes
+                .ok_or(BuildError::MissingField("parent_hashes"))?,
+            genesis_hash: self.genesis_hash,
+            block_store: self.block_store
+                .ok_or(BuildError::MissingField("block_store"))?,
+            validator_store: self.validator_store
+                .ok_or(BuildError::MissingField("validator_store"))?,
+            attester_map: self.attester_map
+                .ok_or(BuildError::MissingField("attester_map"))?,
+            max_future_slots: self.max_future_slots,
+            max_attesters: self.max_attesters,
+            max_committee_sizes: self.max_committee_sizes,
+            metrics: self.metrics,
+            shard_count: self.shard_count,
+            allowed_justified_slots: self.allowed_justified_slots,
+            require_active_validators: self.require_active_validators,
+            min_participation: self.min_participation,
+            forbid_oblique: self.forbid_oblique,
+            parent_hashes_cache: if self.cache_parent_hashes {
+                Some(Mutex::new(HashMap::new()))
+            } else {
+                None
+            },
+            signature_scheme: self.signature_scheme,
+            attestation_domain: self.attestation_domain,
+            preprocessor: self.preprocessor,
+            blacklist: self.blacklist,
+            epoch_params: self.epoch_params,
+        })
+    }
+}
+
+impl<T, B> Default for AttestationValidationContextBuilder<T, B>
+    where T: ClientDB,
+          B: BlockReader
+{
+    fn default() -> Self {
+        Self {
+            block_slot: None,
+            parent_block_slot: None,
+            cycle_length: None,
+            last_justified_slot: None,
+            parent_hashes: None,
+            genesis_hash: Hash256::zero(),
+            block_store: None,
+            validator_store: None,
+            attester_map: None,
+            max_future_slots: 0,
+            max_attesters: None,
+            max_committee_sizes: None,
+            metrics: None,
+            shard_count: None,
+            allowed_justified_slots: None,
+            require_active_validators: false,
+            min_participation: None,
+            forbid_oblique: false,
+            cache_parent_hashes: false,
+            signature_scheme: Arc::new(BlsScheme),
+            attestation_domain: 0,
+            preprocessor: None,
+            blacklist: None,
+            epoch_params: None,
+        }
+    }
+}
+
+impl<T, B> AttestationValidationContext<T, B>
+    where T: ClientDB,
+          B: BlockReader
 {
     /// Validate a (fully deserialized) AttestationRecord against this context.
     ///
@@ -84,80 +895,967 @@ impl<T> AttestationValidationContext<T>
     /// access all required validation public keys via the `validator_store`.
     pub fn validate_attestation(&self, a: &AttestationRecord)
         -> Result<HashSet<usize>, AttestationValidationError>
+    {
+        self.validate_attestation_with_participation(a)
+            .map(|participation| participation.voted)
+    }
+
+    /// Validate a (fully deserialized) AttestationRecord against this context, returning the
+    /// voters along with the justified block and the shard block of the attestation.
+    ///
+    /// Validation is identical to `validate_attestation`.
+    pub fn validate_attestation_full(&self, a: &AttestationRecord)
+        -> Result<ValidatedVote, AttestationValidationError>
+    {
+        self.validate_attestation(a)
+            .map(|voters| ValidatedVote {
+                voters,
+                justified: a.justified_checkpoint(),
+                target: a.vote_target(),
+            })
+    }
+
+    /// Validate a (fully deserialized) AttestationRecord against each of `forks`, which replace
+    /// the parent hashes and last justified slot of this context.
+    ///
+    /// The returned vec is index-aligned with `forks`, so the forks which accept the attestation
+    /// are those with an `Ok` result.
+    pub fn validate_attestation_on_forks(&self, a: &AttestationRecord, forks: &[ForkContext])
+        -> Vec<Result<HashSet<usize>, AttestationValidationError>>
+    {
+        forks.iter()
+            .map(|fork| {
+                /*
+                 * Cloning the context also clears any cached parent hashes, which would belong to
+                 * a different fork.
+                 */
+                let mut context = self.clone();
+                context.parent_hashes = fork.parent_hashes.clone();
+                context.last_justified_slot = fork.last_justified_slot;
+                context.validate_attestation(a)
+            })
+            .collect()
+    }
+
+    /// Validate a (fully deserialized) AttestationRecord against this context, returning both the
+    /// validators who voted and those who abstained.
+    ///
+    /// Validation is identical to `validate_attestation`. The set of non-voting validators is only
+    /// computed once the aggregate signature has been verified.
+    pub fn validate_attestation_with_participation(&self, a: &AttestationRecord)
+        -> Result<AttestationParticipation, AttestationValidationError>
+    {
+        self.validate_attestation_with_cache(a, &mut ValidationCache::default())
+    }
+
+    /// Validate a (fully deserialized) AttestationRecord against this context, recording the time
+    /// spent in each phase of validation.
+    ///
+    /// Validation is identical to `validate_attestation`. The overhead of reading the clock is only
+    /// incurred by this function.
+    pub fn validate_attestation_timed(&self, a: &AttestationRecord)
+        -> (Result<HashSet<usize>, AttestationValidationError>, ValidationTimings)
+    {
+        let mut cache = ValidationCache {
+            timings: Some(ValidationTimings::default()),
+            ..ValidationCache::default()
+        };
+        let result = self.validate_attestation_with_cache(a, &mut cache)
+            .map(|participation| participation.voted);
+        (result, cache.timings.unwrap_or_default())
+    }
+
+    /// Validate a (fully deserialized) AttestationRecord against this context, returning only the
+    /// number of validators who voted.
+    ///
+    /// Validation is identical to `validate_attestation`, however the set of voters is never
+    /// collected. This is cheaper for callers which only require the weight of an attestation
+    /// (e.g., fork choice).
+    pub fn validate_attestation_count(&self, a: &AttestationRecord)
+        -> Result<usize, AttestationValidationError>
+    {
+        let a = self.preprocess(a);
+        let result = self.verify_attestation_count_with_cache(&a, &mut ValidationCache::default());
+        self.record_outcome(result.as_ref().map(|voters| *voters));
+        result
+    }
+
+    /// Validate a (fully deserialized) AttestationRecord against this context, reading blocks and
+    /// validators from `db` rather than from the stores of this context.
+    ///
+    /// Validation is identical to `validate_attestation`. Only the reads of `db` are awaited, all
+    /// other checks are performed before the future is returned (so a malformed attestation is
+    /// rejected without touching `db`).
+    #[cfg(feature = "tokio")]
+    pub fn validate_attestation_async<'a, D>(&'a self, a: &'a AttestationRecord, db: &'a D)
+        -> Box<dyn Future<Item = HashSet<usize>, Error = AttestationValidationError> + 'a>
+        where D: AsyncClientDB
+    {
+        /*
+         * The futures below outlive this call, so they share an owned copy of the (preprocessed)
+         * record rather than borrowing a temporary.
+         */
+        let a = Arc::new(self.preprocess(a).into_owned());
+        let mut cache = ValidationCache::default();
+        let structure = self.verify_committee_structure(&a, &mut cache)
+            .and_then(|attestation_indices| {
+                let parent_hashes = self.parent_hashes_with_cache(&a, &mut cache)?;
+                let latest_parent_hash = *parent_hashes.last()
+                    .ok_or(AttestationValidationError::BadCurrentHashes)?;
+                let voter_indices = a.attester_bitfield.iter_set_bits()
+                    .map(|i| attestation_indices.get(i).cloned()
+                         .ok_or(AttestationValidationError::ValidatorIndexOutOfBounds(i)))
+                    .collect::<Result<Vec<usize>, _>>()?;
+                Ok((attestation_indices.len(), parent_hashes, latest_parent_hash, voter_indices))
+            });
+        let (attesters, parent_hashes, latest_parent_hash, voter_indices) = match structure {
+            Ok(structure) => structure,
+            Err(e) => {
+                self.record_outcome(Err(&e));
+                return Box::new(future::err(e));
+            }
+        };
+        let voter_indices = Arc::new(voter_indices);
+
+        let validation = self.verify_voters_active_async(&a, voter_indices.clone(), db)
+            .and_then({
+                let a = a.clone();
+                move |()| self.verify_justified_block_async(a, latest_parent_hash, db)
+            })
+            .and_then(move |()| {
+                let pub_keys = voter_indices.iter()
+                    .map(|validator| {
+                        db.get_public_key_by_index(*validator)
+                            .map_err(|e| AttestationValidationError::from(
+                                SignatureVerificationError::from(e)))
+                    })
+                    .collect::<Vec<_>>();
+                future::join_all(pub_keys)
+                    .map(move |pub_keys| (voter_indices, pub_keys))
+            })
+            .and_then(move |(voter_indices, pub_keys)| {
+                let pub_keys = pub_keys.into_iter()
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(AttestationValidationError::NoPublicKeyForValidator)?;
+                let signed_message =
+                    attestation_signed_message(&a, &parent_hashes, self.attestation_domain);
+                if !self.signature_scheme.aggregate_verify(
+                    &signed_message,
+                    &a.aggregate_sig,
+                    &pub_keys)
+                {
+                    debug!("Rejected attestation (slot: {}, shard_id: {}): invalid aggregate \
+                            signature", a.slot, a.shard_id);
+                    return Err(AttestationValidationError::BadAggregateSignature);
+                }
+                self.verify_participation(&a, voter_indices.len(), attesters)?;
+                trace!("Validated attestation (slot: {}, shard_id: {}): {} voters",
+                       a.slot, a.shard_id, voter_indices.len());
+                Ok(voter_indices.iter().cloned().collect())
+            })
+            .then(move |result: Result<HashSet<usize>, _>| {
+                self.record_outcome(result.as_ref().map(|voted| voted.len()));
+                result
+            });
+        Box::new(validation)
+    }
+
+    /// Validate each AttestationRecord in `records` against this context.
+    ///
+    /// The returned vec is index-aligned with `records`. A failure of one record does not
+    /// prevent the validation of the others.
+    ///
+    /// Attester map lookups and justified block lookups are shared across the batch, so this is
+    /// cheaper than calling `validate_attestation` for each record when many records refer to the
+    /// same slot and shard.
+    pub fn validate_attestations(&self, records: &[AttestationRecord])
+        -> Vec<Result<HashSet<usize>, AttestationValidationError>>
     {
         /*
-         * The attesation slot must be less than or equal to the parent of the slot of the block
-         * that contained the attestation.
+         * The cache is local to this call. Block existence may change between batches, so
+         * results must never be shared between calls.
          */
-        if a.slot > self.parent_block_slot {
-            return Err(AttestationValidationError::ParentSlotTooHigh);
+        let mut cache = ValidationCache {
+            memoize_block_exists: true,
+            ..ValidationCache::default()
+        };
+
+        /*
+         * Check the existence of all distinct justified block hashes up front.
+         *
+         * If this fails, the existence of each hash will instead be read (and memoized) as
+         * each attestation is validated.
+         */
+        let justified_hashes: Vec<Hash256> = records.iter()
+            .map(|a| a.justified_block_hash)
+            .collect::<HashSet<Hash256>>()
+            .into_iter()
+            .collect();
+        if let Ok(exists) = self.block_store.block_exists_batch(&justified_hashes) {
+            cache.justified_block_exists = justified_hashes.into_iter()
+                .zip(exists)
+                .collect();
         }
 
+        records.iter()
+            .map(|a| {
+                self.validate_attestation_with_cache(a, &mut cache)
+                    .map(|participation| participation.voted)
+            })
+            .collect()
+    }
+
+    /// Validate each AttestationRecord in `records` with `validate_attestations`, returning the
+    /// union of the voters for each shard block, keyed by `(slot, shard_id, shard_block_hash)`.
+    ///
+    /// Invalid records are skipped. A validator which voted in several records for the same shard
+    /// block is counted once.
+    pub fn validate_and_collect_votes_by_shard(&self, records: &[AttestationRecord])
+        -> HashMap<(u64, u16, Hash256), HashSet<usize>>
+    {
+        let mut votes: HashMap<(u64, u16, Hash256), HashSet<usize>> = HashMap::new();
+        for (a, result) in records.iter().zip(self.validate_attestations(records)) {
+            if let Ok(voters) = result {
+                votes.entry((a.slot, a.shard_id, a.shard_block_hash))
+                    .or_default()
+                    .extend(voters);
+            }
+        }
+        votes
+    }
+
+    /// Validate each AttestationRecord in `records` against this context, using the `rayon`
+    /// thread pool to validate records concurrently.
+    ///
+    /// The returned vec is index-aligned with `records`. A failure of one record does not
+    /// prevent the validation of the others.
+    #[cfg(feature = "parallel")]
+    pub fn validate_attestations_parallel(&self, records: &[AttestationRecord])
+        -> Vec<Result<HashSet<usize>, AttestationValidationError>>
+        where T: ClientDB + Send + Sync,
+              B: Send + Sync
+    {
+        records.par_iter()
+            .map(|a| self.validate_attestation(a))
+            .collect()
+    }
+
+    /// As per `verify_justified_block`, reading blocks from `db`.
+    #[cfg(feature = "tokio")]
+    fn verify_justified_block_async<'a, D>(
+        &self,
+        a: Arc<AttestationRecord>,
+        latest_parent_hash: Hash256,
+        db: &'a D)
+        -> Box<dyn Future<Item = (), Error = AttestationValidationError> + 'a>
+        where D: AsyncClientDB
+    {
+        let (a1, a2, a3, a4) = (a.clone(), a.clone(), a.clone(), a.clone());
+        Box::new(db.block_exists(&a.justified_block_hash)
+            .map_err(AttestationValidationError::from)
+            .and_then(move |exists| check_justified_block_exists(&a1, exists))
+            .and_then(move |()| {
+                db.get_block_slot(&a2.justified_block_hash)
+                    .map_err(AttestationValidationError::from)
+            })
+            .and_then(move |block_slot| check_justified_block_slot(&a3, block_slot))
+            .and_then(move |()| {
+                db.block_hash_at_slot(&latest_parent_hash, a4.justified_slot)
+                    .map_err(AttestationValidationError::from)
+            })
+            .and_then(move |block_hash| check_justified_block_in_chain(&a, block_hash.as_ref())))
+    }
+
+    /// Perform every check of `validate_attestation` except for verification of the aggregate
+    /// signature on each AttestationRecord in `records`, using the `rayon` thread pool to check
+    /// records concurrently.
+    ///
+    /// For each record which passes, the message, signature and public keys required to verify
+    /// its signature are collected so that the signatures may later be verified with
+    /// `verify_collected_signatures` (e.g., on another thread). The returned vec is index-aligned
+    /// with `records`.
+    #[cfg(feature = "parallel")]
+    pub fn validate_structures_then_collect(&self, records: &[AttestationRecord])
+        -> Vec<Result<SignatureCheck, AttestationValidationError>>
+        where T: ClientDB + Send + Sync,
+              B: Send + Sync
+    {
+        records.par_iter()
+            .map(|a| self.collect_signature_check(a, &mut ValidationCache::default()))
+            .collect()
+    }
+
+    /// Perform every check of `validate_attestation` except for verification of the aggregate
+    /// signature on each AttestationRecord in `records`.
+    ///
+    /// For each record which passes, the message, signature and public keys required to verify
+    /// its signature are collected so that the signatures may later be verified with
+    /// `verify_collected_signatures` (e.g., on another thread). The returned vec is index-aligned
+    /// with `records`.
+    #[cfg(not(feature = "parallel"))]
+    pub fn validate_structures_then_collect(&self, records: &[AttestationRecord])
+        -> Vec<Result<SignatureCheck, AttestationValidationError>>
+    {
+        let mut cache = ValidationCache::default();
+        records.iter()
+            .map(|a| self.collect_signature_check(a, &mut cache))
+            .collect()
+    }
+
+    /// Verify the signatures collected by `validate_structures_then_collect`, returning the
+    /// voters of each attestation.
+    ///
+    /// The returned vec is index-aligned with `checks` and any error in `checks` is returned
+    /// unchanged. Each signature is verified exactly once, so this costs the same signature
+    /// verification as `validate_attestation` on each record.
+    pub fn verify_collected_signatures(
+        &self,
+        checks: Vec<Result<SignatureCheck, AttestationValidationError>>)
+        -> Vec<Result<HashSet<usize>, AttestationValidationError>>
+    {
+        checks.into_iter()
+            .map(|check| {
+                let result = check.and_then(|check| {
+                    if self.signature_scheme.aggregate_verify(
+                        &check.message,
+                        &check.aggregate_sig,
+                        &check.pub_keys)
+                    {
+                        Ok(check.voters)
+                    } else {
+                        Err(AttestationValidationError::BadAggregateSignature)
+                    }
+                });
+                self.record_outcome(result.as_ref().map(|voters| voters.len()));
+                result
+            })
+            .collect()
+    }
+
+    /// Derive the parent hashes signed by an attestation at `attestation_slot` with the given
+    /// oblique parent hashes.
+    ///
+    /// The result is identical for all attestations sharing a slot and oblique parent hashes,
+    /// so callers may compute it once and cache it. If the context has a `parent_hashes_cache`,
+    /// the result is read from (or stored in) the cache.
+    pub fn derive_parent_hashes(&self, attestation_slot: u64, oblique_parent_hashes: &[Hash256])
+        -> Result<Vec<Hash256>, AttestationValidationError>
+    {
+        let parent_hashes = self.cached_parent_hashes(attestation_slot, oblique_parent_hashes)?;
+        Ok(Arc::try_unwrap(parent_hashes).unwrap_or_else(|parent_hashes| (*parent_hashes).clone()))
+    }
+
+    /// The cycle length in effect at `slot`, according to `epoch_params` if set.
+    fn cycle_length_at(&self, slot: u64) -> CycleLength {
+        match self.epoch_params {
+            Some(ref epoch_params) => epoch_params.cycle_length_at(slot),
+            None => self.cycle_length,
+        }
+    }
+
+    /// As per `derive_parent_hashes`, however a cached result is not copied.
+    fn cached_parent_hashes(&self, attestation_slot: u64, oblique_parent_hashes: &[Hash256])
+        -> Result<Arc<Vec<Hash256>>, AttestationValidationError>
+    {
+        let cache = match self.parent_hashes_cache {
+            Some(ref cache) => cache,
+            None => {
+                return Ok(Arc::new(attestation_parent_hashes(
+                    self.cycle_length_at(attestation_slot),
+                    self.block_slot,
+                    attestation_slot,
+                    &*self.parent_hashes,
+                    oblique_parent_hashes,
+                    &self.genesis_hash)?));
+            }
+        };
+
+        let key = (self.block_slot, attestation_slot, oblique_parent_hashes.to_vec());
+
         /*
-         * The slot of this attestation must not be more than cycle_length + 1 distance
-         * from the parent_slot of block that contained it.
+         * A poisoned cache is ignored, the parent hashes are derived without it.
          */
-        if a.slot < self.parent_block_slot
-            .saturating_sub(u64::from(self.cycle_length).saturating_add(1)) {
-            return Err(AttestationValidationError::ParentSlotTooLow);
+        if let Some(parent_hashes) = cache.lock().ok().and_then(|cache| cache.get(&key).cloned()) {
+            return Ok(parent_hashes);
         }
 
         /*
-         * The attestation must indicate that its last justified slot is the same as the last justified
-         * slot known to us.
+         * The lock is not held during derivation so that concurrent validations are not
+         * serialized. Two threads may derive the same parent hashes, the result is identical.
          */
-        if a.justified_slot > self.last_justified_slot {
-            return Err(AttestationValidationError::JustifiedSlotIncorrect);
+        let parent_hashes = Arc::new(attestation_parent_hashes(
+            self.cycle_length_at(attestation_slot),
+            self.block_slot,
+            attestation_slot,
+            &*self.parent_hashes,
+            oblique_parent_hashes,
+            &self.genesis_hash)?);
+        if let Ok(mut cache) = cache.lock() {
+            cache.insert(key, parent_hashes.clone());
         }
+        Ok(parent_hashes)
+    }
+
+    /// Return the message which the aggregate signature of `a` must sign across in this context.
+    ///
+    /// The slot and oblique parent hash checks required to derive the parent hashes are performed,
+    /// however no other part of the attestation is validated. This is useful for comparing the
+    /// message expected here against that which was signed by a validator.
+    pub fn signed_message_for(&self, a: &AttestationRecord)
+        -> Result<Vec<u8>, AttestationValidationError>
+    {
+        check_attestation_slot(
+            a.slot,
+            self.parent_block_slot,
+            self.max_future_slots,
+            self.cycle_length_at(a.slot).max_parent_slot_distance())?;
+        check_oblique_hash_count(
+            a.oblique_parent_hashes.len(),
+            u8::from(self.cycle_length_at(a.slot)),
+            self.block_slot,
+            a.slot)?;
+        let parent_hashes = self.derive_parent_hashes(a.slot, &a.oblique_parent_hashes)?;
+        Ok(attestation_signed_message(a, &parent_hashes, self.attestation_domain))
+    }
+
+    fn validate_structure_with_cache<'a>(
+        &'a self,
+        a: &AttestationRecord,
+        cache: &mut ValidationCache<'a>)
+        -> Result<AttestationStructure<'a>, AttestationValidationError>
+    {
+        let attestation_indices = self.verify_committee_structure(a, cache)?;
 
         /*
-         * There is no need to include more oblique parents hashes than there are blocks
-         * in a cycle.
+         * If the context tracks validator activity, reject votes from validators who were not yet
+         * activated or had already exited at the attestation slot.
          */
-        if a.oblique_parent_hashes.len() > usize::from(self.cycle_length) {
-            return Err(AttestationValidationError::TooManyObliqueHashes);
+        if self.require_active_validators {
+            self.verify_voters_active(a, attestation_indices)?;
         }
 
+        let parent_hashes = self.parent_hashes_with_cache(a, cache)?;
+
+        let start = cache.start_timer();
+        let justified_block = self.verify_justified_block(a, &parent_hashes, cache);
+        cache.record_timing(start, |timings| &mut timings.block_store);
+        justified_block?;
+
+        Ok(AttestationStructure {
+            parent_hashes,
+            attestation_indices,
+        })
+    }
+
+    /// Perform the checks of `validate_attestation` which precede the lookup of the attestation
+    /// indices, then verify the attester bitfield against those indices.
+    ///
+    /// None of these checks read the `block_store` or `validator_store`.
+    fn verify_committee_structure<'a>(
+        &'a self,
+        a: &AttestationRecord,
+        cache: &mut ValidationCache<'a>)
+        -> Result<&'a Vec<usize>, AttestationValidationError>
+    {
+        self.verify_attestation_slot(a)?;
+        self.verify_justified_slot(a)?;
+        self.verify_oblique_hashes_permitted(a)?;
+        self.verify_oblique_hash_count(a)?;
+        self.verify_unique_oblique_hashes(a)?;
+        self.verify_shard_id(a)?;
+        let attestation_indices = self.attestation_indices_with_cache(a, cache)?;
+        self.verify_committee_size(a, attestation_indices)?;
+        self.verify_attester_bitfield(a, attestation_indices)?;
+        self.verify_bitfield_has_voters(a)?;
+        self.verify_voters_not_blacklisted(a, attestation_indices)?;
+        Ok(attestation_indices)
+    }
+
+    /// Generate the parent hashes for this attestation.
+    fn parent_hashes_with_cache(&self, a: &AttestationRecord, cache: &mut ValidationCache)
+        -> Result<Arc<Vec<Hash256>>, AttestationValidationError>
+    {
+        let start = cache.start_timer();
+        let parent_hashes = self.cached_parent_hashes(a.slot, &a.oblique_parent_hashes);
+        cache.record_timing(start, |timings| &mut timings.parent_hashes);
+        parent_hashes
+            .map_err(|e| {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): unable to derive parent \
+                        hashes: {:?}", a.slot, a.shard_id, e);
+                e
+            })
+    }
+
+    /// Report every check of `validate_attestation` which the attestation fails, rather than only
+    /// the first.
+    ///
+    /// Checks which depend upon another check (e.g., signature verification depends upon the
+    /// shape of the bitfield) are skipped if that check fails. An empty vec indicates that the
+    /// attestation is valid.
+    ///
+    /// This is intended for diagnosing a rejected attestation, use `validate_attestation` to
+    /// validate attestations.
+    pub fn explain_attestation(&self, a: &AttestationRecord)
+        -> Vec<AttestationValidationError>
+    {
+        let a = &*self.preprocess(a);
+        let mut errors = vec![];
+        let mut cache = ValidationCache::default();
+
+        let slot_valid = push_err(&mut errors, self.verify_attestation_slot(a));
+        push_err(&mut errors, self.verify_justified_slot(a));
+        push_err(&mut errors, self.verify_oblique_hashes_permitted(a));
+        let oblique_count_valid = push_err(&mut errors, self.verify_oblique_hash_count(a));
+        push_err(&mut errors, self.verify_unique_oblique_hashes(a));
+        push_err(&mut errors, self.verify_shard_id(a));
+
         /*
-         * Retrieve the set of attestation indices for this slot and shard id.
-         *
-         * This is an array mapping the order that validators will appear in the bitfield to the
-         * canonincal index of a validator.
+         * The committee and bitfield checks require the attestation indices.
          */
-        let attestation_indices = self.attester_map.get(&(a.slot, a.shard_id))
-            .ok_or(AttestationValidationError::BadAttesterMap)?;
+        let mut bitfield_valid = false;
+        let attestation_indices = self.attestation_indices_with_cache(a, &mut cache);
+        let attestation_indices = match attestation_indices {
+            Ok(attestation_indices) => {
+                push_err(&mut errors, self.verify_committee_size(a, attestation_indices));
+                bitfield_valid = push_err(
+                    &mut errors,
+                    self.verify_attester_bitfield(a, attestation_indices));
+                bitfield_valid = bitfield_valid &&
+                    push_err(&mut errors, self.verify_bitfield_has_voters(a));
+                if bitfield_valid {
+                    push_err(&mut errors,
+                             self.verify_voters_not_blacklisted(a, attestation_indices));
+                }
+                if bitfield_valid && self.require_active_validators {
+                    push_err(&mut errors, self.verify_voters_active(a, attestation_indices));
+                }
+                Some(attestation_indices)
+            }
+            Err(e) => {
+                errors.push(e);
+                None
+            }
+        };
 
         /*
-         * The bitfield must be no longer than the minimum required to represent each validator in the
-         * attestation indices for this slot and shard id.
+         * The parent hashes may only be derived from a permitted slot and number of oblique
+         * parent hashes.
          */
-        if a.attester_bitfield.num_bytes() !=
-            bytes_for_bits(attestation_indices.len())
-        {
-            return Err(AttestationValidationError::BadBitfieldLength);
-       }
+        if !(slot_valid && oblique_count_valid) {
+            return errors;
+        }
+        let parent_hashes = match self.cached_parent_hashes(a.slot, &a.oblique_parent_hashes) {
+            Ok(parent_hashes) => parent_hashes,
+            Err(e) => {
+                errors.push(e);
+                return errors;
+            }
+        };
+        push_err(&mut errors, self.verify_justified_block(a, &parent_hashes, &mut cache));
 
         /*
-         * If there are excess bits in the bitfield because the number of a validators in not a
-         * multiple of 8, reject this attestation record.
-         *
-         * Allow extra set bits would permit mutliple different byte layouts (and therefore hashes) to
-         * refer to the same AttesationRecord.
+         * The signature may only be verified with a correctly shaped bitfield.
          */
-        if a.attester_bitfield.len() > attestation_indices.len() {
-            return Err(AttestationValidationError::InvalidBitfieldEndBits)
+        if let (Some(attestation_indices), true) = (attestation_indices, bitfield_valid) {
+            let signed_message =
+                attestation_signed_message(a, &parent_hashes, self.attestation_domain);
+            let voters = verify_aggregate_signature_for_indices_count(
+                &signed_message,
+                &a.aggregate_sig,
+                attestation_indices,
+                &a.attester_bitfield,
+                &self.validator_store,
+                &*self.signature_scheme);
+            match voters {
+                Ok(Some(voters)) => {
+                    push_err(
+                        &mut errors,
+                        self.verify_participation(a, voters, attestation_indices.len()));
+                }
+                Ok(None) => errors.push(AttestationValidationError::BadAggregateSignature),
+                Err(e) => errors.push(e.into()),
+            }
         }
 
+        errors
+    }
+
+    /// Find the voters of an attestation which failed with `BadAggregateSignature` whose own
+    /// signature does not verify, returning their canonical ValidatorRecord indices.
+    ///
+    /// An aggregate signature cannot be split into the signatures of its signers, so these must be
+    /// supplied as `signatures`, by the canonical index of the signer (e.g., as collected from
+    /// unaggregated attestations). Each voter is verified individually against the message of the
+    /// attestation. A voter without a signature in `signatures` cannot be cleared, so is returned.
+    ///
+    /// The structure of the attestation is validated first, however the aggregate signature is
+    /// not verified.
+    ///
+    /// This is expensive (one signature verification per voter) and intended only for debugging,
+    /// use `validate_attestation` to validate attestations.
+    pub fn diagnose_signature_failure(
+        &self,
+        a: &AttestationRecord,
+        signatures: &HashMap<usize, Signature>)
+        -> Result<HashSet<usize>, AttestationValidationError>
+    {
+        let a = &*self.preprocess(a);
+        let AttestationStructure {
+            parent_hashes,
+            attestation_indices,
+        } = self.validate_structure_with_cache(a, &mut ValidationCache::default())?;
+
+        let (voter_indices, pub_keys) = voter_public_keys_for_indices(
+            attestation_indices,
+            &a.attester_bitfield,
+            &self.validator_store)?;
+        let signed_message = attestation_signed_message(a, &parent_hashes, self.attestation_domain);
+
         /*
-         * Generate the parent hashes for this attestation
+         * Each signature is verified as an aggregate of one so that `self.signature_scheme` is
+         * used, as it is for the aggregate.
          */
-        let parent_hashes = attestation_parent_hashes(
-            self.cycle_length,
-            self.block_slot,
+        let bad_signers = voter_indices.into_iter()
+            .zip(pub_keys)
+            .filter(|(validator, pub_key)| {
+                match signatures.get(validator) {
+                    Some(sig) => {
+                        let mut agg_sig = AggregateSignature::new();
+                        agg_sig.add(sig);
+                        !self.signature_scheme.aggregate_verify(
+                            &signed_message,
+                            &agg_sig,
+                            slice::from_ref(pub_key))
+                    }
+                    None => true,
+                }
+            })
+            .map(|(validator, _)| validator)
+            .collect::<HashSet<usize>>();
+
+        debug!("Diagnosed attestation (slot: {}, shard_id: {}): bad signers: {:?}",
+               a.slot, a.shard_id, bad_signers);
+        Ok(bad_signers)
+    }
+
+    /// Verify that the attesation slot is less than or equal to the parent of the slot of the
+    /// block that contained the attestation (plus any permitted future slots) and is not more
+    /// than cycle_length + 1 distance from it.
+    fn verify_attestation_slot(&self, a: &AttestationRecord)
+        -> Result<(), AttestationValidationError>
+    {
+        check_attestation_slot(
             a.slot,
-            &self.parent_hashes,
-            &a.oblique_parent_hashes)?;
+            self.parent_block_slot,
+            self.max_future_slots,
+            self.cycle_length_at(a.slot).max_parent_slot_distance())
+            .map_err(|e| {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): slot is not permitted \
+                        with parent block slot {}: {:?}", a.slot, a.shard_id,
+                        self.parent_block_slot, e);
+                e.into()
+            })
+    }
+
+    /// Verify that the attestation indicates that its last justified slot is the same as the last
+    /// justified slot known to us, or one of the justified slots permitted by the context.
+    fn verify_justified_slot(&self, a: &AttestationRecord)
+        -> Result<(), AttestationValidationError>
+    {
+        if let Some(ref allowed) = self.allowed_justified_slots {
+            if !allowed.contains(&a.justified_slot) {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): justified slot {} is not \
+                        permitted", a.slot, a.shard_id, a.justified_slot);
+                return Err(AttestationValidationError::JustifiedSlotIncorrect);
+            }
+        } else if let Err(e) = check_justified_slot(a.justified_slot, self.last_justified_slot) {
+            debug!("Rejected attestation (slot: {}, shard_id: {}): justified slot {} is above last \
+                    justified slot {}", a.slot, a.shard_id, a.justified_slot,
+                    self.last_justified_slot);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Verify that the attestation has no oblique parent hashes, if they are forbidden by the
+    /// context.
+    fn verify_oblique_hashes_permitted(&self, a: &AttestationRecord)
+        -> Result<(), AttestationValidationError>
+    {
+        if self.forbid_oblique && !a.oblique_parent_hashes.is_empty() {
+            debug!("Rejected attestation (slot: {}, shard_id: {}): {} oblique parent hashes are \
+                    forbidden", a.slot, a.shard_id, a.oblique_parent_hashes.len());
+            return Err(AttestationValidationError::ObliqueHashesForbidden);
+        }
+        Ok(())
+    }
+
+    /// Verify that the number of oblique parent hashes is within the bounds set by the cycle
+    /// length and the distance between the attestation slot and the block slot.
+    ///
+    /// The attestation slot is checked to be less than the block slot when the parent hashes are
+    /// derived.
+    fn verify_oblique_hash_count(&self, a: &AttestationRecord)
+        -> Result<(), AttestationValidationError>
+    {
+        check_oblique_hash_count(
+            a.oblique_parent_hashes.len(),
+            u8::from(self.cycle_length_at(a.slot)),
+            self.block_slot,
+            a.slot)
+            .map_err(|e| {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): {} oblique parent hashes \
+                        not permitted at block slot {}: {:?}", a.slot, a.shard_id,
+                        a.oblique_parent_hashes.len(), self.block_slot, e);
+                e.into()
+            })
+    }
+
+    /// Verify that each oblique parent hash is unique.
+    fn verify_unique_oblique_hashes(&self, a: &AttestationRecord)
+        -> Result<(), AttestationValidationError>
+    {
+        let unique_oblique_hashes: HashSet<&Hash256> = a.oblique_parent_hashes.iter().collect();
+        if unique_oblique_hashes.len() != a.oblique_parent_hashes.len() {
+            debug!("Rejected attestation (slot: {}, shard_id: {}): duplicate oblique parent hashes",
+                   a.slot, a.shard_id);
+            return Err(AttestationValidationError::DuplicateObliqueHashes);
+        }
+        Ok(())
+    }
+
+    /// Verify that the shard id refers to an existing shard, if the context has a shard count.
+    fn verify_shard_id(&self, a: &AttestationRecord)
+        -> Result<(), AttestationValidationError>
+    {
+        if let Some(shard_count) = self.shard_count {
+            if ShardId::new(a.shard_id, shard_count).is_none() {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): shard id is not less than \
+                        shard count {}", a.slot, a.shard_id, shard_count);
+                return Err(AttestationValidationError::ShardIdOutOfRange {
+                    shard_id: a.shard_id,
+                    shard_count,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieve the set of attestation indices for the slot and shard id of the attestation.
+    ///
+    /// This is an array mapping the order that validators will appear in the bitfield to the
+    /// canonincal index of a validator.
+    fn attestation_indices_with_cache<'a>(
+        &'a self,
+        a: &AttestationRecord,
+        cache: &mut ValidationCache<'a>)
+        -> Result<&'a Vec<usize>, AttestationValidationError>
+    {
+        let attester_map = &self.attester_map;
+        let attestation_indices = *cache.attestation_indices
+            .entry((a.slot, a.shard_id))
+            .or_insert_with(|| attester_map.get(&(a.slot, a.shard_id)));
+        match attestation_indices {
+            Some(attestation_indices) => Ok(attestation_indices),
+            None => {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): no attester map entry",
+                       a.slot, a.shard_id);
+                Err(AttestationValidationError::BadAttesterMap {
+                    slot: a.slot,
+                    shard_id: a.shard_id,
+                })
+            }
+        }
+    }
+
+    /// Verify that the committee is neither empty nor larger than permitted by the context.
+    fn verify_committee_size(&self, a: &AttestationRecord, attestation_indices: &[usize])
+        -> Result<(), AttestationValidationError>
+    {
+        /*
+         * An empty committee would permit a single zero byte as a bitfield, representing no voters
+         * at all. Reject it before it reaches the bitfield and signature checks.
+         */
+        if attestation_indices.is_empty() {
+            debug!("Rejected attestation (slot: {}, shard_id: {}): attester map entry is empty",
+                   a.slot, a.shard_id);
+            return Err(AttestationValidationError::EmptyCommittee {
+                slot: a.slot,
+                shard_id: a.shard_id,
+            });
+        }
+
+        /*
+         * Bound the number of public keys which may be loaded and aggregated to verify the
+         * signature.
+         */
+        if let Some(max_attesters) = self.max_attesters {
+            if attestation_indices.len() > max_attesters {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): {} attesters exceeds \
+                        maximum {}", a.slot, a.shard_id, attestation_indices.len(),
+                        max_attesters);
+                return Err(AttestationValidationError::TooManyAttesters {
+                    attesters: attestation_indices.len(),
+                    max_attesters,
+                });
+            }
+        }
+
+        /*
+         * Shards may have different committee sizes, so each may have its own limit.
+         */
+        let max = self.max_committee_sizes.as_ref()
+            .and_then(|max_committee_sizes| max_committee_sizes.get(&a.shard_id));
+        if let Some(&max) = max {
+            if attestation_indices.len() > max {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): committee of {} exceeds \
+                        shard maximum {}", a.slot, a.shard_id, attestation_indices.len(), max);
+                return Err(AttestationValidationError::CommitteeTooLarge {
+                    shard_id: a.shard_id,
+                    size: attestation_indices.len(),
+                    max,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify that the attester bitfield is the correct shape for the committee.
+    fn verify_attester_bitfield(&self, a: &AttestationRecord, attestation_indices: &[usize])
+        -> Result<(), AttestationValidationError>
+    {
+        verify_bitfield_shape(&a.attester_bitfield, attestation_indices.len())
+            .map_err(|e| {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): bitfield of {} bytes is \
+                        invalid for {} attesters: {:?}", a.slot, a.shard_id,
+                        a.attester_bitfield.num_bytes(), attestation_indices.len(), e);
+                e
+            })
+    }
+
+    /// Verify that at least one bit of the attester bitfield is set, and that no bit beyond its
+    /// length is set.
+    ///
+    /// An attestation without voters is useless, so it is rejected before any reads of the block
+    /// store or signature verification.
+    fn verify_bitfield_has_voters(&self, a: &AttestationRecord)
+        -> Result<(), AttestationValidationError>
+    {
+        verify_bitfield_bits(&a.attester_bitfield)
+            .map_err(|e| {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): bitfield of {} bits is \
+                        invalid: {:?}", a.slot, a.shard_id, a.attester_bitfield.len(), e);
+                e
+            })
+    }
+
+    /// Verify that no validator whose bit is set in the attester bitfield is in the blacklist, if
+    /// any.
+    fn verify_voters_not_blacklisted(&self, a: &AttestationRecord, attestation_indices: &[usize])
+        -> Result<(), AttestationValidationError>
+    {
+        let blacklist = match self.blacklist {
+            Some(ref blacklist) => blacklist,
+            None => return Ok(()),
+        };
+        for i in a.attester_bitfield.iter_set_bits() {
+            let validator = *attestation_indices.get(i)
+                .ok_or(AttestationValidationError::ValidatorIndexOutOfBounds(i))?;
+            if blacklist.contains(&validator) {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): validator {} is \
+                        blacklisted", a.slot, a.shard_id, validator);
+                return Err(AttestationValidationError::BlacklistedValidator(validator));
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify that each validator whose bit is set in the attester bitfield was active at the
+    /// attestation slot.
+    fn verify_voters_active(&self, a: &AttestationRecord, attestation_indices: &[usize])
+        -> Result<(), AttestationValidationError>
+    {
+        for i in a.attester_bitfield.iter_set_bits() {
+            let validator = *attestation_indices.get(i)
+                .ok_or(AttestationValidationError::ValidatorIndexOutOfBounds(i))?;
+            if !self.validator_store.is_active_at_slot(validator, a.slot)? {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): validator {} was not \
+                        active", a.slot, a.shard_id, validator);
+                return Err(AttestationValidationError::InactiveValidator(validator));
+            }
+        }
+        Ok(())
+    }
+
+    /// As per `verify_voters_active`, reading validator activity from `db`.
+    ///
+    /// Resolves immediately if the context does not require active validators.
+    #[cfg(feature = "tokio")]
+    fn verify_voters_active_async<'a, D>(
+        &self,
+        a: &AttestationRecord,
+        voter_indices: Arc<Vec<usize>>,
+        db: &'a D)
+        -> Box<dyn Future<Item = (), Error = AttestationValidationError> + 'a>
+        where D: AsyncClientDB
+    {
+        if !self.require_active_validators {
+            return Box::new(future::ok(()));
+        }
+        let (slot, shard_id) = (a.slot, a.shard_id);
+        let activity = voter_indices.iter()
+            .map(|validator| {
+                let validator = *validator;
+                db.is_active_at_slot(validator, slot)
+                    .map(move |active| (validator, active))
+            })
+            .collect::<Vec<_>>();
+        Box::new(future::join_all(activity)
+            .map_err(AttestationValidationError::from)
+            .and_then(move |activity| {
+                match activity.into_iter().find(|(_, active)| !active) {
+                    Some((validator, _)) => {
+                        debug!("Rejected attestation (slot: {}, shard_id: {}): validator {} was \
+                                not active", slot, shard_id, validator);
+                        Err(AttestationValidationError::InactiveValidator(validator))
+                    }
+                    None => Ok(()),
+                }
+            }))
+    }
+
+    /// Verify that the fraction of `attesters` who voted is no less than the minimum participation
+    /// of the context, if any.
+    fn verify_participation(&self, a: &AttestationRecord, voters: usize, attesters: usize)
+        -> Result<(), AttestationValidationError>
+    {
+        if let Some(min_participation) = self.min_participation {
+            /*
+             * Attesters cannot be zero, empty committees are rejected during structural
+             * validation.
+             */
+            if (voters as f64) / (attesters as f64) < min_participation {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): {} of {} attesters voted, \
+                        minimum participation is {}", a.slot, a.shard_id, voters, attesters,
+                        min_participation);
+                return Err(AttestationValidationError::LowParticipation {
+                    voters,
+                    attesters,
+                });
+            }
+        }
+        Ok(())
+    }
 
+    /// Verify that the justified block of the attestation is known, is at the justified slot and
+    /// is in the chain of the latest of the `parent_hashes`.
+    fn verify_justified_block<'a>(
+        &'a self,
+        a: &AttestationRecord,
+        parent_hashes: &[Hash256],
+        cache: &mut ValidationCache<'a>)
+        -> Result<(), AttestationValidationError>
+    {
         /*
          * The specified justified block hash supplied in the attestation must be in the chain at
          * the given slot number.
@@ -168,43 +1866,320 @@ impl<T> AttestationValidationContext<T>
          */
         let latest_parent_hash = parent_hashes.last()
             .ok_or(AttestationValidationError::BadCurrentHashes)?;
-        match self.block_store.block_at_slot(&latest_parent_hash, a.justified_slot)? {
-            Some((ref hash, _)) if *hash == a.justified_block_hash.to_vec() => (),
-            _ => return Err(AttestationValidationError::InvalidJustifiedBlockHash)
+        let justified_block_exists = match cache.justified_block_exists
+            .entry(a.justified_block_hash)
+        {
+            Entry::Occupied(entry) => Some(*entry.get()),
+            Entry::Vacant(entry) => {
+                if cache.memoize_block_exists {
+                    let exists = self.block_store.block_exists(&a.justified_block_hash)?;
+                    Some(*entry.insert(exists))
+                } else {
+                    None
+                }
+            }
         };
+        if let Some(exists) = justified_block_exists {
+            check_justified_block_exists(a, exists)?;
+        }
 
         /*
-         * Generate the message that this attestation aggregate signature must sign across.
+         * The justified block must be at the justified slot specified in the attestation record.
          */
-        let signed_message = {
-            generate_signed_message(
-                a.slot,
-                &parent_hashes,
-                a.shard_id,
-                &a.shard_block_hash,
-                a.justified_slot)
+        let justified_block_slot = match cache.justified_block_slots
+            .entry(a.justified_block_hash)
+        {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => {
+                let slot = self.block_store.get_block_slot(&a.justified_block_hash)?;
+                *entry.insert(slot)
+            }
         };
+        check_justified_block_slot(a, justified_block_slot)?;
+        let justified_block_hash = match cache.justified_block_hashes
+            .entry((*latest_parent_hash, a.justified_slot))
+        {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let hash = self.block_store
+                    .block_at_slot(latest_parent_hash, a.justified_slot)?
+                    .map(|(hash, _)| hash);
+                entry.insert(hash)
+            }
+        };
+        check_justified_block_in_chain(a, justified_block_hash.as_ref())
+    }
+
+    /// Perform every check of `validate_attestation` except for verification of the aggregate
+    /// signature.
+    ///
+    /// This is useful for cheaply rejecting malformed attestations before any BLS operations
+    /// are performed. Note: the justified block is still read from the `block_store`.
+    pub fn validate_attestation_structure(&self, a: &AttestationRecord)
+        -> Result<(), AttestationValidationError>
+    {
+        self.validate_structure_with_cache(&self.preprocess(a), &mut ValidationCache::default())
+            .map(|_| ())
+    }
+
+    fn collect_signature_check<'a>(
+        &'a self,
+        a: &AttestationRecord,
+        cache: &mut ValidationCache<'a>)
+        -> Result<SignatureCheck, AttestationValidationError>
+    {
+        let a = &*self.preprocess(a);
+        let AttestationStructure {
+            parent_hashes,
+            attestation_indices,
+        } = self.validate_structure_with_cache(a, cache)?;
 
+        let (voter_indices, pub_keys) = voter_public_keys_for_indices(
+            attestation_indices,
+            &a.attester_bitfield,
+            &self.validator_store)?;
+
+        /*
+         * The signature is not verified until later, however the number of voters is already
+         * known from the bitfield.
+         */
+        self.verify_participation(a, voter_indices.len(), attestation_indices.len())?;
+
+        Ok(SignatureCheck {
+            message: attestation_signed_message(a, &parent_hashes, self.attestation_domain),
+            aggregate_sig: a.aggregate_sig.clone(),
+            pub_keys,
+            voters: voter_indices.into_iter().collect(),
+        })
+    }
+
+    /// Validate the attestation and report the outcome to `self.metrics`, if any.
+    fn validate_attestation_with_cache<'a>(
+        &'a self,
+        a: &AttestationRecord,
+        cache: &mut ValidationCache<'a>)
+        -> Result<AttestationParticipation, AttestationValidationError>
+    {
+        let result = self.verify_attestation_with_cache(&self.preprocess(a), cache);
+        self.record_outcome(result.as_ref().map(|participation| participation.voted.len()));
+        result
+    }
+
+    /// Return a clone of `a` transformed by `self.preprocessor`, or `a` itself if there is no
+    /// preprocessor.
+    fn preprocess<'r>(&self, a: &'r AttestationRecord) -> Cow<'r, AttestationRecord> {
+        match self.preprocessor {
+            Some(ref preprocessor) => {
+                let mut a = a.clone();
+                preprocessor(&mut a);
+                Cow::Owned(a)
+            }
+            None => Cow::Borrowed(a),
+        }
+    }
+
+    /// Report the number of voters of an accepted attestation, or the reason an attestation was
+    /// rejected, to `self.metrics`, if any.
+    fn record_outcome(&self, outcome: Result<usize, &AttestationValidationError>) {
+        if let Some(ref metrics) = self.metrics {
+            match outcome {
+                Ok(voters) => metrics.record_accepted(voters),
+                Err(e) => metrics.record_rejected(e),
+            }
+        }
+    }
+
+    fn verify_attestation_count_with_cache<'a>(
+        &'a self,
+        a: &AttestationRecord,
+        cache: &mut ValidationCache<'a>)
+        -> Result<usize, AttestationValidationError>
+    {
+        let AttestationStructure {
+            parent_hashes,
+            attestation_indices,
+        } = self.validate_structure_with_cache(a, cache)?;
+
+        let signed_message = attestation_signed_message(a, &parent_hashes, self.attestation_domain);
+
+        let voters =
+            verify_aggregate_signature_for_indices_count(
+                &signed_message,
+                &a.aggregate_sig,
+                attestation_indices,
+                &a.attester_bitfield,
+                &self.validator_store,
+                &*self.signature_scheme)?;
+
+        match voters {
+            Some(voters) => {
+                self.verify_participation(a, voters, attestation_indices.len())?;
+                trace!("Validated attestation (slot: {}, shard_id: {}): {} voters",
+                       a.slot, a.shard_id, voters);
+                Ok(voters)
+            }
+            None => {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): invalid aggregate signature",
+                       a.slot, a.shard_id);
+                Err(AttestationValidationError::BadAggregateSignature)
+            }
+        }
+    }
+
+    fn verify_attestation_with_cache<'a>(
+        &'a self,
+        a: &AttestationRecord,
+        cache: &mut ValidationCache<'a>)
+        -> Result<AttestationParticipation, AttestationValidationError>
+    {
+        let AttestationStructure {
+            parent_hashes,
+            attestation_indices,
+        } = self.validate_structure_with_cache(a, cache)?;
+
+        let signed_message = attestation_signed_message(a, &parent_hashes, self.attestation_domain);
+
+        let start = cache.start_timer();
         let voted_hashset =
             verify_aggregate_signature_for_indices(
                 &signed_message,
                 &a.aggregate_sig,
                 &attestation_indices,
                 &a.attester_bitfield,
-                &self.validator_store)?;
+                &self.validator_store,
+                &*self.signature_scheme);
+        cache.record_timing(start, |timings| &mut timings.signature);
+        let voted_hashset = voted_hashset?;
 
         /*
          * If the hashset of voters is None, the signature verification failed.
          */
-        match voted_hashset {
-            None => Err(AttestationValidationError::BadAggregateSignature),
-            Some(hashset) => Ok(hashset),
+        let voted = match voted_hashset {
+            Some(voted) => voted,
+            None => {
+                debug!("Rejected attestation (slot: {}, shard_id: {}): invalid aggregate signature",
+                       a.slot, a.shard_id);
+                return Err(AttestationValidationError::BadAggregateSignature);
+            }
+        };
+
+        self.verify_participation(a, voted.len(), attestation_indices.len())?;
+
+        /*
+         * Collect the validators in the attestation indices whose bit was not set.
+         *
+         * Only bits which map to an attestation index are considered, any padding bits at the end
+         * of the bitfield are ignored.
+         */
+        let not_voted = attestation_indices.iter()
+            .enumerate()
+            .filter(|(i, _)| !a.attester_bitfield.get_bit(*i))
+            .map(|(_, validator)| *validator)
+            .collect();
+
+        trace!("Validated attestation (slot: {}, shard_id: {}): {} voters, parent hashes: {:?}",
+               a.slot, a.shard_id, voted.len(), parent_hashes);
+
+        Ok(AttestationParticipation {
+            voted,
+            not_voted,
+        })
+    }
+}
+
+/// Push the error of `result` (if any) to `errors`, returning true if there was no error.
+fn push_err(
+    errors: &mut Vec<AttestationValidationError>,
+    result: Result<(), AttestationValidationError>)
+    -> bool
+{
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            errors.push(e);
+            false
+        }
+    }
+}
+
+/// Reject an attestation whose justified block is unknown.
+fn check_justified_block_exists(a: &AttestationRecord, exists: bool)
+    -> Result<(), AttestationValidationError>
+{
+    if exists {
+        Ok(())
+    } else {
+        debug!("Rejected attestation (slot: {}, shard_id: {}): justified block {:?} is unknown",
+               a.slot, a.shard_id, a.justified_block_hash);
+        Err(AttestationValidationError::InvalidJustifiedBlockHash)
+    }
+}
+
+/// Reject an attestation whose justified block is not at its justified slot, given the slot of
+/// the justified block (if it is known).
+fn check_justified_block_slot(a: &AttestationRecord, justified_block_slot: Option<u64>)
+    -> Result<(), AttestationValidationError>
+{
+    match justified_block_slot {
+        Some(block_slot) if block_slot != a.justified_slot => {
+            debug!("Rejected attestation (slot: {}, shard_id: {}): justified block is at slot {}, \
+                    not justified slot {}", a.slot, a.shard_id, block_slot, a.justified_slot);
+            Err(AttestationValidationError::JustifiedBlockSlotMismatch {
+                justified_slot: a.justified_slot,
+                block_slot,
+            })
+        }
+        Some(_) => Ok(()),
+        None => check_justified_block_exists(a, false),
+    }
+}
+
+/// Reject an attestation whose justified block is not `block_hash`, the hash of the block at the
+/// justified slot in the chain of the latest parent hash (if there is one).
+fn check_justified_block_in_chain(a: &AttestationRecord, block_hash: Option<&Vec<u8>>)
+    -> Result<(), AttestationValidationError>
+{
+    match block_hash {
+        Some(hash) if *hash == a.justified_block_hash.to_vec() => Ok(()),
+        _ => {
+            debug!("Rejected attestation (slot: {}, shard_id: {}): justified block {:?} is not in \
+                    the chain at justified slot {}", a.slot, a.shard_id, a.justified_block_hash,
+                    a.justified_slot);
+            Err(AttestationValidationError::InvalidJustifiedBlockHash)
         }
     }
 }
 
-fn bytes_for_bits(bits: usize) -> usize {
-    (bits.saturating_sub(1) / 8) + 1
+/// Generate the message that the aggregate signature of `a` must sign across.
+fn attestation_signed_message(a: &AttestationRecord, parent_hashes: &[Hash256], domain: u64)
+    -> Vec<u8>
+{
+    generate_signed_message(
+        a.slot,
+        parent_hashes,
+        a.shard_id,
+        &a.shard_block_hash,
+        a.justified_slot,
+        domain)
+}
+
+/// Verify that `bitfield` is the correct shape to represent the votes of `num_validators`
+/// validators, without reference to any other part of an attestation.
+///
+/// This allows the bitfield of an attestation to be checked (e.g., by a block proposer) before it
+/// is broadcast.
+pub fn verify_bitfield_shape(bitfield: &Bitfield, num_validators: usize)
+    -> Result<(), AttestationValidationError>
+{
+    check_bitfield_shape(bitfield.num_bytes(), bitfield.highest_set_bit(), num_validators)?;
+    Ok(())
+}
+
+/// Verify that `bitfield` has at least one vote (`NoSignatures`) and no set bits beyond its length
+/// (`NonZeroTrailingBits`), without reference to a committee or any cryptography.
+pub fn verify_bitfield_bits(bitfield: &Bitfield) -> Result<(), AttestationValidationError> {
+    check_bitfield_bits(bitfield.len(), bitfield.highest_set_bit())?;
+    Ok(())
 }
 
 impl From<ParentHashesError> for AttestationValidationError {
@@ -224,6 +2199,46 @@ impl From<ParentHashesError> for AttestationValidationError {
     }
 }
 
+impl From<StructuralError> for AttestationValidationError {
+    fn from(e: StructuralError) -> Self {
+        match e {
+            StructuralError::ParentSlotTooHigh { attestation_slot, parent_block_slot }
+                => AttestationValidationError::ParentSlotTooHigh {
+                    attestation_slot,
+                    parent_block_slot,
+                },
+            StructuralError::ParentSlotTooLow { attestation_slot, min_slot }
+                => AttestationValidationError::ParentSlotTooLow {
+                    attestation_slot,
+                    min_slot,
+                },
+            StructuralError::JustifiedSlotIncorrect
+                => AttestationValidationError::JustifiedSlotIncorrect,
+            StructuralError::TooManyObliqueHashes
+                => AttestationValidationError::TooManyObliqueHashes,
+            StructuralError::ObliqueHashesExceedSlotDistance { oblique_hashes, max_oblique_hashes }
+                => AttestationValidationError::ObliqueHashesExceedSlotDistance {
+                    oblique_hashes,
+                    max_oblique_hashes,
+                },
+            StructuralError::ObliqueHashesNotPermittedAtHead
+                => AttestationValidationError::ObliqueHashesNotPermittedAtHead,
+            StructuralError::BadBitfieldLength
+                => AttestationValidationError::BadBitfieldLength,
+            StructuralError::InvalidBitfieldEndBits
+                => AttestationValidationError::InvalidBitfieldEndBits,
+            StructuralError::NoSignatures
+                => AttestationValidationError::NoSignatures,
+            StructuralError::NonZeroTrailingBits
+                => AttestationValidationError::NonZeroTrailingBits,
+            StructuralError::CommitteeExceedsMaxBits(size)
+                => AttestationValidationError::CommitteeExceedsMaxBits(size),
+            StructuralError::IntWrapping
+                => AttestationValidationError::IntWrapping,
+        }
+    }
+}
+
 impl From<BlockAtSlotError> for AttestationValidationError {
     fn from(e: BlockAtSlotError) -> Self {
         match e {
@@ -236,19 +2251,21 @@ impl From<BlockAtSlotError> for AttestationValidationError {
 
 impl From<DBError> for AttestationValidationError {
     fn from(e: DBError) -> Self {
-        AttestationValidationError::DBError(e.message)
+        AttestationValidationError::DBError(e)
     }
 }
 
 impl From<SignatureVerificationError> for AttestationValidationError {
     fn from(e: SignatureVerificationError) -> Self {
         match e {
-            SignatureVerificationError::BadValidatorIndex
-                => AttestationValidationError::BadAttesterMap,
-            SignatureVerificationError::PublicKeyCorrupt
-                => AttestationValidationError::PublicKeyCorrupt,
+            SignatureVerificationError::BadValidatorIndex(i)
+                => AttestationValidationError::ValidatorIndexOutOfBounds(i),
+            SignatureVerificationError::PublicKeyCorrupt(validator)
+                => AttestationValidationError::PublicKeyCorrupt(validator),
             SignatureVerificationError::NoPublicKeyForValidator
                 => AttestationValidationError::NoPublicKeyForValidator,
+            SignatureVerificationError::MalformedAggregateSignature
+                => AttestationValidationError::MalformedAggregateSignature,
             SignatureVerificationError::DBError(s)
                 => AttestationValidationError::DBError(s),
         }
@@ -1,6 +1,4 @@
-extern crate rayon;
-
-use self::rayon::prelude::*;
+use super::rayon::prelude::*;
 
 use std::sync::{
     Arc,
@@ -14,9 +12,11 @@ use super::types::{
     AttestationRecord,
     AttesterMap,
     Block,
+    CycleLength,
     ProposerMap,
 };
 use super::ssz_helpers::attestation_ssz_splitter::{
+    split_attestation_signature,
     split_one_attestation,
     split_all_attestations,
     AttestationSplitError,
@@ -28,6 +28,7 @@ use super::ssz_helpers::ssz_block::{
 use super::db::{
     ClientDB,
     DBError,
+    DBErrorKind,
 };
 use super::db::stores::{
     BlockStore,
@@ -39,6 +40,10 @@ use super::ssz::{
     DecodeError,
 };
 use super::types::Hash256;
+use super::signature_verification::{
+    BlsScheme,
+    decode_aggregate_signature,
+};
 
 #[derive(Debug, PartialEq)]
 pub enum BlockStatus {
@@ -60,7 +65,7 @@ pub enum SszBlockValidationError {
     NoProposerSignature,
     BadProposerMap,
     RwLockPoisoned,
-    DBError(String),
+    DBError(DBError),
 }
 
 /// The context against which a block should be validated.
@@ -70,7 +75,7 @@ pub struct BlockValidationContext<T>
     /// The slot as determined by the system time.
     pub present_slot: u64,
     /// The cycle_length as determined by the chain configuration.
-    pub cycle_length: u8,
+    pub cycle_length: CycleLength,
     /// The last justified slot as per the client's view of the canonical chain.
     pub last_justified_slot: u64,
     /// The last justified block hash as per the client's view of the canonical chain.
@@ -175,8 +180,7 @@ impl<T> BlockValidationContext<T>
         let (first_attestation_ssz, next_index) = split_one_attestation(
             &attestations_ssz,
             0)?;
-        let (first_attestation, _) = AttestationRecord::ssz_decode(
-            &first_attestation_ssz, 0)?;
+        let first_attestation = decode_attestation(&first_attestation_ssz)?;
 
         /*
          * The first attestation may not have oblique hashes.
@@ -223,9 +227,25 @@ impl<T> BlockValidationContext<T>
             cycle_length: self.cycle_length,
             last_justified_slot: self.last_justified_slot,
             parent_hashes: self.parent_hashes.clone(),
+            genesis_hash: Hash256::zero(),
             block_store: self.block_store.clone(),
             validator_store: self.validator_store.clone(),
             attester_map: self.attester_map.clone(),
+            max_future_slots: 0,
+            max_attesters: None,
+            max_committee_sizes: None,
+            metrics: None,
+            shard_count: None,
+            allowed_justified_slots: None,
+            require_active_validators: false,
+            min_participation: None,
+            forbid_oblique: false,
+            parent_hashes_cache: None,
+            signature_scheme: Arc::new(BlsScheme),
+            attestation_domain: 0,
+            preprocessor: None,
+            blacklist: None,
+            epoch_params: None,
         });
 
         /*
@@ -280,19 +300,19 @@ impl<T> BlockValidationContext<T>
                  * If there has not been a failure yet, attempt to serialize and validate the
                  * attestation.
                  */
-                match AttestationRecord::ssz_decode(&attestation_ssz, 0) {
+                match decode_attestation(&attestation_ssz) {
                     /*
                      * Deserialization failed, therefore the block is invalid.
                      */
                     Err(e) => {
                         let mut failure = failure.write().unwrap();
-                        *failure = Some(SszBlockValidationError::from(e));
+                        *failure = Some(e);
                         None
                     }
                     /*
                      * Deserialization succeeded and the attestation should be validated.
                      */
-                    Ok((attestation, _)) => {
+                    Ok(attestation) => {
                         match attestation_validation_context.validate_attestation(&attestation) {
                             /*
                              * Attestation validation failed with some error.
@@ -348,7 +368,7 @@ impl<T> BlockValidationContext<T>
 
 impl From<DBError> for SszBlockValidationError {
     fn from(e: DBError) -> Self {
-        SszBlockValidationError::DBError(e.message)
+        SszBlockValidationError::DBError(e)
     }
 }
 
@@ -361,13 +381,32 @@ impl From<AttestationSplitError> for SszBlockValidationError {
     }
 }
 
+/// Decode a serialized AttestationRecord.
+///
+/// The aggregate signature is checked before the remainder of the record is decoded, so that
+/// bytes which are not a valid BLS signature are reported as `MalformedAggregateSignature` rather
+/// than `BadAttestationSsz`.
+pub fn decode_attestation(attestation_ssz: &[u8])
+    -> Result<AttestationRecord, SszBlockValidationError>
+{
+    let sig_ssz = split_attestation_signature(attestation_ssz)?;
+    decode_aggregate_signature(sig_ssz)
+        .map_err(AttestationValidationError::from)?;
+    let (attestation, _) = AttestationRecord::ssz_decode(attestation_ssz, 0)?;
+    Ok(attestation)
+}
+
 impl From<SszBlockError> for SszBlockValidationError {
     fn from(e: SszBlockError) -> Self {
         match e {
             SszBlockError::TooShort =>
-                SszBlockValidationError::DBError("Bad parent block in db.".to_string()),
+                SszBlockValidationError::DBError(DBError::new(
+                    DBErrorKind::Corruption,
+                    "Bad parent block in db.".to_string())),
             SszBlockError::TooLong =>
-                SszBlockValidationError::DBError("Bad parent block in db.".to_string()),
+                SszBlockValidationError::DBError(DBError::new(
+                    DBErrorKind::Corruption,
+                    "Bad parent block in db.".to_string())),
         }
     }
 }
@@ -0,0 +1,263 @@
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use super::attestation_validation::{
+    AttestationValidationContext,
+    AttestationValidationContextBuilder,
+};
+use super::db::ClientDB;
+use super::db::stores::{
+    BlockReader,
+    BlockStore,
+    ValidatorStore,
+};
+use super::types::{
+    AttesterMap,
+    CycleLength,
+    Hash256,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum ChainHeadError {
+    /// The slot of a block is not above the slot of the head.
+    SlotNotAfterHead {
+        head_slot: u64,
+        slot: u64,
+    },
+    /// The last justified slot of a block is lower than that of the head.
+    JustifiedSlotRegression {
+        previous_justified_slot: u64,
+        justified_slot: u64,
+    },
+}
+
+/// The fields of a block required to advance a `ChainHead`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockHeader {
+    pub slot: u64,
+    pub hash: Hash256,
+    pub last_justified_slot: u64,
+}
+
+/// Tracks the head of a chain as blocks are applied, producing an `AttestationValidationContext`
+/// for the attestations of the next block.
+///
+/// The hashes of the blocks of the most recent `2 * cycle_length` slots are kept, a skipped slot
+/// taking the hash of the block before it. Until the chain is that long, there is one hash for each
+/// slot since genesis.
+pub struct ChainHead<T, B = BlockStore<T>>
+    where T: ClientDB + Sized,
+          B: BlockReader
+{
+    cycle_length: CycleLength,
+    genesis_hash: Hash256,
+    head_slot: u64,
+    head_hash: Hash256,
+    last_justified_slot: u64,
+    /// The hash of the block at each slot up to and including `head_slot`, oldest first.
+    parent_hashes: VecDeque<Hash256>,
+    block_store: Arc<B>,
+    validator_store: Arc<ValidatorStore<T>>,
+}
+
+impl<T, B> ChainHead<T, B>
+    where T: ClientDB,
+          B: BlockReader
+{
+    /// Start tracking a chain with its genesis block (at slot zero) as the head.
+    pub fn new(
+        cycle_length: CycleLength,
+        genesis_hash: Hash256,
+        block_store: Arc<B>,
+        validator_store: Arc<ValidatorStore<T>>)
+        -> Self
+    {
+        Self {
+            cycle_length,
+            genesis_hash,
+            head_slot: 0,
+            head_hash: genesis_hash,
+            last_justified_slot: 0,
+            parent_hashes: vec![genesis_hash].into_iter().collect(),
+            block_store,
+            validator_store,
+        }
+    }
+
+    /// The slot of the most recently applied block.
+    pub fn head_slot(&self) -> u64 {
+        self.head_slot
+    }
+
+    /// The last justified slot of the most recently applied block.
+    pub fn last_justified_slot(&self) -> u64 {
+        self.last_justified_slot
+    }
+
+    /// Make the block described by `header` the head of the chain.
+    ///
+    /// Returns an error (and leaves the head unchanged) if the block is not after the head or its
+    /// last justified slot is lower than that of the head.
+    pub fn apply_block(&mut self, header: &BlockHeader) -> Result<(), ChainHeadError> {
+        if header.slot <= self.head_slot {
+            return Err(ChainHeadError::SlotNotAfterHead {
+                head_slot: self.head_slot,
+                slot: header.slot,
+            });
+        }
+        if header.last_justified_slot < self.last_justified_slot {
+            return Err(ChainHeadError::JustifiedSlotRegression {
+                previous_justified_slot: self.last_justified_slot,
+                justified_slot: header.last_justified_slot,
+            });
+        }
+
+        /*
+         * Skipped slots beyond the length of the ring would be evicted immediately, so they are
+         * never pushed.
+         */
+        let max_len = 2 * usize::from(self.cycle_length);
+        let skipped = min(header.slot - self.head_slot - 1, max_len as u64) as usize;
+        for _ in 0..skipped {
+            self.parent_hashes.push_back(self.head_hash);
+        }
+        self.parent_hashes.push_back(header.hash);
+        while self.parent_hashes.len() > max_len {
+            self.parent_hashes.pop_front();
+        }
+
+        self.head_slot = header.slot;
+        self.head_hash = header.hash;
+        self.last_justified_slot = header.last_justified_slot;
+        Ok(())
+    }
+
+    /// Produce a context in which to validate the attestations of a block whose parent is the
+    /// head.
+    pub fn context(&self, attester_map: Arc<AttesterMap>) -> AttestationValidationContext<T, B> {
+        AttestationValidationContextBuilder::default()
+            .block_slot(self.head_slot + 1)
+            .parent_block_slot(self.head_slot)
+            .cycle_length(self.cycle_length)
+            .last_justified_slot(self.last_justified_slot)
+            .parent_hashes(self.parent_hashes.iter().cloned().collect::<Vec<Hash256>>())
+            .genesis_hash(self.genesis_hash)
+            .block_store(self.block_store.clone())
+            .validator_store(self.validator_store.clone())
+            .attester_map(attester_map)
+            .build()
+            .expect("all required fields are set")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::db::MemoryDB;
+
+    fn chain_head(cycle_length: u8) -> ChainHead<MemoryDB> {
+        let db = Arc::new(MemoryDB::open());
+        ChainHead::new(
+            CycleLength::new(cycle_length).unwrap(),
+            Hash256::from("genesis".as_bytes()),
+            Arc::new(BlockStore::new(db.clone())),
+            Arc::new(ValidatorStore::new(db)))
+    }
+
+    fn header(slot: u64) -> BlockHeader {
+        BlockHeader {
+            slot,
+            hash: Hash256::from(slot),
+            last_justified_slot: 0,
+        }
+    }
+
+    fn context_parent_hashes(head: &ChainHead<MemoryDB>) -> Vec<Hash256> {
+        let context = head.context(Arc::new(AttesterMap::new()));
+        context.parent_hashes.get_range(0, context.parent_hashes.len()).unwrap().into_owned()
+    }
+
+    #[test]
+    fn test_chain_head_apply_blocks() {
+        let mut head = chain_head(4);
+        let genesis_hash = Hash256::from("genesis".as_bytes());
+        assert_eq!(context_parent_hashes(&head), vec![genesis_hash]);
+
+        /*
+         * Until the chain is two cycles long, there is a hash for each slot since genesis.
+         */
+        for slot in 1..3 {
+            head.apply_block(&header(slot)).unwrap();
+        }
+        assert_eq!(context_parent_hashes(&head),
+                   vec![genesis_hash, Hash256::from(1), Hash256::from(2)]);
+
+        /*
+         * Afterwards, the oldest hash is evicted as each block is applied.
+         */
+        for slot in 3..11 {
+            head.apply_block(&header(slot)).unwrap();
+        }
+        let expected: Vec<Hash256> = (3..11).map(Hash256::from).collect();
+        assert_eq!(context_parent_hashes(&head), expected);
+
+        let context = head.context(Arc::new(AttesterMap::new()));
+        assert_eq!(context.block_slot, 11);
+        assert_eq!(context.parent_block_slot, 10);
+        assert_eq!(context.genesis_hash, genesis_hash);
+
+        /*
+         * The derived context provides the hashes of the cycle preceding an attestation slot.
+         */
+        let expected: Vec<Hash256> = (6..10).map(Hash256::from).collect();
+        assert_eq!(context.derive_parent_hashes(10, &[]).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_chain_head_skipped_slots() {
+        let mut head = chain_head(4);
+        for slot in 1..11 {
+            head.apply_block(&header(slot)).unwrap();
+        }
+
+        /*
+         * Skipped slots take the hash of the block before them.
+         */
+        head.apply_block(&header(13)).unwrap();
+        let expected: Vec<Hash256> = [6, 7, 8, 9, 10, 10, 10, 13].iter()
+            .map(|slot| Hash256::from(*slot as u64))
+            .collect();
+        assert_eq!(context_parent_hashes(&head), expected);
+
+        /*
+         * Skipping more than two cycles leaves only the hash of the previous head.
+         */
+        head.apply_block(&header(1000)).unwrap();
+        let mut expected = vec![Hash256::from(13); 7];
+        expected.push(Hash256::from(1000));
+        assert_eq!(context_parent_hashes(&head), expected);
+        assert_eq!(head.head_slot(), 1000);
+    }
+
+    #[test]
+    fn test_chain_head_invalid_blocks() {
+        let mut head = chain_head(4);
+        head.apply_block(&BlockHeader { last_justified_slot: 2, ..header(5) }).unwrap();
+
+        assert_eq!(head.apply_block(&header(5)), Err(ChainHeadError::SlotNotAfterHead {
+            head_slot: 5,
+            slot: 5,
+        }));
+        assert_eq!(head.apply_block(&header(6)), Err(ChainHeadError::JustifiedSlotRegression {
+            previous_justified_slot: 2,
+            justified_slot: 0,
+        }));
+
+        /*
+         * A rejected block does not modify the head.
+         */
+        assert_eq!(head.head_slot(), 5);
+        assert_eq!(head.last_justified_slot(), 2);
+        assert_eq!(context_parent_hashes(&head).len(), 6);
+    }
+}
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+use super::attestation_validation::AttestationValidationContext;
+use super::db::ClientDB;
+use super::db::stores::{
+    BlockReader,
+    BlockStore,
+    ValidatorStore,
+};
+use super::parent_hash_provider::ParentHashProvider;
+use super::signature_verification::BlsScheme;
+use super::types::{
+    AttesterMap,
+    CycleLength,
+    Hash256,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum ChainValidationError {
+    /// The last justified slot of a block is lower than that of the block before it.
+    JustifiedSlotRegression {
+        previous_justified_slot: u64,
+        justified_slot: u64,
+    },
+}
+
+/// Tracks the last justified slot whilst a sequence of blocks is validated, producing an
+/// `AttestationValidationContext` for each block.
+///
+/// The last justified slot must never decrease from one block to the next.
+pub struct ChainValidationState<T, B = BlockStore<T>>
+    where T: ClientDB + Sized,
+          B: BlockReader
+{
+    cycle_length: CycleLength,
+    last_justified_slot: u64,
+    block_store: Arc<B>,
+    validator_store: Arc<ValidatorStore<T>>,
+}
+
+impl<T, B> ChainValidationState<T, B>
+    where T: ClientDB,
+          B: BlockReader
+{
+    /// Start tracking a chain at some known `last_justified_slot` (e.g., zero at genesis).
+    pub fn new(
+        cycle_length: CycleLength,
+        last_justified_slot: u64,
+        block_store: Arc<B>,
+        validator_store: Arc<ValidatorStore<T>>)
+        -> Self
+    {
+        Self {
+            cycle_length,
+            last_justified_slot,
+            block_store,
+            validator_store,
+        }
+    }
+
+    /// The last justified slot of the most recent block for which a context was produced.
+    pub fn last_justified_slot(&self) -> u64 {
+        self.last_justified_slot
+    }
+
+    /// Produce a context in which to validate the attestations of the next block in the chain.
+    ///
+    /// Returns an error (and leaves the state unchanged) if `last_justified_slot` is lower than
+    /// that of the previous block.
+    pub fn context_for_block(
+        &mut self,
+        block_slot: u64,
+        parent_block_slot: u64,
+        last_justified_slot: u64,
+        parent_hashes: Arc<dyn ParentHashProvider>,
+        attester_map: Arc<AttesterMap>)
+        -> Result<AttestationValidationContext<T, B>, ChainValidationError>
+    {
+        if last_justified_slot < self.last_justified_slot {
+            return Err(ChainValidationError::JustifiedSlotRegression {
+                previous_justified_slot: self.last_justified_slot,
+                justified_slot: last_justified_slot,
+            });
+        }
+        self.last_justified_slot = last_justified_slot;
+
+        Ok(AttestationValidationContext {
+            block_slot,
+            parent_block_slot,
+            cycle_length: self.cycle_length,
+            last_justified_slot,
+            parent_hashes,
+            genesis_hash: Hash256::zero(),
+            block_store: self.block_store.clone(),
+            validator_store: self.validator_store.clone(),
+            attester_map,
+            max_future_slots: 0,
+            max_attesters: None,
+            max_committee_sizes: None,
+            metrics: None,
+            shard_count: None,
+            allowed_justified_slots: None,
+            require_active_validators: false,
+            min_participation: None,
+            forbid_oblique: false,
+            parent_hashes_cache: None,
+            signature_scheme: Arc::new(BlsScheme),
+            attestation_domain: 0,
+            preprocessor: None,
+            blacklist: None,
+            epoch_params: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::db::MemoryDB;
+
+    #[test]
+    fn test_chain_validation_state_justified_slot_regression() {
+        let db = Arc::new(MemoryDB::open());
+        let block_store = Arc::new(BlockStore::new(db.clone()));
+        let validator_store = Arc::new(ValidatorStore::new(db));
+        let mut state: ChainValidationState<MemoryDB> = ChainValidationState::new(
+            CycleLength::new(8).unwrap(),
+            0,
+            block_store,
+            validator_store);
+
+        let parent_hashes: Arc<dyn ParentHashProvider> = Arc::new(vec![Hash256::zero(); 16]);
+        let attester_map = Arc::new(AttesterMap::new());
+
+        /*
+         * The justified slot may stay the same or increase.
+         */
+        for (block_slot, justified_slot) in &[(10, 2), (11, 2), (12, 5)] {
+            let context = state.context_for_block(
+                *block_slot,
+                *block_slot - 1,
+                *justified_slot,
+                parent_hashes.clone(),
+                attester_map.clone()).unwrap();
+            assert_eq!(context.last_justified_slot, *justified_slot);
+        }
+
+        /*
+         * A decrease is rejected and does not modify the state.
+         */
+        let result = state.context_for_block(
+            13,
+            12,
+            4,
+            parent_hashes.clone(),
+            attester_map.clone());
+        assert_eq!(result.err(), Some(ChainValidationError::JustifiedSlotRegression {
+            previous_justified_slot: 5,
+            justified_slot: 4,
+        }));
+        assert_eq!(state.last_justified_slot(), 5);
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::Arc;
+use super::attestation_validation::{
+    AttestationValidationContext,
+    AttestationValidationContextBuilder,
+};
+use super::db::ClientDB;
+use super::db::stores::{
+    BlockReader,
+    BlockStore,
+    ValidatorStore,
+};
+use super::types::{
+    AttesterMap,
+    CycleLength,
+    Hash256,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum CheckpointError {
+    /// Contexts may only be produced for blocks after the checkpoint.
+    BlockSlotNotAfterCheckpoint {
+        checkpoint_slot: u64,
+        block_slot: u64,
+    },
+    /// The parent block slot must not be below the checkpoint slot or above the block slot.
+    InvalidParentBlockSlot,
+    /// The hash of the block at this slot (after the checkpoint) has not been supplied.
+    MissingBlockHash(u64),
+}
+
+/// Produces an `AttestationValidationContext` for blocks after a (e.g., weak subjectivity)
+/// checkpoint, without any knowledge of the chain prior to the checkpoint.
+///
+/// The checkpoint is treated as the genesis of the parent hash window: the hash of each slot at
+/// or before the checkpoint is the checkpoint block hash. The checkpoint is the last justified
+/// slot, so the checkpoint block must be in the block store.
+pub struct CheckpointContext<T, B = BlockStore<T>>
+    where T: ClientDB + Sized,
+          B: BlockReader
+{
+    cycle_length: CycleLength,
+    checkpoint_slot: u64,
+    checkpoint_hash: Hash256,
+    /// The hashes of the blocks at each slot after the checkpoint, in slot order.
+    block_hashes: Vec<Hash256>,
+    block_store: Arc<B>,
+    validator_store: Arc<ValidatorStore<T>>,
+}
+
+impl<T, B> CheckpointContext<T, B>
+    where T: ClientDB,
+          B: BlockReader
+{
+    pub fn new(
+        cycle_length: CycleLength,
+        checkpoint_slot: u64,
+        checkpoint_hash: Hash256,
+        block_store: Arc<B>,
+        validator_store: Arc<ValidatorStore<T>>)
+        -> Self
+    {
+        Self {
+            cycle_length,
+            checkpoint_slot,
+            checkpoint_hash,
+            block_hashes: vec![],
+            block_store,
+            validator_store,
+        }
+    }
+
+    /// Record the hash of the block at the slot following the last recorded block (or the
+    /// checkpoint, if no block has been recorded).
+    ///
+    /// The hash of a skipped slot should be that of the latest block prior to it.
+    pub fn push_block_hash(&mut self, hash: Hash256) {
+        self.block_hashes.push(hash);
+    }
+
+    /// Return the hashes of the `2 * cycle_length` slots preceding `block_slot`, as required by
+    /// `AttestationValidationContext::parent_hashes`.
+    pub fn parent_hashes_for(&self, block_slot: u64)
+        -> Result<Vec<Hash256>, CheckpointError>
+    {
+        if block_slot <= self.checkpoint_slot {
+            return Err(CheckpointError::BlockSlotNotAfterCheckpoint {
+                checkpoint_slot: self.checkpoint_slot,
+                block_slot,
+            });
+        }
+
+        /*
+         * Saturates so that, as at genesis, slots prior to slot zero share the hash of the
+         * checkpoint.
+         */
+        let window = u64::from(self.cycle_length) * 2;
+        let first_slot = block_slot.saturating_sub(window);
+        let padding = (window - (block_slot - first_slot)) as usize;
+
+        let mut parent_hashes = vec![self.checkpoint_hash; padding];
+        for slot in first_slot..block_slot {
+            if slot <= self.checkpoint_slot {
+                parent_hashes.push(self.checkpoint_hash);
+            } else {
+                let i = (slot - self.checkpoint_slot - 1) as usize;
+                let hash = self.block_hashes.get(i)
+                    .ok_or(CheckpointError::MissingBlockHash(slot))?;
+                parent_hashes.push(*hash);
+            }
+        }
+        Ok(parent_hashes)
+    }
+
+    /// Produce a context in which to validate the attestations of a block after the checkpoint.
+    pub fn context_for_block(
+        &self,
+        block_slot: u64,
+        parent_block_slot: u64,
+        attester_map: Arc<AttesterMap>)
+        -> Result<AttestationValidationContext<T, B>, CheckpointError>
+    {
+        let parent_hashes = self.parent_hashes_for(block_slot)?;
+        if parent_block_slot < self.checkpoint_slot || parent_block_slot >= block_slot {
+            return Err(CheckpointError::InvalidParentBlockSlot);
+        }
+
+        Ok(AttestationValidationContextBuilder::default()
+            .block_slot(block_slot)
+            .parent_block_slot(parent_block_slot)
+            .cycle_length(self.cycle_length)
+            .last_justified_slot(self.checkpoint_slot)
+            .parent_hashes(parent_hashes)
+            .genesis_hash(self.checkpoint_hash)
+            .block_store(self.block_store.clone())
+            .validator_store(self.validator_store.clone())
+            .attester_map(attester_map)
+            .build()
+            .expect("all required fields are set"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::db::MemoryDB;
+
+    #[test]
+    fn test_checkpoint_context_parent_hashes() {
+        let db = Arc::new(MemoryDB::open());
+        let block_store = Arc::new(BlockStore::new(db.clone()));
+        let validator_store = Arc::new(ValidatorStore::new(db));
+        let checkpoint_hash = Hash256::from(1000);
+        let mut checkpoint: CheckpointContext<MemoryDB> = CheckpointContext::new(
+            CycleLength::new(4).unwrap(),
+            100,
+            checkpoint_hash,
+            block_store,
+            validator_store);
+
+        assert_eq!(checkpoint.parent_hashes_for(100),
+                   Err(CheckpointError::BlockSlotNotAfterCheckpoint {
+                       checkpoint_slot: 100,
+                       block_slot: 100,
+                   }));
+        assert_eq!(checkpoint.parent_hashes_for(101).unwrap(), vec![checkpoint_hash; 8]);
+        assert_eq!(checkpoint.parent_hashes_for(102), Err(CheckpointError::MissingBlockHash(101)));
+
+        checkpoint.push_block_hash(Hash256::from(101));
+        checkpoint.push_block_hash(Hash256::from(102));
+        let mut expected = vec![checkpoint_hash; 6];
+        expected.push(Hash256::from(101));
+        expected.push(Hash256::from(102));
+        assert_eq!(checkpoint.parent_hashes_for(103).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_checkpoint_context_near_genesis() {
+        let db = Arc::new(MemoryDB::open());
+        let block_store = Arc::new(BlockStore::new(db.clone()));
+        let validator_store = Arc::new(ValidatorStore::new(db));
+        let checkpoint_hash = Hash256::from(1000);
+        let mut checkpoint: CheckpointContext<MemoryDB> = CheckpointContext::new(
+            CycleLength::new(4).unwrap(),
+            0,
+            checkpoint_hash,
+            block_store,
+            validator_store);
+        checkpoint.push_block_hash(Hash256::from(1));
+
+        let mut expected = vec![checkpoint_hash; 7];
+        expected.push(Hash256::from(1));
+        assert_eq!(checkpoint.parent_hashes_for(2).unwrap(), expected);
+    }
+}
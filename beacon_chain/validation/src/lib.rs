@@ -1,12 +1,57 @@
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+
+/*
+ * Without the `std` feature the crate is `no_std` and only `structural_checks` is built, every
+ * other module requires the database, BLS or the standard library.
+ */
+#[cfg(feature = "std")]
 extern crate db;
+#[cfg(feature = "std")]
 extern crate bls;
+#[cfg(feature = "tokio")]
+extern crate futures;
+#[cfg(feature = "std")]
 extern crate hashing;
+#[cfg(feature = "std")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "std")]
+extern crate rayon;
+#[cfg(feature = "std")]
 extern crate ssz;
+#[cfg(feature = "std")]
 extern crate ssz_helpers;
+#[cfg(feature = "std")]
 extern crate types;
 
+#[cfg(feature = "tokio")]
+pub mod async_db;
+#[cfg(feature = "std")]
+pub mod attestation_budget;
+#[cfg(feature = "std")]
 pub mod attestation_validation;
-mod attestation_parent_hashes;
+#[cfg(feature = "std")]
+pub mod attestation_parent_hashes;
+#[cfg(feature = "std")]
 pub mod block_validation;
+#[cfg(feature = "std")]
+pub mod chain_head;
+#[cfg(feature = "std")]
+pub mod chain_validation_state;
+#[cfg(feature = "std")]
+pub mod checkpoint_context;
+#[cfg(feature = "std")]
 mod message_generation;
-mod signature_verification;
+#[cfg(feature = "std")]
+pub mod parent_hash_provider;
+#[cfg(feature = "std")]
+pub mod replay;
+#[cfg(feature = "std")]
+pub mod signature_verification;
+#[cfg(feature = "std")]
+pub mod slashing;
+pub mod structural_checks;
+#[cfg(feature = "std")]
+pub mod validation_metrics;
+#[cfg(feature = "std")]
+pub mod validation_worker;
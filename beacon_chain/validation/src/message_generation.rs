@@ -5,12 +5,17 @@ use super::types::Hash256;
 /// Generates the message used to validate the signature provided with an AttestationRecord.
 ///
 /// Ensures that the signer of the message has a view of the chain that is compatible with ours.
+///
+/// A non-zero `domain` (e.g., one per network) is appended to the message so that a signature
+/// for one domain is not valid in another. A zero domain is not appended, leaving the message
+/// as it was prior to the introduction of domains.
 pub fn generate_signed_message(
     slot: u64,
     parent_hashes: &[Hash256],
     shard_id: u16,
     shard_block_hash: &Hash256,
-    justified_slot: u64)
+    justified_slot: u64,
+    domain: u64)
     -> Vec<u8>
 {
     /*
@@ -28,6 +33,9 @@ pub fn generate_signed_message(
     ssz_stream.append(&shard_id);
     ssz_stream.append(shard_block_hash);
     ssz_stream.append(&justified_slot);
+    if domain != 0 {
+        ssz_stream.append(&domain);
+    }
     let bytes = ssz_stream.drain();
     canonical_hash(&bytes)
 }
@@ -51,7 +59,8 @@ mod tests {
             &parent_hashes,
             shard_id,
             &shard_block_hash,
-            justified_slot);
+            justified_slot,
+            0);
 
         /*
          * Note: this is not some well-known test vector, it's simply the result of running
@@ -67,4 +76,19 @@ mod tests {
 
         assert_eq!(output, expected);
     }
+
+    #[test]
+    fn test_generate_signed_message_domain() {
+        let parent_hashes: Vec<Hash256> = (0..12)
+            .map(|i| Hash256::from(i as u64))
+            .collect();
+        let shard_block_hash = Hash256::from("shard_block_hash".as_bytes());
+        let message = |domain| {
+            generate_signed_message(93, &parent_hashes, 15, &shard_block_hash, 18, domain)
+        };
+
+        assert_ne!(message(0), message(1));
+        assert_ne!(message(1), message(2));
+        assert_eq!(message(1), message(1));
+    }
 }
@@ -0,0 +1,58 @@
+use std::borrow::Cow;
+use super::types::Hash256;
+
+/// Provides the hashes of the blocks preceding the present slot, in ascending slot order.
+///
+/// Allows a validation context to be backed by something other than a `Vec` of all parent hashes
+/// (e.g., a ring buffer or database reads). An implementation which cannot return a contiguous
+/// slice of its own memory may return an owned `Vec` instead.
+pub trait ParentHashProvider: Send + Sync {
+    /// The number of parent hashes available.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return `len` consecutive parent hashes starting at index `start`, or `None` if any are
+    /// unavailable.
+    fn get_range<'a>(&'a self, start: usize, len: usize) -> Option<Cow<'a, [Hash256]>>;
+}
+
+impl ParentHashProvider for [Hash256] {
+    fn len(&self) -> usize {
+        <[Hash256]>::len(self)
+    }
+
+    fn get_range<'a>(&'a self, start: usize, len: usize) -> Option<Cow<'a, [Hash256]>> {
+        let end = start.checked_add(len)?;
+        self.get(start..end).map(Cow::Borrowed)
+    }
+}
+
+impl ParentHashProvider for Vec<Hash256> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn get_range<'a>(&'a self, start: usize, len: usize) -> Option<Cow<'a, [Hash256]>> {
+        self[..].get_range(start, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vec_parent_hash_provider() {
+        let hashes: Vec<Hash256> = (0..4).map(Hash256::from).collect();
+        let provider: &dyn ParentHashProvider = &hashes;
+
+        assert_eq!(provider.len(), 4);
+        assert_eq!(provider.get_range(1, 2).unwrap(), &hashes[1..3]);
+        assert_eq!(provider.get_range(0, 4).unwrap(), &hashes[..]);
+        assert_eq!(provider.get_range(3, 2), None);
+        assert_eq!(provider.get_range(1, usize::max_value()), None);
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::time::{
+    Duration,
+    Instant,
+};
+use super::attestation_validation::{
+    AttestationValidationContext,
+    AttestationValidationError,
+    ValidationErrorCategory,
+};
+use super::db::ClientDB;
+use super::db::stores::BlockReader;
+use super::types::AttestationRecord;
+
+/// The outcome of re-validating a log of attestations with `replay_attestations`.
+#[derive(Debug)]
+pub struct ReplayReport {
+    /// The number of records which were accepted.
+    pub accepted: usize,
+    /// The number of records which were rejected, by the category of their error.
+    pub rejected: HashMap<ValidationErrorCategory, usize>,
+    /// The first rejected records, as their position in the log and their error.
+    pub failures: Vec<(usize, AttestationValidationError)>,
+    /// The time taken to replay the entire log, including the creation of contexts.
+    pub duration: Duration,
+}
+
+impl ReplayReport {
+    /// The total number of records which were rejected.
+    pub fn rejected_count(&self) -> usize {
+        self.rejected.values().sum()
+    }
+}
+
+/// Re-validate each of `records` (e.g., a log of received attestations), reporting the outcomes.
+///
+/// `context_fn` is called with the slot of a record to supply a fresh context in which to
+/// validate it. Consecutive records at the same slot share a context, so a log in slot order
+/// requires one context per slot.
+///
+/// Only the first `max_failures` rejected records are listed in the report, all are tallied.
+pub fn replay_attestations<T, B, F>(
+    mut context_fn: F,
+    records: &[AttestationRecord],
+    max_failures: usize)
+    -> ReplayReport
+    where T: ClientDB,
+          B: BlockReader,
+          F: FnMut(u64) -> AttestationValidationContext<T, B>
+{
+    let start = Instant::now();
+    let mut report = ReplayReport {
+        accepted: 0,
+        rejected: HashMap::new(),
+        failures: vec![],
+        duration: Duration::default(),
+    };
+
+    let mut i = 0;
+    while i < records.len() {
+        let slot = records[i].slot;
+        let context = context_fn(slot);
+
+        while i < records.len() && records[i].slot == slot {
+            match context.validate_attestation(&records[i]) {
+                Ok(_) => report.accepted += 1,
+                Err(e) => {
+                    *report.rejected.entry(e.category()).or_insert(0) += 1;
+                    if report.failures.len() < max_failures {
+                        report.failures.push((i, e));
+                    }
+                }
+            }
+            i += 1;
+        }
+    }
+
+    report.duration = start.elapsed();
+    report
+}
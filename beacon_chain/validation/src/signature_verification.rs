@@ -2,8 +2,12 @@ use std::collections::HashSet;
 use super::bls::{
     AggregateSignature,
     AggregatePublicKey,
+    PublicKey,
+};
+use super::db::{
+    ClientDB,
+    DBError,
 };
-use super::db::ClientDB;
 use super::db::stores::{
     ValidatorStore,
     ValidatorStoreError,
@@ -12,10 +16,68 @@ use super::types::Bitfield;
 
 #[derive(Debug, PartialEq)]
 pub enum SignatureVerificationError {
-    BadValidatorIndex,
-    PublicKeyCorrupt,
+    BadValidatorIndex(usize),
+    PublicKeyCorrupt(usize),
     NoPublicKeyForValidator,
-    DBError(String),
+    /// The bytes of an aggregate signature are not a valid BLS signature.
+    MalformedAggregateSignature,
+    DBError(DBError),
+}
+
+/// A scheme by which an aggregate signature is verified against the public keys of its signers.
+///
+/// Validation uses `BlsScheme` unless some other scheme is supplied, e.g., a mock scheme which
+/// allows validation logic to be tested without real cryptography.
+pub trait SignatureScheme: Send + Sync {
+    /// Returns true if `agg_sig` is a valid signature across `message` by exactly the holders of
+    /// `pub_keys`.
+    fn aggregate_verify(
+        &self,
+        message: &[u8],
+        agg_sig: &AggregateSignature,
+        pub_keys: &[PublicKey])
+        -> bool;
+}
+
+/// Everything required to verify the aggregate signature of an attestation without further
+/// access to the validator store.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureCheck {
+    /// The message which must be signed.
+    pub message: Vec<u8>,
+    /// The aggregate signature of the attestation.
+    pub aggregate_sig: AggregateSignature,
+    /// The public keys of the voters, in the order of their bitfield bits.
+    pub pub_keys: Vec<PublicKey>,
+    /// Canonical indices of the voters.
+    pub voters: HashSet<usize>,
+}
+
+/// Verifies BLS aggregate signatures.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BlsScheme;
+
+impl SignatureScheme for BlsScheme {
+    fn aggregate_verify(
+        &self,
+        message: &[u8],
+        agg_sig: &AggregateSignature,
+        pub_keys: &[PublicKey])
+        -> bool
+    {
+        verify_aggregate_signature(message, agg_sig, pub_keys)
+    }
+}
+
+/// Decode an aggregate signature, rejecting bytes which are not a valid BLS signature (e.g., they
+/// are of the wrong length or are not a point on the curve).
+///
+/// This distinguishes a malformed signature from one which is well-formed but does not verify.
+pub fn decode_aggregate_signature(bytes: &[u8])
+    -> Result<AggregateSignature, SignatureVerificationError>
+{
+    AggregateSignature::from_bytes(bytes)
+        .map_err(|_| SignatureVerificationError::MalformedAggregateSignature)
 }
 
 /// Verify an aggregate signature across the supplied message.
@@ -24,63 +86,173 @@ pub enum SignatureVerificationError {
 /// each true bitfield bit to canonical ValidatorRecord index through
 /// the attestation_indicies map.
 ///
-/// Each public key is loaded from the store on-demand.
-pub fn verify_aggregate_signature_for_indices<T>(
+/// The public keys for all voters are loaded from the store in a single batch, then
+/// verification is delegated to `verify_aggregate_signature_for_pubkeys`.
+pub fn verify_aggregate_signature_for_indices<T, S>(
     message: &[u8],
     agg_sig: &AggregateSignature,
     attestation_indices: &[usize],
     bitfield: &Bitfield,
-    validator_store: &ValidatorStore<T>)
+    validator_store: &ValidatorStore<T>,
+    scheme: &S)
     -> Result<Option<HashSet<usize>>, SignatureVerificationError>
+    where T: ClientDB + Sized,
+          S: SignatureScheme + ?Sized
+{
+    let voter_indices = voter_indices(attestation_indices, bitfield)?;
+    let pub_keys = voter_public_keys(&voter_indices, validator_store)?;
+
+    /*
+     * Only the public keys of voters were loaded, so every bit is set.
+     */
+    let mut voters_bitfield = Bitfield::with_capacity(pub_keys.len());
+    for i in 0..pub_keys.len() {
+        voters_bitfield.set_bit(i, true);
+    }
+
+    let voters = verify_aggregate_signature_for_pubkeys(
+        message,
+        agg_sig,
+        &pub_keys,
+        &voters_bitfield,
+        scheme)?;
+
+    /*
+     * Map the positions in `pub_keys` back to canonical ValidatorRecord indices.
+     */
+    Ok(voters.map(|positions| {
+        positions.into_iter()
+            .map(|i| voter_indices[i])
+            .collect()
+    }))
+}
+
+/// Verify an aggregate signature across the supplied message, returning only the number of voters.
+///
+/// This is identical to `verify_aggregate_signature_for_indices` except that the set of voters is
+/// never collected, for callers which only require the weight of an attestation. The attestation
+/// indices are expected to be distinct, so the count is the number of true bitfield bits which map
+/// to an attestation index.
+pub fn verify_aggregate_signature_for_indices_count<T, S>(
+    message: &[u8],
+    agg_sig: &AggregateSignature,
+    attestation_indices: &[usize],
+    bitfield: &Bitfield,
+    validator_store: &ValidatorStore<T>,
+    scheme: &S)
+    -> Result<Option<usize>, SignatureVerificationError>
+    where T: ClientDB + Sized,
+          S: SignatureScheme + ?Sized
+{
+    let voter_indices = voter_indices(attestation_indices, bitfield)?;
+    let pub_keys = voter_public_keys(&voter_indices, validator_store)?;
+
+    if scheme.aggregate_verify(message, agg_sig, &pub_keys) {
+        Ok(Some(pub_keys.len()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Load the public keys of each validator with a true bit in the bitfield, returning the
+/// canonical ValidatorRecord indices of the voters and their public keys in the same order.
+pub fn voter_public_keys_for_indices<T>(
+    attestation_indices: &[usize],
+    bitfield: &Bitfield,
+    validator_store: &ValidatorStore<T>)
+    -> Result<(Vec<usize>, Vec<PublicKey>), SignatureVerificationError>
     where T: ClientDB + Sized
 {
-    let mut voters = HashSet::new();
-    let mut agg_pub_key = AggregatePublicKey::new();
+    let voter_indices = voter_indices(attestation_indices, bitfield)?;
+    let pub_keys = voter_public_keys(&voter_indices, validator_store)?;
+    Ok((voter_indices, pub_keys))
+}
 
+/// De-reference each attestation index with a true bit into a canonical ValidatorRecord index.
+///
+/// Only the position of a bit is significant, the canonical indices of a committee may have gaps.
+fn voter_indices(attestation_indices: &[usize], bitfield: &Bitfield)
+    -> Result<Vec<usize>, SignatureVerificationError>
+{
+    let mut voter_indices = vec![];
     for i in 0..attestation_indices.len() {
-        let voted = bitfield.get_bit(i);
-        if voted {
-            /*
-             * De-reference the attestation index into a canonical ValidatorRecord index.
-             */
+        if bitfield.get_bit(i) {
             let validator = *attestation_indices.get(i)
-                .ok_or(SignatureVerificationError::BadValidatorIndex)?;
-            /*
-             * Load the validators public key from our store.
-             */
-            let pub_key = validator_store
-                .get_public_key_by_index(validator)?
-                .ok_or(SignatureVerificationError::NoPublicKeyForValidator)?;
-            /*
-             * Add the validators public key to the aggregate public key.
-             */
-            agg_pub_key.add(&pub_key);
-            /*
-             * Add validator to the set of voters for this attestation record.
-             */
-            voters.insert(validator);
+                .ok_or(SignatureVerificationError::BadValidatorIndex(i))?;
+            voter_indices.push(validator);
         }
     }
-    /*
-     * Verify the aggregate public key against the aggregate signature.
-     *
-     * This verification will only succeed if the exact set of public keys
-     * were added to the aggregate public key as those that signed the aggregate signature.
-     */
-    if agg_sig.verify(&message, &agg_pub_key) {
+    Ok(voter_indices)
+}
+
+/// Load the public keys of all voters from the store, in the order of `voter_indices`.
+fn voter_public_keys<T>(voter_indices: &[usize], validator_store: &ValidatorStore<T>)
+    -> Result<Vec<PublicKey>, SignatureVerificationError>
+    where T: ClientDB + Sized
+{
+    validator_store.get_public_keys(voter_indices)?
+        .into_iter()
+        .collect::<Option<Vec<PublicKey>>>()
+        .ok_or(SignatureVerificationError::NoPublicKeyForValidator)
+}
+
+/// Verify an aggregate signature across the supplied message using public keys which are
+/// already in memory.
+///
+/// Each true bitfield bit `i` selects `pub_keys[i]`. Bits beyond the end of `pub_keys` are
+/// ignored.
+///
+/// If the signature is valid, the set of positions (in `pub_keys`) of the voters is returned.
+pub fn verify_aggregate_signature_for_pubkeys<S>(
+    message: &[u8],
+    agg_sig: &AggregateSignature,
+    pub_keys: &[PublicKey],
+    bitfield: &Bitfield,
+    scheme: &S)
+    -> Result<Option<HashSet<usize>>, SignatureVerificationError>
+    where S: SignatureScheme + ?Sized
+{
+    let mut voters = HashSet::new();
+    let mut voter_pub_keys = vec![];
+
+    for (i, pub_key) in pub_keys.iter().enumerate() {
+        if bitfield.get_bit(i) {
+            voters.insert(i);
+            voter_pub_keys.push(pub_key.clone());
+        }
+    }
+
+    if scheme.aggregate_verify(message, agg_sig, &voter_pub_keys) {
         Ok(Some(voters))
     } else {
         Ok(None)
     }
 }
 
+/// Verify an aggregate signature against the aggregate of the supplied public keys.
+///
+/// This verification will only succeed if the exact set of public keys which signed the
+/// aggregate signature is supplied.
+fn verify_aggregate_signature(
+    message: &[u8],
+    agg_sig: &AggregateSignature,
+    pub_keys: &[PublicKey])
+    -> bool
+{
+    let mut agg_pub_key = AggregatePublicKey::new();
+    for pub_key in pub_keys {
+        agg_pub_key.add(pub_key);
+    }
+    agg_sig.verify(message, &agg_pub_key)
+}
+
 impl From<ValidatorStoreError> for SignatureVerificationError {
     fn from(error: ValidatorStoreError) -> Self {
         match error {
             ValidatorStoreError::DBError(s) =>
                 SignatureVerificationError::DBError(s),
-            ValidatorStoreError::DecodeError =>
-                SignatureVerificationError::PublicKeyCorrupt,
+            ValidatorStoreError::DecodeError(validator) =>
+                SignatureVerificationError::PublicKeyCorrupt(validator),
         }
     }
 }
@@ -94,6 +266,7 @@ mod tests {
         Signature,
     };
     use super::super::db::MemoryDB;
+    use std::slice;
     use std::sync::Arc;
 
     /*
@@ -158,7 +331,8 @@ mod tests {
             &agg_sig,
             &attestation_indices,
             &bitfield,
-            &store).unwrap();
+            &store,
+            &BlsScheme).unwrap();
 
         let voters = voters.unwrap();
         (0..signing_keypairs.len())
@@ -176,8 +350,72 @@ mod tests {
             &agg_sig,
             &attestation_indices,
             &bitfield,
-            &store).unwrap();
+            &store,
+            &BlsScheme).unwrap();
 
         assert_eq!(voters, None);
     }
+
+    #[test]
+    fn test_signature_verification_for_pubkeys() {
+        let message = "cats".as_bytes();
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let pub_keys: Vec<PublicKey> = keypairs.iter().map(|k| k.pk.clone()).collect();
+
+        /*
+         * Positions 1 and 3 sign.
+         */
+        let mut bitfield = Bitfield::new();
+        let mut agg_sig = AggregateSignature::new();
+        for i in &[1, 3] {
+            bitfield.set_bit(*i, true);
+            agg_sig.add(&Signature::new(&message, &keypairs[*i].sk));
+        }
+
+        let voters = verify_aggregate_signature_for_pubkeys(
+            &message,
+            &agg_sig,
+            &pub_keys,
+            &bitfield,
+            &BlsScheme).unwrap().unwrap();
+        let expected: HashSet<usize> = [1, 3].iter().cloned().collect();
+        assert_eq!(voters, expected);
+
+        /*
+         * A missing signer causes verification to fail.
+         */
+        bitfield.set_bit(0, true);
+        let voters = verify_aggregate_signature_for_pubkeys(
+            &message,
+            &agg_sig,
+            &pub_keys,
+            &bitfield,
+            &BlsScheme).unwrap();
+        assert_eq!(voters, None);
+    }
+
+    #[test]
+    fn test_bls_scheme_single_signer() {
+        let message = "cats".as_bytes();
+        let keypair = Keypair::random();
+        let other_keypair = Keypair::random();
+
+        let mut agg_sig = AggregateSignature::new();
+        agg_sig.add(&Signature::new(&message, &keypair.sk));
+
+        let mut two_signer_agg_sig = agg_sig.clone();
+        two_signer_agg_sig.add(&Signature::new(&message, &other_keypair.sk));
+
+        let cases = vec![
+            (message, &agg_sig, &keypair.pk, true),
+            (message, &agg_sig, &other_keypair.pk, false),
+            ("dogs".as_bytes(), &agg_sig, &keypair.pk, false),
+            (message, &two_signer_agg_sig, &keypair.pk, false),
+        ];
+
+        for (message, agg_sig, pub_key, expected) in cases {
+            let valid = BlsScheme.aggregate_verify(message, agg_sig, slice::from_ref(pub_key));
+            assert_eq!(valid, expected);
+        }
+    }
 }
@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+use super::types::AttestationRecord;
+
+/// The kind of slashable conflict between two attestations, with the validators who signed both.
+#[derive(Debug, PartialEq)]
+pub enum SlashingKind {
+    /// The attestations do not conflict, or no validator signed both.
+    None,
+    /// The attestations are for the same slot but different shard blocks.
+    DoubleVote(HashSet<usize>),
+    /// The justified slot and slot of one attestation are strictly within those of the other.
+    SurroundVote(HashSet<usize>),
+}
+
+/// Determine whether any validator who signed both `a` and `b` may be slashed for doing so.
+///
+/// `voters_a` and `voters_b` are the (canonical) indices of the validators who signed `a` and
+/// `b` respectively, e.g., as returned from `validate_attestation`. The signatures of the
+/// attestations are not checked.
+pub fn detect_slashing(
+    a: &AttestationRecord,
+    b: &AttestationRecord,
+    voters_a: &HashSet<usize>,
+    voters_b: &HashSet<usize>)
+    -> SlashingKind
+{
+    let voters: HashSet<usize> = voters_a.intersection(voters_b).cloned().collect();
+    if voters.is_empty() {
+        return SlashingKind::None;
+    }
+
+    if a.slot == b.slot && a.shard_block_hash != b.shard_block_hash {
+        return SlashingKind::DoubleVote(voters);
+    }
+
+    if surrounds(a, b) || surrounds(b, a) {
+        return SlashingKind::SurroundVote(voters);
+    }
+
+    SlashingKind::None
+}
+
+/// Returns true if the interval from justified slot to slot of `outer` strictly contains that of
+/// `inner`.
+fn surrounds(outer: &AttestationRecord, inner: &AttestationRecord) -> bool {
+    outer.justified_slot < inner.justified_slot && inner.slot < outer.slot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::Hash256;
+
+    fn attestation(slot: u64, justified_slot: u64, shard_block_hash: &[u8])
+        -> AttestationRecord
+    {
+        let mut a = AttestationRecord::zero();
+        a.slot = slot;
+        a.justified_slot = justified_slot;
+        a.shard_block_hash = Hash256::from(shard_block_hash);
+        a
+    }
+
+    fn voters(indices: &[usize]) -> HashSet<usize> {
+        indices.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_detect_slashing_double_vote() {
+        let a = attestation(10, 5, b"cats");
+        let b = attestation(10, 5, b"dogs");
+
+        assert_eq!(detect_slashing(&a, &b, &voters(&[1, 2, 3]), &voters(&[3, 2, 4])),
+                   SlashingKind::DoubleVote(voters(&[2, 3])));
+
+        /*
+         * Voting for the same shard block twice is not slashable.
+         */
+        assert_eq!(detect_slashing(&a, &a, &voters(&[1, 2, 3]), &voters(&[1, 2, 3])),
+                   SlashingKind::None);
+    }
+
+    #[test]
+    fn test_detect_slashing_surround_vote() {
+        let outer = attestation(20, 5, b"cats");
+        let inner = attestation(15, 10, b"dogs");
+
+        assert_eq!(detect_slashing(&outer, &inner, &voters(&[1, 2]), &voters(&[2])),
+                   SlashingKind::SurroundVote(voters(&[2])));
+        assert_eq!(detect_slashing(&inner, &outer, &voters(&[2]), &voters(&[1, 2])),
+                   SlashingKind::SurroundVote(voters(&[2])));
+
+        /*
+         * Intervals which share an endpoint, or overlap without containment, do not surround.
+         */
+        let shared_justified = attestation(15, 5, b"dogs");
+        assert_eq!(detect_slashing(&outer, &shared_justified, &voters(&[2]), &voters(&[2])),
+                   SlashingKind::None);
+        let overlapping = attestation(25, 10, b"dogs");
+        assert_eq!(detect_slashing(&outer, &overlapping, &voters(&[2]), &voters(&[2])),
+                   SlashingKind::None);
+    }
+
+    #[test]
+    fn test_detect_slashing_no_common_voters() {
+        let a = attestation(10, 5, b"cats");
+        let b = attestation(10, 5, b"dogs");
+
+        assert_eq!(detect_slashing(&a, &b, &voters(&[1, 2]), &voters(&[3, 4])),
+                   SlashingKind::None);
+    }
+}
@@ -0,0 +1,282 @@
+/*
+ * Checks of an attestation which require neither database access nor any cryptography.
+ *
+ * Every function in this module operates only on integers and depends on nothing outside of
+ * `core`. It is the only module built without the `std` feature, so it may be used in a `no_std`
+ * environment (e.g., WASM) via `default-features = false`.
+ *
+ * Arithmetic which overflows because of an invalid input (e.g., a slot near u64::MAX) must use
+ * checked math and return `IntWrapping`. Saturating math is only used where the clamped value is
+ * the intended result, each such case is commented.
+ */
+
+/// The largest committee for which an attester bitfield is considered plausible.
+///
+/// Committees are far smaller than this in practice, a larger committee indicates a malformed
+/// attester map.
+pub const MAX_COMMITTEE_BITS: usize = 1 << 24;
+
+#[derive(Debug, PartialEq)]
+pub enum StructuralError {
+    ParentSlotTooHigh {
+        attestation_slot: u64,
+        parent_block_slot: u64,
+    },
+    ParentSlotTooLow {
+        attestation_slot: u64,
+        min_slot: u64,
+    },
+    JustifiedSlotIncorrect,
+    TooManyObliqueHashes,
+    ObliqueHashesExceedSlotDistance {
+        oblique_hashes: usize,
+        max_oblique_hashes: usize,
+    },
+    ObliqueHashesNotPermittedAtHead,
+    BadBitfieldLength,
+    InvalidBitfieldEndBits,
+    NoSignatures,
+    NonZeroTrailingBits,
+    CommitteeExceedsMaxBits(usize),
+    IntWrapping,
+}
+
+/// Check that an attestation slot is no higher than the parent block slot (plus any permitted
+/// future slots) and no more than `max_parent_slot_distance` below it.
+pub fn check_attestation_slot(
+    attestation_slot: u64,
+    parent_block_slot: u64,
+    max_future_slots: u64,
+    max_parent_slot_distance: u64)
+    -> Result<(), StructuralError>
+{
+    let max_slot = parent_block_slot.checked_add(max_future_slots)
+        .ok_or(StructuralError::IntWrapping)?;
+    if attestation_slot > max_slot {
+        return Err(StructuralError::ParentSlotTooHigh {
+            attestation_slot,
+            parent_block_slot,
+        });
+    }
+
+    /*
+     * Saturates so that any slot is permitted to be "below" the parent block slot near genesis.
+     */
+    let min_slot = parent_block_slot.saturating_sub(max_parent_slot_distance);
+    if attestation_slot < min_slot {
+        return Err(StructuralError::ParentSlotTooLow {
+            attestation_slot,
+            min_slot,
+        });
+    }
+
+    Ok(())
+}
+
+/// Check that the justified slot of an attestation is not above the last justified slot known to
+/// us.
+pub fn check_justified_slot(justified_slot: u64, last_justified_slot: u64)
+    -> Result<(), StructuralError>
+{
+    if justified_slot > last_justified_slot {
+        Err(StructuralError::JustifiedSlotIncorrect)
+    } else {
+        Ok(())
+    }
+}
+
+/// The maximum number of oblique parent hashes an attestation may include, regardless of its
+/// slot.
+///
+/// Oblique parent hashes replace hashes of the cycle preceding the attestation slot, so there is
+/// no need to include more than one per slot of a cycle. Block builders should use this limit so
+/// that they produce the attestations which are accepted by `check_oblique_hash_count`.
+pub fn max_oblique_hashes(cycle_length: u8) -> usize {
+    usize::from(cycle_length)
+}
+
+/// Check the number of oblique parent hashes of an attestation.
+///
+/// There may be no more than `max_oblique_hashes(cycle_length)` oblique parent hashes.
+/// Furthermore, oblique parent hashes replace the most recent hashes of the cycle preceding the
+/// attestation slot and only the hashes of slots within the cycle preceding the block slot can
+/// differ from those known to us, so the further the attestation slot is behind the block slot
+/// the fewer oblique parent hashes are permitted. An attestation at the block slot has no hashes
+/// for oblique parent hashes to replace, so it may have none.
+pub fn check_oblique_hash_count(
+    oblique_hashes: usize,
+    cycle_length: u8,
+    block_slot: u64,
+    attestation_slot: u64)
+    -> Result<(), StructuralError>
+{
+    if oblique_hashes > max_oblique_hashes(cycle_length) {
+        return Err(StructuralError::TooManyObliqueHashes);
+    }
+
+    if oblique_hashes > 0 && attestation_slot == block_slot {
+        return Err(StructuralError::ObliqueHashesNotPermittedAtHead);
+    }
+
+    /*
+     * An attestation slot above the block slot saturates to a distance of zero, it is rejected
+     * when the parent hashes are derived.
+     *
+     * Attestations further than a cycle from the block slot saturate to zero permitted oblique
+     * parent hashes.
+     */
+    let slot_distance = block_slot.saturating_sub(attestation_slot);
+    let max_for_distance = u64::from(cycle_length).saturating_sub(slot_distance) as usize;
+    if oblique_hashes > max_for_distance {
+        return Err(StructuralError::ObliqueHashesExceedSlotDistance {
+            oblique_hashes,
+            max_oblique_hashes: max_for_distance,
+        });
+    }
+
+    Ok(())
+}
+
+/// Check that a bitfield of `num_bytes` bytes with its highest set bit at `highest_set_bit` is
+/// the correct shape to represent the votes of `num_validators` validators.
+pub fn check_bitfield_shape(
+    num_bytes: usize,
+    highest_set_bit: Option<usize>,
+    num_validators: usize)
+    -> Result<(), StructuralError>
+{
+    /*
+     * The bitfield must be no longer than the minimum required to represent each validator in the
+     * attestation indices for this slot and shard id.
+     */
+    if num_bytes != bytes_for_bits(num_validators)? {
+        return Err(StructuralError::BadBitfieldLength);
+    }
+
+    /*
+     * If there are excess bits in the bitfield because the number of a validators in not a
+     * multiple of 8, reject this attestation record.
+     *
+     * Allow extra set bits would permit mutliple different byte layouts (and therefore hashes) to
+     * refer to the same AttesationRecord.
+     */
+    match highest_set_bit {
+        Some(i) if i >= num_validators => Err(StructuralError::InvalidBitfieldEndBits),
+        _ => Ok(()),
+    }
+}
+
+/// Check that a bitfield of `bit_len` bits with its highest set bit at `highest_set_bit` has at
+/// least one vote, and no set bits beyond its length (e.g., in the padding of its final byte).
+///
+/// Unlike `check_bitfield_shape` this requires no knowledge of the committee, so a block builder
+/// may check a bitfield before it is aggregated.
+pub fn check_bitfield_bits(bit_len: usize, highest_set_bit: Option<usize>)
+    -> Result<(), StructuralError>
+{
+    match highest_set_bit {
+        None => Err(StructuralError::NoSignatures),
+        Some(i) if i >= bit_len => Err(StructuralError::NonZeroTrailingBits),
+        Some(_) => Ok(()),
+    }
+}
+
+fn bytes_for_bits(bits: usize) -> Result<usize, StructuralError> {
+    if bits > MAX_COMMITTEE_BITS {
+        return Err(StructuralError::CommitteeExceedsMaxBits(bits));
+    }
+    /*
+     * Saturates so that zero bits require one byte, as does one bit. The addition cannot
+     * overflow as the dividend is at least 8.
+     */
+    Ok((bits.saturating_sub(1) / 8) + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_attestation_slot() {
+        assert_eq!(check_attestation_slot(10, 10, 0, 5), Ok(()));
+        assert_eq!(check_attestation_slot(5, 10, 0, 5), Ok(()));
+        assert_eq!(check_attestation_slot(11, 10, 1, 5), Ok(()));
+        assert_eq!(check_attestation_slot(11, 10, 0, 5), Err(StructuralError::ParentSlotTooHigh {
+            attestation_slot: 11,
+            parent_block_slot: 10,
+        }));
+        assert_eq!(check_attestation_slot(4, 10, 0, 5), Err(StructuralError::ParentSlotTooLow {
+            attestation_slot: 4,
+            min_slot: 5,
+        }));
+    }
+
+    #[test]
+    fn test_check_attestation_slot_near_u64_max() {
+        let max = u64::max_value();
+
+        assert_eq!(check_attestation_slot(max, max, 0, 5), Ok(()));
+        assert_eq!(check_attestation_slot(max, max - 1, 1, 5), Ok(()));
+        assert_eq!(check_attestation_slot(max, max - 1, 2, 5), Err(StructuralError::IntWrapping));
+        assert_eq!(check_attestation_slot(0, 3, 0, 5), Ok(()));
+    }
+
+    #[test]
+    fn test_max_oblique_hashes() {
+        assert_eq!(max_oblique_hashes(1), 1);
+        assert_eq!(max_oblique_hashes(64), 64);
+        assert_eq!(max_oblique_hashes(255), 255);
+    }
+
+    #[test]
+    fn test_check_oblique_hash_count() {
+        assert_eq!(check_oblique_hash_count(2, 8, 10, 8), Ok(()));
+        assert_eq!(check_oblique_hash_count(9, 8, 10, 9),
+                   Err(StructuralError::TooManyObliqueHashes));
+        assert_eq!(check_oblique_hash_count(7, 8, 10, 8),
+                   Err(StructuralError::ObliqueHashesExceedSlotDistance {
+                       oblique_hashes: 7,
+                       max_oblique_hashes: 6,
+                   }));
+        assert_eq!(check_oblique_hash_count(0, 8, 10, 10), Ok(()));
+        assert_eq!(check_oblique_hash_count(1, 8, 10, 10),
+                   Err(StructuralError::ObliqueHashesNotPermittedAtHead));
+        assert_eq!(check_oblique_hash_count(1, 8, u64::max_value(), 0),
+                   Err(StructuralError::ObliqueHashesExceedSlotDistance {
+                       oblique_hashes: 1,
+                       max_oblique_hashes: 0,
+                   }));
+    }
+
+    #[test]
+    fn test_check_bitfield_shape() {
+        assert_eq!(check_bitfield_shape(1, Some(4), 5), Ok(()));
+        assert_eq!(check_bitfield_shape(2, Some(4), 5), Err(StructuralError::BadBitfieldLength));
+        assert_eq!(check_bitfield_shape(1, Some(5), 5),
+                   Err(StructuralError::InvalidBitfieldEndBits));
+        assert_eq!(check_bitfield_shape(2, None, 9), Ok(()));
+    }
+
+    #[test]
+    fn test_check_bitfield_bits() {
+        assert_eq!(check_bitfield_bits(5, Some(4)), Ok(()));
+        assert_eq!(check_bitfield_bits(5, Some(0)), Ok(()));
+        assert_eq!(check_bitfield_bits(5, None), Err(StructuralError::NoSignatures));
+        assert_eq!(check_bitfield_bits(0, None), Err(StructuralError::NoSignatures));
+        assert_eq!(check_bitfield_bits(5, Some(5)), Err(StructuralError::NonZeroTrailingBits));
+        assert_eq!(check_bitfield_bits(1, Some(7)), Err(StructuralError::NonZeroTrailingBits));
+    }
+
+    #[test]
+    fn test_check_bitfield_shape_max_committee_bits() {
+        let max_bytes = MAX_COMMITTEE_BITS / 8;
+
+        assert_eq!(bytes_for_bits(MAX_COMMITTEE_BITS), Ok(max_bytes));
+        assert_eq!(check_bitfield_shape(max_bytes, Some(MAX_COMMITTEE_BITS - 1),
+                                        MAX_COMMITTEE_BITS), Ok(()));
+        assert_eq!(check_bitfield_shape(max_bytes + 1, None, MAX_COMMITTEE_BITS + 1),
+                   Err(StructuralError::CommitteeExceedsMaxBits(MAX_COMMITTEE_BITS + 1)));
+        assert_eq!(bytes_for_bits(usize::max_value()),
+                   Err(StructuralError::CommitteeExceedsMaxBits(usize::max_value())));
+    }
+}
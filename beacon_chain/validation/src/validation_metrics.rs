@@ -0,0 +1,20 @@
+use super::attestation_validation::AttestationValidationError;
+
+/// Receives the outcome of each attestation validated by an `AttestationValidationContext`,
+/// e.g., to maintain counters for a metrics endpoint.
+///
+/// All methods default to doing nothing, so an implementation need only provide the methods it
+/// requires.
+pub trait ValidationMetrics: Send + Sync {
+    /// Called when an attestation is accepted with `voters` validators voting.
+    fn record_accepted(&self, _voters: usize) {}
+
+    /// Called when an attestation is rejected with `err`.
+    fn record_rejected(&self, _err: &AttestationValidationError) {}
+}
+
+/// A ValidationMetrics which discards all outcomes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopMetrics;
+
+impl ValidationMetrics for NoopMetrics {}
@@ -0,0 +1,56 @@
+use std::collections::HashSet;
+use std::sync::mpsc::{
+    Receiver,
+    SyncSender,
+};
+use std::thread::{
+    self,
+    JoinHandle,
+};
+use super::attestation_validation::{
+    AttestationValidationContext,
+    AttestationValidationError,
+};
+use super::db::ClientDB;
+use super::db::stores::BlockReader;
+use super::types::AttestationRecord;
+
+/// An attestation and the outcome of its validation, as sent by `spawn_validation_worker`.
+pub type ValidationOutcome =
+    (AttestationRecord, Result<HashSet<usize>, AttestationValidationError>);
+
+/// Spawn a thread which validates each `(slot, attestation)` received from `rx`, sending the
+/// attestation and its outcome to `tx`.
+///
+/// `context_fn` is called on the worker thread with the slot of a record to supply a context in
+/// which to validate it. Consecutive records at the same slot share a context, as per
+/// `replay_attestations`.
+///
+/// If `tx` is bounded (i.e., created with `sync_channel`), the worker blocks until there is room
+/// for each outcome, so a slow consumer applies backpressure to the producer. The worker exits
+/// once `rx` is closed and drained, or once the receiver of `tx` is dropped.
+pub fn spawn_validation_worker<T, B, F>(
+    mut context_fn: F,
+    rx: Receiver<(u64, AttestationRecord)>,
+    tx: SyncSender<ValidationOutcome>)
+    -> JoinHandle<()>
+    where T: ClientDB + 'static,
+          B: BlockReader + 'static,
+          F: FnMut(u64) -> AttestationValidationContext<T, B> + Send + 'static
+{
+    thread::spawn(move || {
+        let mut current: Option<(u64, AttestationValidationContext<T, B>)> = None;
+
+        for (slot, a) in rx {
+            if current.as_ref().map(|(context_slot, _)| *context_slot) != Some(slot) {
+                current = None;
+            }
+            let (_, context) = current.get_or_insert_with(|| (slot, context_fn(slot)));
+            let result = context.validate_attestation(&a);
+            if tx.send((a, result)).is_err() {
+                debug!("Validation worker stopped: the receiver of outcomes was dropped");
+                return;
+            }
+        }
+    })
+}
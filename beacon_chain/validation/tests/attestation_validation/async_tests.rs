@@ -0,0 +1,172 @@
+use std::sync::Arc;
+use std::sync::atomic::{
+    AtomicUsize,
+    Ordering,
+};
+
+use super::bls::PublicKey;
+use super::db::stores::{
+    BlockReader,
+    ValidatorStoreError,
+};
+use super::futures::{
+    future,
+    Future,
+};
+use super::helpers::{
+    TestStore,
+    setup_attestation_validation_test,
+    setup_attestation_validation_test_with_abstainers,
+};
+use super::types::{
+    AttestationRecord,
+    Hash256,
+};
+use super::validation::async_db::{
+    AsyncClientDB,
+    DBFuture,
+};
+use super::validation::attestation_validation::AttestationValidationError;
+
+/// An AsyncClientDB which reads from in-memory stores, counting each read.
+struct MockAsyncDB<'a> {
+    stores: &'a TestStore,
+    reads: AtomicUsize,
+}
+
+impl<'a> MockAsyncDB<'a> {
+    fn new(stores: &'a TestStore) -> Self {
+        Self {
+            stores,
+            reads: AtomicUsize::new(0),
+        }
+    }
+
+    fn reads(&self) -> usize {
+        self.reads.load(Ordering::SeqCst)
+    }
+
+    fn read(&self) {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl<'a> AsyncClientDB for MockAsyncDB<'a> {
+    fn block_exists(&self, hash: &Hash256) -> DBFuture<bool> {
+        self.read();
+        Box::new(future::result(self.stores.block.block_exists(hash)))
+    }
+
+    fn get_block_slot(&self, hash: &Hash256) -> DBFuture<Option<u64>> {
+        self.read();
+        Box::new(future::result(self.stores.block.get_block_slot(hash)))
+    }
+
+    fn block_hash_at_slot(&self, head_hash: &Hash256, slot: u64) -> DBFuture<Option<Vec<u8>>> {
+        self.read();
+        let block_hash = self.stores.block.block_at_slot(head_hash, slot)
+            .map(|block| block.map(|(hash, _)| hash))
+            .unwrap_or(None);
+        Box::new(future::ok(block_hash))
+    }
+
+    fn is_active_at_slot(&self, index: usize, slot: u64) -> DBFuture<bool> {
+        self.read();
+        Box::new(future::result(self.stores.validator.is_active_at_slot(index, slot)))
+    }
+
+    fn get_public_key_by_index(&self, index: usize)
+        -> Box<dyn Future<Item = Option<PublicKey>, Error = ValidatorStoreError> + Send>
+    {
+        self.read();
+        Box::new(future::result(self.stores.validator.get_public_key_by_index(index)))
+    }
+}
+
+#[test]
+fn test_attestation_validation_async_valid() {
+    let rig = setup_attestation_validation_test_with_abstainers(10, 8, &[3]);
+    let db = MockAsyncDB::new(&rig.stores);
+
+    let result = rig.context.validate_attestation_async(&rig.attestation, &db).wait();
+    assert_eq!(result, rig.context.validate_attestation(&rig.attestation));
+    assert_eq!(result.unwrap().len(), 7);
+    assert!(db.reads() > 0);
+}
+
+#[test]
+fn test_attestation_validation_async_structural_checks_do_not_read_db() {
+    let mut rig = setup_attestation_validation_test(10, 2);
+    let db = MockAsyncDB::new(&rig.stores);
+
+    rig.attestation.slot = rig.context.parent_block_slot + 1;
+    let result = rig.context.validate_attestation_async(&rig.attestation, &db).wait();
+    assert_eq!(result, Err(AttestationValidationError::ParentSlotTooHigh {
+        attestation_slot: rig.attestation.slot,
+        parent_block_slot: rig.context.parent_block_slot,
+    }));
+    assert_eq!(db.reads(), 0);
+}
+
+#[test]
+fn test_attestation_validation_async_reads_db() {
+    let mut rig = setup_attestation_validation_test(10, 2);
+
+    /*
+     * The stores of the context are ignored, only `db` is read.
+     */
+    let empty_stores = TestStore::new();
+    let db = MockAsyncDB::new(&empty_stores);
+    let result = rig.context.validate_attestation_async(&rig.attestation, &db).wait();
+    assert_eq!(result, Err(AttestationValidationError::InvalidJustifiedBlockHash));
+
+    let db = MockAsyncDB::new(&rig.stores);
+    rig.context.require_active_validators = true;
+    rig.stores.validator.put_validator_activity(0, 0, None).unwrap();
+    let result = rig.context.validate_attestation_async(&rig.attestation, &db).wait();
+    assert_eq!(result, Err(AttestationValidationError::InactiveValidator(1)));
+    assert_eq!(result, rig.context.validate_attestation(&rig.attestation));
+}
+
+#[test]
+fn test_attestation_validation_async_invalid_signature() {
+    let mut rig = setup_attestation_validation_test(10, 2);
+    let db = MockAsyncDB::new(&rig.stores);
+
+    rig.attestation.attester_bitfield.set_bit(1, false);
+    let result = rig.context.validate_attestation_async(&rig.attestation, &db).wait();
+    assert_eq!(result, Err(AttestationValidationError::BadAggregateSignature));
+}
+
+#[test]
+fn test_attestation_validation_async_preprocessor() {
+    let mut rig = setup_attestation_validation_test(10, 2);
+    let db = MockAsyncDB::new(&rig.stores);
+
+    let one_bit_high = rig.attester_count + 1;
+    rig.attestation.attester_bitfield.set_bit(one_bit_high, true);
+    let result = rig.context.validate_attestation_async(&rig.attestation, &db).wait();
+    assert_eq!(result, Err(AttestationValidationError::InvalidBitfieldEndBits));
+
+    let attester_count = rig.attester_count;
+    rig.context.preprocessor = Some(Arc::new(move |a: &mut AttestationRecord| {
+        for i in attester_count..a.attester_bitfield.num_bytes() * 8 {
+            a.attester_bitfield.set_bit(i, false);
+        }
+    }));
+    let original = rig.attestation.clone();
+    let result = rig.context.validate_attestation_async(&rig.attestation, &db).wait();
+    assert_eq!(result, rig.context.validate_attestation(&rig.attestation));
+    assert_eq!(result.unwrap().len(), attester_count);
+    assert_eq!(rig.attestation, original);
+
+    /*
+     * The preprocessed record is also the one whose signature is verified.
+     */
+    rig.attestation.attester_bitfield.set_bit(one_bit_high, false);
+    rig.context.preprocessor = Some(Arc::new(|a: &mut AttestationRecord| {
+        a.attester_bitfield.set_bit(1, false);
+    }));
+    let result = rig.context.validate_attestation_async(&rig.attestation, &db).wait();
+    assert_eq!(result, Err(AttestationValidationError::BadAggregateSignature));
+}
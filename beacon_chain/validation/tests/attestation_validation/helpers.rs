@@ -12,11 +12,13 @@ use super::types::{
     AttesterMap,
     Bitfield,
     Block,
+    CycleLength,
     Hash256,
 };
 use super::validation::attestation_validation::{
     AttestationValidationContext,
 };
+use super::validation::signature_verification::BlsScheme;
 use super::bls::{
     AggregateSignature,
     Keypair,
@@ -53,6 +55,8 @@ pub struct TestRig {
     pub context: AttestationValidationContext<MemoryDB>,
     pub stores: TestStore,
     pub attester_count: usize,
+    /// The keypair of each attester, indexed by attestation index.
+    pub keypairs: Vec<Keypair>,
 }
 
 fn generate_message_hash(slot: u64,
@@ -109,6 +113,13 @@ pub fn generate_attestation(shard_id: u16,
         shard_block_hash,
         justified_slot);
 
+    /*
+     * Size the bitfield for every attester, including any trailing attesters who do not sign.
+     */
+    if let Some(last) = signing_keys.len().checked_sub(1) {
+        attester_bitfield.set_bit(last, false);
+    }
+
     for (i, secret_key) in signing_keys.iter().enumerate() {
         /*
          * If the signing key is Some, set the bitfield bit to true
@@ -160,6 +171,17 @@ pub fn insert_justified_block_hash(
 
 pub fn setup_attestation_validation_test(shard_id: u16, attester_count: usize)
     -> TestRig
+{
+    setup_attestation_validation_test_with_abstainers(shard_id, attester_count, &[])
+}
+
+/// As per `setup_attestation_validation_test`, however the attesters at the given
+/// attestation indices will not sign the attestation.
+pub fn setup_attestation_validation_test_with_abstainers(
+    shard_id: u16,
+    attester_count: usize,
+    abstainers: &[usize])
+    -> TestRig
 {
     let stores = TestStore::new();
 
@@ -198,20 +220,40 @@ pub fn setup_attestation_validation_test(shard_id: u16, attester_count: usize)
        let keypair = Keypair::random();
        keypairs.push(keypair.clone());
        stores.validator.put_public_key_by_index(i, &keypair.pk).unwrap();
-       signing_keys.push(Some(keypair.sk.clone()));
+       if abstainers.contains(&i) {
+           signing_keys.push(None);
+       } else {
+           signing_keys.push(Some(keypair.sk.clone()));
+       }
        attesters.push(i);
     }
-    attester_map.insert((attestation_slot, shard_id), attesters);
+    attester_map.insert(attestation_slot, shard_id, attesters);
 
     let context: AttestationValidationContext<MemoryDB> = AttestationValidationContext {
         block_slot,
         parent_block_slot,
-        cycle_length,
+        cycle_length: CycleLength::new(cycle_length).unwrap(),
         last_justified_slot,
         parent_hashes: parent_hashes.clone(),
+        genesis_hash: Hash256::zero(),
         block_store: stores.block.clone(),
         validator_store: stores.validator.clone(),
         attester_map: Arc::new(attester_map),
+        max_future_slots: 0,
+        max_attesters: None,
+        max_committee_sizes: None,
+        metrics: None,
+        shard_count: None,
+        allowed_justified_slots: None,
+        require_active_validators: false,
+        min_participation: None,
+        forbid_oblique: false,
+        parent_hashes_cache: None,
+        signature_scheme: Arc::new(BlsScheme),
+        attestation_domain: 0,
+        preprocessor: None,
+        blacklist: None,
+        epoch_params: None,
     };
     let attestation = generate_attestation(
         shard_id,
@@ -230,5 +272,6 @@ pub fn setup_attestation_validation_test(shard_id: u16, attester_count: usize)
         context,
         stores,
         attester_count,
+        keypairs,
     }
 }
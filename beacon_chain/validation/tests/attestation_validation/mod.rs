@@ -1,9 +1,16 @@
+#[cfg(feature = "tokio")]
+mod async_tests;
 pub mod helpers;
+mod proptests;
 mod tests;
 
 use super::bls;
 use super::db;
+#[cfg(feature = "tokio")]
+use super::futures;
+use super::proptest;
 use super::ssz;
+use super::ssz_helpers;
 use super::types;
 use super::hashing;
 use super::validation;
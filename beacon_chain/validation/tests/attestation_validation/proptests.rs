@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+
+use super::bls::{
+    AggregateSignature,
+    Signature,
+};
+use super::helpers::{
+    TestRig,
+    setup_attestation_validation_test_with_abstainers,
+};
+use super::proptest::prelude::*;
+use super::types::{
+    AttestationRecord,
+    Bitfield,
+};
+
+/// The largest committee generated. Each attester requires a keypair, so this is kept small.
+const MAX_COMMITTEE_SIZE: usize = 40;
+
+/// Generate a committee size and, for each attester, whether or not they vote.
+///
+/// At least one attester always votes.
+fn committee_and_votes() -> impl Strategy<Value = (usize, Vec<bool>)> {
+    (1..MAX_COMMITTEE_SIZE + 1)
+        .prop_flat_map(|committee_size| {
+            (Just(committee_size), prop::collection::vec(any::<bool>(), committee_size))
+        })
+        .prop_filter("at least one attester must vote", |(_, votes)| votes.iter().any(|v| *v))
+}
+
+/// Build a valid AttestationRecord (and a context in which it is valid) for the given votes.
+fn valid_rig(votes: &[bool]) -> TestRig {
+    let abstainers: Vec<usize> = votes.iter()
+        .enumerate()
+        .filter(|(_, voted)| !**voted)
+        .map(|(i, _)| i)
+        .collect();
+    setup_attestation_validation_test_with_abstainers(10, votes.len(), &abstainers)
+}
+
+/// Produce a copy of the attestation in `rig` signed only by the attester at `index`.
+fn single_voter_record(rig: &TestRig, index: usize) -> AttestationRecord {
+    let message = rig.context.signed_message_for(&rig.attestation).unwrap();
+
+    let mut attester_bitfield = Bitfield::new();
+    attester_bitfield.set_bit(rig.attester_count - 1, false);
+    attester_bitfield.set_bit(index, true);
+
+    let mut aggregate_sig = AggregateSignature::new();
+    aggregate_sig.add(&Signature::new(&message, &rig.keypairs[index].sk));
+
+    AttestationRecord {
+        attester_bitfield,
+        aggregate_sig,
+        ..rig.attestation.clone()
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    /*
+     * Any accepted record may be split into single-voter records which are each accepted, and
+     * which aggregate back into an equivalent record.
+     */
+    #[test]
+    fn prop_valid_attestation_splits_and_reaggregates((_, votes) in committee_and_votes()) {
+        let rig = valid_rig(&votes);
+
+        let voters = rig.context.validate_attestation(&rig.attestation);
+        prop_assert!(voters.is_ok(), "generated record was rejected: {:?}", voters);
+        let voters = voters.unwrap();
+
+        let singles: Vec<AttestationRecord> = rig.attestation.attester_bitfield.iter_set_bits()
+            .map(|i| single_voter_record(&rig, i))
+            .collect();
+        prop_assert_eq!(singles.len(), voters.len());
+
+        for single in &singles {
+            let single_voters = rig.context.validate_attestation(single);
+            let expected: HashSet<usize> = single.attester_bitfield.iter_set_bits().collect();
+            prop_assert_eq!(single_voters, Ok(expected));
+        }
+
+        let aggregate = singles[1..].iter()
+            .try_fold(singles[0].clone(), |aggregate, single| aggregate.try_aggregate(single));
+        prop_assert!(aggregate.is_ok(), "unable to aggregate: {:?}", aggregate);
+        let aggregate = aggregate.unwrap();
+
+        prop_assert_eq!(aggregate.attester_bitfield.to_vec(),
+                        rig.attestation.attester_bitfield.to_vec());
+        prop_assert_eq!(aggregate.aggregate_sig.to_bytes(),
+                        rig.attestation.aggregate_sig.to_bytes());
+        prop_assert_eq!(rig.context.validate_attestation(&aggregate), Ok(voters));
+    }
+}
@@ -1,20 +1,115 @@
-use std::sync::Arc;
+use std::collections::{
+    BinaryHeap,
+    HashMap,
+    HashSet,
+};
+use std::sync::{
+    mpsc,
+    Arc,
+    Mutex,
+};
+use std::time::Duration;
 
 use super::helpers::{
     TestRig,
+    TestStore,
     setup_attestation_validation_test,
+    setup_attestation_validation_test_with_abstainers,
     create_block_at_slot,
+    generate_attestation,
 };
 use super::validation::attestation_validation::{
+    AttestationValidationContextBuilder,
     AttestationValidationError,
+    BuildError,
+    ForkContext,
+    ValidatedAttestation,
+    ValidationErrorCategory,
+    ValidationTimings,
+    verify_bitfield_bits,
+    verify_bitfield_shape,
+};
+use super::validation::checkpoint_context::CheckpointContext;
+use super::validation::replay::replay_attestations;
+use super::validation::signature_verification::SignatureScheme;
+use super::validation::validation_metrics::ValidationMetrics;
+use super::validation::validation_worker::spawn_validation_worker;
+use super::ssz_helpers::ssz_block::SszBlock;
+use super::types::{
+    AttesterMap,
+    Checkpoint,
+    CycleLength,
+    EpochParams,
+    VoteTarget,
+};
+use super::db::{
+    ClientDB,
+    DBError,
+    DBErrorKind,
+    MemoryDB,
+};
+use super::db::stores::{
+    BlockStore,
+    CachingBlockStore,
+    VALIDATOR_DB_COLUMN,
 };
-use super::types::AttesterMap;
 use super::bls::{
     AggregateSignature,
+    Keypair,
+    PublicKey,
+    Signature,
 };
 use super::types::{
+    Bitfield,
     Hash256,
+    RleDecodeError,
 };
+use super::types::AttestationRecord;
+
+/// Tallies accepted attestations and rejected attestations by error variant.
+#[derive(Default)]
+struct TallyMetrics {
+    accepted: Mutex<usize>,
+    rejected: Mutex<HashMap<String, usize>>,
+}
+
+impl ValidationMetrics for TallyMetrics {
+    fn record_accepted(&self, _voters: usize) {
+        *self.accepted.lock().unwrap() += 1;
+    }
+
+    fn record_rejected(&self, err: &AttestationValidationError) {
+        let debug = format!("{:?}", err);
+        let variant = debug.split(|c: char| !c.is_alphanumeric()).next().unwrap();
+        *self.rejected.lock().unwrap().entry(variant.to_string()).or_insert(0) += 1;
+    }
+}
+
+/// A SignatureScheme which returns a fixed result without any cryptography.
+struct MockScheme(bool);
+
+impl SignatureScheme for MockScheme {
+    fn aggregate_verify(&self, _message: &[u8], _agg_sig: &AggregateSignature, _: &[PublicKey])
+        -> bool
+    {
+        self.0
+    }
+}
+
+/// A SignatureScheme which records each message it is asked to verify and then accepts it.
+#[derive(Default)]
+struct RecordingScheme {
+    messages: Mutex<Vec<Vec<u8>>>,
+}
+
+impl SignatureScheme for RecordingScheme {
+    fn aggregate_verify(&self, message: &[u8], _agg_sig: &AggregateSignature, _: &[PublicKey])
+        -> bool
+    {
+        self.messages.lock().unwrap().push(message.to_vec());
+        true
+    }
+}
 
 fn generic_rig() -> TestRig {
     let shard_id = 10;
@@ -32,6 +127,193 @@ fn test_attestation_validation_valid() {
     assert_eq!(voter_map.len(), 2);
 }
 
+#[test]
+fn test_attestation_validation_rle_bitfield() {
+    let mut rig = generic_rig();
+
+    /*
+     * An attestation received with a run-length encoded bitfield is validated once expanded.
+     */
+    let rle = rig.attestation.attester_bitfield.to_rle();
+    rig.attestation.attester_bitfield = Bitfield::from_rle(&rle).unwrap();
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result.unwrap().len(), 2);
+
+    assert_eq!(Bitfield::from_rle(&[2, 1]), Err(RleDecodeError::InvalidBitValue(2)));
+}
+
+#[test]
+fn test_attestation_validation_bitfield_from_indices() {
+    let mut rig = generic_rig();
+
+    /*
+     * A bitfield built from the positions of the voters in the committee is the one an attester
+     * would have built bit by bit.
+     */
+    let bitfield = rig.attestation.attester_bitfield.clone();
+    let voters: Vec<usize> = bitfield.iter_set_bits().collect();
+    rig.attestation.attester_bitfield = Bitfield::from_indices(&voters, bitfield.len());
+    assert_eq!(rig.attestation.attester_bitfield, bitfield);
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result.unwrap().len(), voters.len());
+}
+
+#[test]
+fn test_attestation_validation_participation() {
+    let shard_id = 10;
+    let validator_count = 5;
+    let rig = setup_attestation_validation_test_with_abstainers(shard_id, validator_count, &[1, 3]);
+
+    let participation = rig.context
+        .validate_attestation_with_participation(&rig.attestation)
+        .unwrap();
+
+    let voted: HashSet<usize> = [0, 2, 4].iter().cloned().collect();
+    let not_voted: HashSet<usize> = [1, 3].iter().cloned().collect();
+    assert_eq!(participation.voted, voted);
+    assert_eq!(participation.not_voted, not_voted);
+}
+
+#[test]
+fn test_attestation_validation_timed() {
+    let mut rig = generic_rig();
+
+    let (result, timings) = rig.context.validate_attestation_timed(&rig.attestation);
+    assert_eq!(result, rig.context.validate_attestation(&rig.attestation));
+    assert!(timings.signature > Duration::from_secs(0));
+
+    /*
+     * Phases which are not reached are not timed.
+     */
+    rig.attestation.justified_block_hash = Hash256::from("unknown".as_bytes());
+    let (result, timings) = rig.context.validate_attestation_timed(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::InvalidJustifiedBlockHash));
+    assert_eq!(timings.signature, Duration::from_secs(0));
+
+    rig.context.attester_map = Arc::new(AttesterMap::new());
+    let (_, timings) = rig.context.validate_attestation_timed(&rig.attestation);
+    assert_eq!(timings, ValidationTimings::default());
+}
+
+#[test]
+fn test_attestation_validation_count() {
+    let shard_id = 10;
+    let validator_count = 5;
+    let mut rig =
+        setup_attestation_validation_test_with_abstainers(shard_id, validator_count, &[1, 3]);
+
+    let voters = rig.context.validate_attestation(&rig.attestation).unwrap();
+    assert_eq!(rig.context.validate_attestation_count(&rig.attestation), Ok(voters.len()));
+    assert_eq!(voters.len(), 3);
+
+    rig.attestation.aggregate_sig = AggregateSignature::new();
+    assert_eq!(rig.context.validate_attestation_count(&rig.attestation),
+               Err(AttestationValidationError::BadAggregateSignature));
+}
+
+#[test]
+fn test_attestation_validation_full() {
+    let mut rig = setup_attestation_validation_test_with_abstainers(10, 5, &[1]);
+
+    let vote = rig.context.validate_attestation_full(&rig.attestation).unwrap();
+    assert_eq!(vote.voters, rig.context.validate_attestation(&rig.attestation).unwrap());
+    assert_eq!(vote.justified, Checkpoint {
+        slot: rig.attestation.justified_slot,
+        block_hash: rig.attestation.justified_block_hash,
+    });
+    assert_eq!(vote.target, VoteTarget {
+        shard_id: rig.attestation.shard_id,
+        shard_block_hash: rig.attestation.shard_block_hash,
+        slot: rig.attestation.slot,
+    });
+
+    rig.attestation.aggregate_sig = AggregateSignature::new();
+    assert_eq!(rig.context.validate_attestation_full(&rig.attestation),
+               Err(AttestationValidationError::BadAggregateSignature));
+}
+
+#[test]
+fn test_validated_attestation_ordering() {
+    let validated = |abstainers: &[usize], slot_offset: u64| {
+        let rig = setup_attestation_validation_test_with_abstainers(10, 5, abstainers);
+        let voters = rig.context.validate_attestation(&rig.attestation).unwrap();
+        let mut record = rig.attestation.clone();
+        record.slot -= slot_offset;
+        ValidatedAttestation::new(record, voters)
+    };
+
+    let mut heap = BinaryHeap::new();
+    heap.push(validated(&[1, 3], 0));
+    heap.push(validated(&[], 0));
+    heap.push(validated(&[1], 0));
+    heap.push(validated(&[], 1));
+
+    /*
+     * Most voters first, the lower slot first amongst equal voters.
+     */
+    let popped: Vec<(usize, u64)> = (0..4)
+        .map(|_| heap.pop().unwrap())
+        .map(|a| (a.voter_count(), a.record.slot))
+        .collect();
+    let slot = popped[1].1;
+    assert_eq!(popped, vec![(5, slot - 1), (5, slot), (4, slot), (3, slot)]);
+
+    let mut sorted = [validated(&[1], 0), validated(&[], 0), validated(&[], 1)];
+    sorted.sort_by(|a, b| b.cmp(a));
+    assert_eq!(sorted.iter().map(|a| a.voter_count()).collect::<Vec<_>>(), vec![5, 5, 4]);
+    assert_eq!(sorted[0].record.slot, slot - 1);
+}
+
+#[test]
+fn test_attestation_validation_participation_excludes_padding_bits() {
+    /*
+     * With 3 attesters the bitfield has 5 trailing padding bits, none of which should be
+     * reported as abstaining validators.
+     */
+    let shard_id = 10;
+    let validator_count = 3;
+    let rig = setup_attestation_validation_test_with_abstainers(shard_id, validator_count, &[2]);
+
+    let participation = rig.context
+        .validate_attestation_with_participation(&rig.attestation)
+        .unwrap();
+
+    let not_voted: HashSet<usize> = [2].iter().cloned().collect();
+    assert_eq!(participation.voted.len(), 2);
+    assert_eq!(participation.not_voted, not_voted);
+}
+
+#[test]
+fn test_attestation_validation_committee_index_gaps() {
+    let shard_id = 10;
+    let mut rig = setup_attestation_validation_test_with_abstainers(shard_id, 3, &[1]);
+
+    /*
+     * Move the committee to canonical indices with gaps between them, replacing the public keys
+     * at the original indices so that they cannot be used by mistake.
+     */
+    let attestation_indices = vec![3, 7, 100];
+    for (i, keypair) in rig.keypairs.iter().enumerate() {
+        rig.stores.validator.put_public_key_by_index(i, &Keypair::random().pk).unwrap();
+        rig.stores.validator
+            .put_public_key_by_index(attestation_indices[i], &keypair.pk)
+            .unwrap();
+    }
+    let mut attester_map = AttesterMap::new();
+    attester_map.insert(rig.attestation.slot, shard_id, attestation_indices);
+    rig.context.attester_map = Arc::new(attester_map);
+
+    let participation = rig.context
+        .validate_attestation_with_participation(&rig.attestation)
+        .unwrap();
+
+    let voted: HashSet<usize> = [3, 100].iter().cloned().collect();
+    let not_voted: HashSet<usize> = [7].iter().cloned().collect();
+    assert_eq!(participation.voted, voted);
+    assert_eq!(participation.not_voted, not_voted);
+}
+
 #[test]
 fn test_attestation_validation_invalid_parent_slot_too_high() {
     let mut rig = generic_rig();
@@ -39,7 +321,28 @@ fn test_attestation_validation_invalid_parent_slot_too_high() {
     rig.context.parent_block_slot = rig.attestation.slot - 1;
 
     let result = rig.context.validate_attestation(&rig.attestation);
-    assert_eq!(result, Err(AttestationValidationError::ParentSlotTooHigh));
+    assert_eq!(result, Err(AttestationValidationError::ParentSlotTooHigh {
+        attestation_slot: rig.attestation.slot,
+        parent_block_slot: rig.attestation.slot - 1,
+    }));
+}
+
+#[test]
+fn test_attestation_validation_max_future_slots() {
+    let mut rig = generic_rig();
+
+    rig.context.parent_block_slot = rig.attestation.slot - 1;
+    rig.context.max_future_slots = 1;
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result.unwrap().len(), 2);
+
+    rig.context.parent_block_slot = rig.attestation.slot - 2;
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::ParentSlotTooHigh {
+        attestation_slot: rig.attestation.slot,
+        parent_block_slot: rig.attestation.slot - 2,
+    }));
 }
 
 #[test]
@@ -48,7 +351,10 @@ fn test_attestation_validation_invalid_parent_slot_too_low() {
 
     rig.attestation.slot = rig.context.parent_block_slot - u64::from(rig.context.cycle_length) - 2;
     let result = rig.context.validate_attestation(&rig.attestation);
-    assert_eq!(result, Err(AttestationValidationError::ParentSlotTooLow));
+    assert_eq!(result, Err(AttestationValidationError::ParentSlotTooLow {
+        attestation_slot: rig.attestation.slot,
+        min_slot: rig.context.parent_block_slot - u64::from(rig.context.cycle_length) - 1,
+    }));
 }
 
 #[test]
@@ -61,6 +367,75 @@ fn test_attestation_validation_invalid_block_slot_too_high() {
     assert_eq!(result, Err(AttestationValidationError::BlockSlotTooHigh));
 }
 
+#[test]
+fn test_attestation_validation_block_slot_boundary() {
+    let mut rig = generic_rig();
+    rig.context.max_future_slots = 1;
+
+    /*
+     * The slot immediately below the block slot is the highest permitted, even when the parent
+     * block slot would permit the block slot itself.
+     */
+    assert_eq!(rig.attestation.slot, rig.context.block_slot - 1);
+    assert_eq!(rig.context.validate_attestation(&rig.attestation).unwrap().len(), 2);
+
+    rig.context.block_slot = rig.attestation.slot;
+    rig.context.parent_block_slot = rig.attestation.slot - 1;
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::BlockSlotTooHigh));
+}
+
+#[test]
+fn test_attestation_validation_invalid_oblique_hashes_at_head() {
+    let mut rig = generic_rig();
+    rig.context.block_slot = rig.attestation.slot;
+    rig.context.max_future_slots = 1;
+    rig.context.parent_block_slot = rig.attestation.slot - 1;
+
+    /*
+     * Without oblique parent hashes the attestation is rejected when the parent hashes are
+     * derived, with oblique parent hashes it is rejected before then.
+     */
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::BlockSlotTooHigh));
+
+    rig.attestation.oblique_parent_hashes = vec![Hash256::from("oblique".as_bytes())];
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::ObliqueHashesNotPermittedAtHead));
+    assert!(rig.context.explain_attestation(&rig.attestation)
+            .contains(&AttestationValidationError::ObliqueHashesNotPermittedAtHead));
+}
+
+#[test]
+fn test_attestation_validation_epoch_params() {
+    let mut rig = generic_rig();
+    let historical = rig.context.cycle_length;
+    let current = CycleLength::new(32).unwrap();
+
+    /*
+     * The cycle length changed after the attestation slot, so the attestation is invalid with
+     * the current cycle length.
+     */
+    rig.context.cycle_length = current;
+    assert!(rig.context.validate_attestation(&rig.attestation).is_err());
+
+    /*
+     * With the cycle length in effect at the attestation slot, it is valid again.
+     */
+    let mut epoch_params = EpochParams::new(historical);
+    epoch_params.set_cycle_length(rig.attestation.slot + 1, current);
+    rig.context.epoch_params = Some(Arc::new(epoch_params));
+    assert_eq!(rig.context.validate_attestation(&rig.attestation).unwrap().len(), 2);
+
+    /*
+     * Had the change applied from the attestation slot, the attestation would be invalid.
+     */
+    let mut epoch_params = EpochParams::new(historical);
+    epoch_params.set_cycle_length(rig.attestation.slot, current);
+    rig.context.epoch_params = Some(Arc::new(epoch_params));
+    assert!(rig.context.validate_attestation(&rig.attestation).is_err());
+}
+
 #[test]
 fn test_attestation_validation_invalid_block_slot_too_low() {
     let mut rig = generic_rig();
@@ -98,120 +473,1365 @@ fn test_attestation_validation_invalid_justified_slot_incorrect() {
 }
 
 #[test]
-fn test_attestation_validation_invalid_too_many_oblique() {
+fn test_attestation_validation_allowed_justified_slots() {
     let mut rig = generic_rig();
 
-    let obliques: Vec<Hash256> = (0..(rig.context.cycle_length + 1))
-        .map(|i| Hash256::from((i * 2) as u64))
-        .collect();
+    let latest = rig.attestation.justified_slot;
+    let previous = latest - 1;
+    rig.context.allowed_justified_slots = Some([previous, latest].iter().cloned().collect());
 
-    rig.attestation.oblique_parent_hashes = obliques;
+    let result = rig.context.validate_attestation_structure(&rig.attestation);
+    assert_eq!(result, Ok(()));
 
-    let result = rig.context.validate_attestation(&rig.attestation);
-    assert_eq!(result, Err(AttestationValidationError::TooManyObliqueHashes));
+    rig.attestation.justified_slot = previous;
+    // Ensures we don't get a bad justified block error instead.
+    create_block_at_slot(
+        &rig.stores.block,
+        &rig.attestation.justified_block_hash,
+        rig.attestation.justified_slot);
+    let result = rig.context.validate_attestation_structure(&rig.attestation);
+    assert_eq!(result, Ok(()));
+
+    /*
+     * A justified slot below the last justified slot is rejected if it is not in the set.
+     */
+    rig.context.allowed_justified_slots = Some([latest].iter().cloned().collect());
+    let result = rig.context.validate_attestation_structure(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::JustifiedSlotIncorrect));
 }
 
 #[test]
-fn test_attestation_validation_invalid_bad_attester_map() {
-    let mut rig = generic_rig();
+fn test_attestation_validation_inactive_validator() {
+    let shard_id = 10;
+    let validator_count = 3;
+    let mut rig =
+        setup_attestation_validation_test_with_abstainers(shard_id, validator_count, &[1]);
+    let slot = rig.attestation.slot;
 
-    rig.context.attester_map = Arc::new(AttesterMap::new());
+    /*
+     * Validator 2 exited at the attestation slot, validator 1 exited earlier but abstained.
+     */
+    rig.stores.validator.put_validator_activity(0, 0, None).unwrap();
+    rig.stores.validator.put_validator_activity(1, 0, Some(slot - 1)).unwrap();
+    rig.stores.validator.put_validator_activity(2, 0, Some(slot)).unwrap();
+
+    /*
+     * The activity of validators is ignored unless required by the context.
+     */
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result.unwrap().len(), 2);
+
+    rig.context.require_active_validators = true;
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::InactiveValidator(2)));
 
+    rig.stores.validator.put_validator_activity(2, 0, Some(slot + 1)).unwrap();
     let result = rig.context.validate_attestation(&rig.attestation);
-    assert_eq!(result, Err(AttestationValidationError::BadAttesterMap));
+    assert_eq!(result.unwrap().len(), 2);
+
+    /*
+     * A validator who had not yet been activated is also rejected.
+     */
+    rig.stores.validator.put_validator_activity(0, slot + 1, None).unwrap();
+    let result = rig.context.validate_attestation_count(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::InactiveValidator(0)));
 }
 
 #[test]
-fn test_attestation_validation_invalid_bad_bitfield_length() {
-    let mut rig = generic_rig();
+fn test_attestation_validation_min_participation() {
+    let shard_id = 10;
+    let validator_count = 10;
+    let abstainers: Vec<usize> = (3..10).collect();
+    let mut rig =
+        setup_attestation_validation_test_with_abstainers(shard_id, validator_count, &abstainers);
 
     /*
-     * Extend the bitfield by one byte
-     *
-     * This is a little hacky and makes assumptions about the internals
-     * of the bitfield.
+     * Participation is not checked unless required by the context.
      */
-    let one_byte_higher = rig.attester_count + 8;
-    rig.attestation.attester_bitfield.set_bit(one_byte_higher, true);
-    rig.attestation.attester_bitfield.set_bit(one_byte_higher, false);
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result.unwrap().len(), 3);
 
+    rig.context.min_participation = Some(0.5);
+    let expected = Err(AttestationValidationError::LowParticipation {
+        voters: 3,
+        attesters: 10,
+    });
+    assert_eq!(rig.context.validate_attestation(&rig.attestation).map(|v| v.len()), expected);
+    assert_eq!(rig.context.validate_attestation_count(&rig.attestation), expected);
+
+    rig.context.min_participation = Some(0.3);
     let result = rig.context.validate_attestation(&rig.attestation);
-    assert_eq!(result, Err(AttestationValidationError::BadBitfieldLength));
+    assert_eq!(result.unwrap().len(), 3);
 }
 
 #[test]
-fn test_attestation_validation_invalid_invalid_bitfield_end_bit() {
-    let mut rig = generic_rig();
+fn test_attestation_validation_context_clone() {
+    let rig = generic_rig();
 
-    let one_bit_high = rig.attester_count + 1;
-    rig.attestation.attester_bitfield.set_bit(one_bit_high, true);
+    /*
+     * The stores are shared, not copied.
+     */
+    let mut context = rig.context.clone();
+    assert!(Arc::ptr_eq(&context.validator_store, &rig.context.validator_store));
+    assert!(Arc::ptr_eq(&context.block_store, &rig.context.block_store));
 
+    /*
+     * Modifying the clone does not modify the original.
+     */
+    context.block_slot = rig.attestation.slot;
+    let result = context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::BlockSlotTooHigh));
     let result = rig.context.validate_attestation(&rig.attestation);
-    assert_eq!(result, Err(AttestationValidationError::InvalidBitfieldEndBits));
+    assert_eq!(result.unwrap().len(), 2);
 }
 
 #[test]
-fn test_attestation_validation_invalid_invalid_bitfield_end_bit_with_irreguar_bitfield_len() {
+fn test_attestation_validation_explain() {
     let mut rig = generic_rig();
+    assert_eq!(rig.context.explain_attestation(&rig.attestation), vec![]);
 
     /*
-     * This test ensure that if the number of attesters is "irregular" (with respect to the
-     * bitfield), and there is a invalid bit is set, validation will still fail.
-     *
-     * "Irregular" here means that number of validators + 1 is not a clean multiple of eight.
-     *
-     * This test exists to ensure that the application can distinguish between the highest set
-     * bit in a bitfield and the byte length of that bitfield
+     * Place the attestation slot above the parent block slot and extend the bitfield by one byte.
      */
-    let one_bit_high = rig.attester_count + 1;
-    assert!(one_bit_high % 8 != 0, "the test is ineffective in this case.");
-    rig.attestation.attester_bitfield.set_bit(one_bit_high, true);
+    rig.context.parent_block_slot = rig.attestation.slot - 1;
+    let one_byte_higher = rig.attester_count + 8;
+    rig.attestation.attester_bitfield.set_bit(one_byte_higher, true);
+    rig.attestation.attester_bitfield.set_bit(one_byte_higher, false);
 
+    /*
+     * Only the first failure is returned by validation, however both are explained. Neither the
+     * parent hashes nor the signature are checked as their prerequisites failed.
+     */
     let result = rig.context.validate_attestation(&rig.attestation);
-    assert_eq!(result, Err(AttestationValidationError::InvalidBitfieldEndBits));
+    assert_eq!(result, Err(AttestationValidationError::ParentSlotTooHigh {
+        attestation_slot: rig.attestation.slot,
+        parent_block_slot: rig.attestation.slot - 1,
+    }));
+    assert_eq!(rig.context.explain_attestation(&rig.attestation), vec![
+        AttestationValidationError::ParentSlotTooHigh {
+            attestation_slot: rig.attestation.slot,
+            parent_block_slot: rig.attestation.slot - 1,
+        },
+        AttestationValidationError::BadBitfieldLength,
+    ]);
 }
 
 #[test]
-fn test_attestation_validation_invalid_unknown_justified_block_hash() {
-    let mut rig = generic_rig();
+fn test_attestation_validation_after_checkpoint() {
+    let stores = TestStore::new();
+    let cycle_length: u8 = 8;
+    let shard_id = 10;
+    let checkpoint_slot = 1000;
+    let checkpoint_hash = Hash256::from("checkpoint".as_bytes());
 
-    rig.attestation.justified_block_hash = Hash256::from("unknown block hash".as_bytes());
+    let mut checkpoint: CheckpointContext<MemoryDB> = CheckpointContext::new(
+        CycleLength::new(cycle_length).unwrap(),
+        checkpoint_slot,
+        checkpoint_hash,
+        stores.block.clone(),
+        stores.validator.clone());
+    checkpoint.push_block_hash(Hash256::from("block".as_bytes()));
 
-    let result = rig.context.validate_attestation(&rig.attestation);
-    assert_eq!(result, Err(AttestationValidationError::InvalidJustifiedBlockHash));
+    /*
+     * Attest to the slot immediately after the checkpoint, justifying the checkpoint.
+     */
+    let attestation_slot = checkpoint_slot + 1;
+    let block_slot = attestation_slot + 1;
+
+    let mut attester_map = AttesterMap::new();
+    let mut signing_keys = vec![];
+    for i in 0..3 {
+        let keypair = Keypair::random();
+        stores.validator.put_public_key_by_index(i, &keypair.pk).unwrap();
+        signing_keys.push(Some(keypair.sk));
+    }
+    attester_map.insert(attestation_slot, shard_id, vec![0, 1, 2]);
+
+    let context = checkpoint
+        .context_for_block(block_slot, attestation_slot, Arc::new(attester_map))
+        .unwrap();
+    assert_eq!(context.last_justified_slot, checkpoint_slot);
+
+    let attestation = generate_attestation(
+        shard_id,
+        &Hash256::from("shard_block".as_bytes()),
+        block_slot,
+        attestation_slot,
+        checkpoint_slot,
+        &checkpoint_hash,
+        cycle_length,
+        &checkpoint.parent_hashes_for(block_slot).unwrap(),
+        &signing_keys,
+        &stores.block);
+
+    let voters = context.validate_attestation(&attestation).unwrap();
+    assert_eq!(voters, [0, 1, 2].iter().cloned().collect());
 }
 
 #[test]
-fn test_attestation_validation_invalid_unknown_justified_block_hash_wrong_slot() {
-    let rig = generic_rig();
+fn test_attestation_validation_near_genesis() {
+    let stores = TestStore::new();
+    let cycle_length: u8 = 8;
+    let shard_id = 10;
+    let genesis_hash = Hash256::from("genesis".as_bytes());
+    let justified_block_hash = Hash256::from("justified_block".as_bytes());
 
     /*
-     * justified_block_hash points to a block with a slot that is too high.
+     * At block 3 there are only three parent hashes, one for each slot since genesis.
      */
-    create_block_at_slot(
-        &rig.stores.block,
-        &rig.attestation.justified_block_hash,
-        rig.attestation.justified_slot + 1);
-    let result = rig.context.validate_attestation(&rig.attestation);
-    assert_eq!(result, Err(AttestationValidationError::InvalidJustifiedBlockHash));
+    let block_slot = 3;
+    let attestation_slot = 2;
+    let justified_slot = 1;
+    let parent_hashes = vec![genesis_hash, justified_block_hash, Hash256::from(2)];
+
+    let mut attester_map = AttesterMap::new();
+    let mut signing_keys = vec![];
+    for i in 0..3 {
+        let keypair = Keypair::random();
+        stores.validator.put_public_key_by_index(i, &keypair.pk).unwrap();
+        signing_keys.push(Some(keypair.sk));
+    }
+    attester_map.insert(attestation_slot, shard_id, vec![0, 1, 2]);
 
     /*
-     * justified_block_hash points to a block with a slot that is too low.
+     * The attesters sign across a full window, padded with the genesis hash.
      */
-    create_block_at_slot(
-        &rig.stores.block,
-        &rig.attestation.justified_block_hash,
-        rig.attestation.justified_slot - 1);
+    let mut padded_parent_hashes = vec![genesis_hash; 13];
+    padded_parent_hashes.extend_from_slice(&parent_hashes);
+    let attestation = generate_attestation(
+        shard_id,
+        &Hash256::from("shard_block".as_bytes()),
+        block_slot,
+        attestation_slot,
+        justified_slot,
+        &justified_block_hash,
+        cycle_length,
+        &padded_parent_hashes,
+        &signing_keys,
+        &stores.block);
+
+    let builder = || {
+        AttestationValidationContextBuilder::new()
+            .block_slot(block_slot)
+            .parent_block_slot(attestation_slot)
+            .cycle_length(CycleLength::new(cycle_length).unwrap())
+            .last_justified_slot(justified_slot)
+            .parent_hashes(parent_hashes.clone())
+            .block_store(stores.block.clone())
+            .validator_store(stores.validator.clone())
+            .attester_map(attester_map.clone())
+    };
+
+    let context = builder().genesis_hash(genesis_hash).build().unwrap();
+    let voters = context.validate_attestation(&attestation).unwrap();
+    assert_eq!(voters, [0, 1, 2].iter().cloned().collect());
+
+    /*
+     * Padding with the wrong hash changes the signed message.
+     */
+    let context = builder().build().unwrap();
+    assert_eq!(context.validate_attestation(&attestation),
+               Err(AttestationValidationError::BadAggregateSignature));
+}
+
+#[test]
+fn test_attestation_validation_invalid_int_wrapping() {
+    let mut rig = generic_rig();
+
+    /*
+     * The highest permitted attestation slot is beyond u64::MAX.
+     */
+    rig.context.parent_block_slot = u64::max_value() - 1;
+    rig.context.max_future_slots = 2;
+
     let result = rig.context.validate_attestation(&rig.attestation);
-    assert_eq!(result, Err(AttestationValidationError::InvalidJustifiedBlockHash));
+    assert_eq!(result, Err(AttestationValidationError::IntWrapping));
 }
 
 #[test]
-fn test_attestation_validation_invalid_empty_signature() {
+fn test_attestation_validation_invalid_oblique_forbidden() {
     let mut rig = generic_rig();
+    rig.context.forbid_oblique = true;
 
-    rig.attestation.aggregate_sig = AggregateSignature::new();
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result.unwrap().len(), 2);
 
+    rig.attestation.oblique_parent_hashes = vec![Hash256::from("oblique".as_bytes())];
     let result = rig.context.validate_attestation(&rig.attestation);
-    assert_eq!(result, Err(AttestationValidationError::BadAggregateSignature));
+    assert_eq!(result, Err(AttestationValidationError::ObliqueHashesForbidden));
+}
+
+#[test]
+fn test_attestation_validation_invalid_too_many_oblique() {
+    let mut rig = generic_rig();
+
+    let obliques: Vec<Hash256> = (0..(rig.context.cycle_length.get() + 1))
+        .map(|i| Hash256::from((i * 2) as u64))
+        .collect();
+
+    rig.attestation.oblique_parent_hashes = obliques;
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::TooManyObliqueHashes));
+}
+
+#[test]
+fn test_attestation_validation_invalid_oblique_exceeds_slot_distance() {
+    let mut rig = generic_rig();
+
+    /*
+     * The attestation is 10 slots behind the block, so only cycle_length - 10 oblique parent
+     * hashes are permitted.
+     */
+    rig.attestation.slot = rig.context.block_slot - 10;
+    let max_oblique_hashes = usize::from(rig.context.cycle_length) - 10;
+
+    let obliques: Vec<Hash256> = (0..(max_oblique_hashes + 1))
+        .map(|i| Hash256::from((i * 2) as u64))
+        .collect();
+    assert!(obliques.len() <= usize::from(rig.context.cycle_length));
+    rig.attestation.oblique_parent_hashes = obliques;
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::ObliqueHashesExceedSlotDistance {
+        oblique_hashes: max_oblique_hashes + 1,
+        max_oblique_hashes,
+    }));
+
+    rig.attestation.oblique_parent_hashes.pop();
+    let result = rig.context.validate_attestation_structure(&rig.attestation);
+    assert_ne!(result, Err(AttestationValidationError::ObliqueHashesExceedSlotDistance {
+        oblique_hashes: max_oblique_hashes,
+        max_oblique_hashes,
+    }));
+}
+
+#[test]
+fn test_attestation_validation_invalid_duplicate_oblique() {
+    let mut rig = generic_rig();
+
+    let oblique = Hash256::from("oblique".as_bytes());
+    rig.attestation.oblique_parent_hashes = vec![oblique, oblique];
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::DuplicateObliqueHashes));
+}
+
+#[test]
+fn test_attestation_validation_invalid_bad_attester_map() {
+    let mut rig = generic_rig();
+
+    rig.context.attester_map = Arc::new(AttesterMap::new());
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::BadAttesterMap {
+        slot: rig.attestation.slot,
+        shard_id: rig.attestation.shard_id,
+    }));
+}
+
+#[test]
+fn test_attestation_validation_invalid_shard_id_out_of_range() {
+    let mut rig = generic_rig();
+
+    rig.context.shard_count = Some(64);
+    rig.context.validate_attestation(&rig.attestation).unwrap();
+
+    rig.attestation.shard_id = 100;
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::ShardIdOutOfRange {
+        shard_id: 100,
+        shard_count: 64,
+    }));
+}
+
+#[test]
+fn test_attestation_validation_invalid_empty_committee() {
+    let mut rig = generic_rig();
+
+    let mut attester_map = AttesterMap::new();
+    attester_map.insert(rig.attestation.slot, rig.attestation.shard_id, vec![]);
+    rig.context.attester_map = Arc::new(attester_map);
+
+    /*
+     * A single zero byte would otherwise be a correctly sized bitfield for zero attesters.
+     */
+    rig.attestation.attester_bitfield = Bitfield::from_raw_parts(vec![0], 1).unwrap();
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::EmptyCommittee {
+        slot: rig.attestation.slot,
+        shard_id: rig.attestation.shard_id,
+    }));
+}
+
+#[test]
+fn test_attestation_validation_invalid_too_many_attesters() {
+    let mut rig = generic_rig();
+
+    rig.context.max_attesters = Some(100);
+    let mut attester_map = AttesterMap::new();
+    attester_map.insert(rig.attestation.slot, rig.attestation.shard_id, (0..200).collect());
+    rig.context.attester_map = Arc::new(attester_map);
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::TooManyAttesters {
+        attesters: 200,
+        max_attesters: 100,
+    }));
+
+    /*
+     * The cap is inclusive.
+     */
+    let mut rig = generic_rig();
+    let attesters = rig.context.attester_map
+        .get(&(rig.attestation.slot, rig.attestation.shard_id))
+        .unwrap()
+        .len();
+    rig.context.max_attesters = Some(attesters);
+    assert!(rig.context.validate_attestation(&rig.attestation).is_ok());
+}
+
+#[test]
+fn test_attestation_validation_invalid_committee_too_large_for_shard() {
+    let max_committee_sizes: HashMap<u16, usize> = vec![(10, 4), (11, 5)].into_iter().collect();
+    let mut rig_10 = setup_attestation_validation_test(10, 5);
+    let mut rig_11 = setup_attestation_validation_test(11, 5);
+    let mut rig_12 = setup_attestation_validation_test(12, 5);
+    rig_10.context.max_committee_sizes = Some(max_committee_sizes.clone());
+    rig_11.context.max_committee_sizes = Some(max_committee_sizes.clone());
+    rig_12.context.max_committee_sizes = Some(max_committee_sizes);
+
+    let result = rig_10.context.validate_attestation(&rig_10.attestation);
+    assert_eq!(result, Err(AttestationValidationError::CommitteeTooLarge {
+        shard_id: 10,
+        size: 5,
+        max: 4,
+    }));
+
+    /*
+     * The limit is inclusive, and shards without a limit are not limited.
+     */
+    assert_eq!(rig_11.context.validate_attestation(&rig_11.attestation).unwrap().len(), 5);
+    assert_eq!(rig_12.context.validate_attestation(&rig_12.attestation).unwrap().len(), 5);
+}
+
+#[test]
+fn test_attestation_validation_invalid_bad_bitfield_length() {
+    let mut rig = generic_rig();
+
+    /*
+     * Extend the bitfield by one byte
+     *
+     * This is a little hacky and makes assumptions about the internals
+     * of the bitfield.
+     */
+    let one_byte_higher = rig.attester_count + 8;
+    rig.attestation.attester_bitfield.set_bit(one_byte_higher, true);
+    rig.attestation.attester_bitfield.set_bit(one_byte_higher, false);
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::BadBitfieldLength));
+}
+
+#[test]
+fn test_attestation_validation_invalid_invalid_bitfield_end_bit() {
+    let mut rig = generic_rig();
+
+    let one_bit_high = rig.attester_count + 1;
+    rig.attestation.attester_bitfield.set_bit(one_bit_high, true);
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::InvalidBitfieldEndBits));
+}
+
+#[test]
+fn test_attestation_validation_invalid_invalid_bitfield_end_bit_with_irreguar_bitfield_len() {
+    let mut rig = generic_rig();
+
+    /*
+     * This test ensure that if the number of attesters is "irregular" (with respect to the
+     * bitfield), and there is a invalid bit is set, validation will still fail.
+     *
+     * "Irregular" here means that number of validators + 1 is not a clean multiple of eight.
+     *
+     * This test exists to ensure that the application can distinguish between the highest set
+     * bit in a bitfield and the byte length of that bitfield
+     */
+    let one_bit_high = rig.attester_count + 1;
+    assert!(one_bit_high % 8 != 0, "the test is ineffective in this case.");
+    rig.attestation.attester_bitfield.set_bit(one_bit_high, true);
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::InvalidBitfieldEndBits));
+}
+
+#[test]
+fn test_attestation_validation_preprocessor() {
+    let mut rig = generic_rig();
+
+    let one_bit_high = rig.attester_count + 1;
+    rig.attestation.attester_bitfield.set_bit(one_bit_high, true);
+    assert_eq!(rig.context.validate_attestation(&rig.attestation),
+               Err(AttestationValidationError::InvalidBitfieldEndBits));
+
+    /*
+     * A preprocessor which zeroes each bit beyond the committee allows the attestation to pass,
+     * without modifying the original record.
+     */
+    let attester_count = rig.attester_count;
+    rig.context.preprocessor = Some(Arc::new(move |a: &mut AttestationRecord| {
+        for i in attester_count..a.attester_bitfield.num_bytes() * 8 {
+            a.attester_bitfield.set_bit(i, false);
+        }
+    }));
+    let original = rig.attestation.clone();
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result.unwrap().len(), attester_count);
+    assert_eq!(rig.attestation, original);
+    assert!(rig.attestation.attester_bitfield.get_bit(one_bit_high));
+    assert_eq!(rig.context.explain_attestation(&rig.attestation), vec![]);
+}
+
+#[test]
+fn test_attestation_validation_blacklist() {
+    let mut rig = setup_attestation_validation_test_with_abstainers(10, 3, &[1]);
+
+    /*
+     * Blacklisting a validator who did not vote has no effect.
+     */
+    rig.context.blacklist = Some([1].iter().cloned().collect());
+    assert_eq!(rig.context.validate_attestation(&rig.attestation).unwrap().len(), 2);
+
+    /*
+     * Blacklisting a validator who voted rejects the attestation before the justified block is
+     * read or the signature is verified, so neither need be valid.
+     */
+    rig.context.blacklist = Some([1, 2].iter().cloned().collect());
+    rig.attestation.justified_block_hash = Hash256::from("unknown".as_bytes());
+    rig.attestation.aggregate_sig = AggregateSignature::new();
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::BlacklistedValidator(2)));
+    assert_eq!(rig.context.validate_attestation_structure(&rig.attestation), result.map(|_| ()));
+}
+
+#[test]
+fn test_verify_bitfield_shape() {
+    let mut bitfield = Bitfield::new();
+    bitfield.set_bit(0, true);
+    bitfield.set_bit(9, true);
+
+    assert_eq!(verify_bitfield_shape(&bitfield, 10), Ok(()));
+    assert_eq!(verify_bitfield_shape(&bitfield, 16), Ok(()));
+    assert_eq!(verify_bitfield_shape(&bitfield, 8),
+               Err(AttestationValidationError::BadBitfieldLength));
+    assert_eq!(verify_bitfield_shape(&bitfield, 17),
+               Err(AttestationValidationError::BadBitfieldLength));
+    assert_eq!(verify_bitfield_shape(&bitfield, 9),
+               Err(AttestationValidationError::InvalidBitfieldEndBits));
+}
+
+#[test]
+fn test_attestation_validation_invalid_unknown_justified_block_hash() {
+    let mut rig = generic_rig();
+
+    rig.attestation.justified_block_hash = Hash256::from("unknown block hash".as_bytes());
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::InvalidJustifiedBlockHash));
+}
+
+#[test]
+fn test_attestation_validation_justified_block_slot_matches() {
+    let rig = generic_rig();
+
+    let ssz = rig.stores.block.get_serialized_block(&rig.attestation.justified_block_hash)
+        .unwrap()
+        .unwrap();
+    assert_eq!(SszBlock::from_slice(&ssz).unwrap().slot_number(),
+               rig.attestation.justified_slot);
+
+    let result = rig.context.validate_attestation_structure(&rig.attestation);
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn test_attestation_validation_invalid_justified_block_slot_mismatch() {
+    let rig = generic_rig();
+
+    /*
+     * justified_block_hash points to a block with a slot that is too high.
+     */
+    create_block_at_slot(
+        &rig.stores.block,
+        &rig.attestation.justified_block_hash,
+        rig.attestation.justified_slot + 1);
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::JustifiedBlockSlotMismatch {
+        justified_slot: rig.attestation.justified_slot,
+        block_slot: rig.attestation.justified_slot + 1,
+    }));
+
+    /*
+     * justified_block_hash points to a block with a slot that is too low.
+     */
+    create_block_at_slot(
+        &rig.stores.block,
+        &rig.attestation.justified_block_hash,
+        rig.attestation.justified_slot - 1);
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::JustifiedBlockSlotMismatch {
+        justified_slot: rig.attestation.justified_slot,
+        block_slot: rig.attestation.justified_slot - 1,
+    }));
+}
+
+#[test]
+fn test_attestation_validation_invalid_corrupt_public_key() {
+    let rig = generic_rig();
+
+    /*
+     * Overwrite the public key of validator 1 with some junk.
+     */
+    let mut key = b"pubkey".to_vec();
+    key.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    rig.stores.db.put(VALIDATOR_DB_COLUMN, &key, b"cats").unwrap();
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::PublicKeyCorrupt(1)));
+}
+
+#[test]
+fn test_attestation_validation_invalid_empty_signature() {
+    let mut rig = generic_rig();
+
+    rig.attestation.aggregate_sig = AggregateSignature::new();
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::BadAggregateSignature));
+}
+
+#[test]
+fn test_attestation_validation_invalid_all_zero_bitfield() {
+    let mut rig = generic_rig();
+
+    /*
+     * A bitfield of the correct length for the committee, without any bit set.
+     */
+    rig.attestation.attester_bitfield = Bitfield::new();
+    rig.attestation.attester_bitfield.set_bit(1, false);
+    assert!(verify_bitfield_shape(&rig.attestation.attester_bitfield, 2).is_ok());
+
+    /*
+     * The attestation is rejected before the justified block is read, even if it is unknown.
+     */
+    rig.attestation.justified_block_hash = Hash256::from("unknown".as_bytes());
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::NoSignatures));
+    assert_eq!(rig.context.validate_attestation_structure(&rig.attestation),
+               Err(AttestationValidationError::NoSignatures));
+}
+
+#[test]
+fn test_attestation_validation_invalid_non_zero_trailing_bits() {
+    let mut rig = generic_rig();
+
+    /*
+     * A bitfield which claims a length of one bit but has its second bit set. The bitfield is
+     * the correct shape for the committee of two, so only the trailing bit is at fault.
+     */
+    rig.attestation.attester_bitfield = Bitfield::from_raw_parts(vec![0b0000_0010], 1).unwrap();
+    assert!(verify_bitfield_shape(&rig.attestation.attester_bitfield, 2).is_ok());
+
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::NonZeroTrailingBits));
+}
+
+#[test]
+fn test_verify_bitfield_bits() {
+    let mut bitfield = Bitfield::new();
+    assert_eq!(verify_bitfield_bits(&bitfield), Err(AttestationValidationError::NoSignatures));
+    bitfield.set_bit(9, false);
+    assert_eq!(verify_bitfield_bits(&bitfield), Err(AttestationValidationError::NoSignatures));
+    bitfield.set_bit(9, true);
+    assert_eq!(verify_bitfield_bits(&bitfield), Ok(()));
+
+    let bitfield = Bitfield::from_raw_parts(vec![0b1000_0001], 4).unwrap();
+    assert_eq!(verify_bitfield_bits(&bitfield),
+               Err(AttestationValidationError::NonZeroTrailingBits));
+    let bitfield = Bitfield::from_raw_parts(vec![0b1000_0001], 8).unwrap();
+    assert_eq!(verify_bitfield_bits(&bitfield), Ok(()));
+}
+
+#[test]
+fn test_attestation_validation_on_forks() {
+    let rig = generic_rig();
+    let parent_hashes: Vec<Hash256> = rig.context.parent_hashes
+        .get_range(0, rig.context.parent_hashes.len())
+        .unwrap()
+        .into_owned();
+
+    /*
+     * The second fork has not yet justified the justified slot of the attestation, the third
+     * fork has different parent hashes to those signed by the attesters.
+     */
+    let mut other_parent_hashes = parent_hashes.clone();
+    let signed = other_parent_hashes.len() - 3;
+    other_parent_hashes[signed] = Hash256::from("other_parent".as_bytes());
+    let forks = vec![
+        ForkContext::new(parent_hashes.clone(), rig.context.last_justified_slot),
+        ForkContext::new(parent_hashes, rig.attestation.justified_slot - 1),
+        ForkContext::new(other_parent_hashes, rig.context.last_justified_slot),
+    ];
+
+    let results = rig.context.validate_attestation_on_forks(&rig.attestation, &forks);
+    assert_eq!(results.len(), forks.len());
+    assert_eq!(results[0], rig.context.validate_attestation(&rig.attestation));
+    assert_eq!(results[0].as_ref().unwrap().len(), 2);
+    assert_eq!(results[1], Err(AttestationValidationError::JustifiedSlotIncorrect));
+    assert_eq!(results[2], Err(AttestationValidationError::BadAggregateSignature));
+}
+
+#[test]
+fn test_attestation_validation_batch() {
+    let rig = generic_rig();
+
+    let valid = rig.attestation.clone();
+    let mut bad_justified_hash = rig.attestation.clone();
+    bad_justified_hash.justified_block_hash = Hash256::from("unknown block hash".as_bytes());
+    let mut bad_signature = rig.attestation.clone();
+    bad_signature.aggregate_sig = AggregateSignature::new();
+
+    let records = vec![
+        valid.clone(),
+        bad_justified_hash,
+        valid,
+        bad_signature,
+    ];
+    let results = rig.context.validate_attestations(&records);
+
+    assert_eq!(results.len(), records.len());
+    assert_eq!(results[0].as_ref().unwrap().len(), 2);
+    assert_eq!(results[1], Err(AttestationValidationError::InvalidJustifiedBlockHash));
+    assert_eq!(results[2], results[0]);
+    assert_eq!(results[3], Err(AttestationValidationError::BadAggregateSignature));
+}
+
+#[test]
+fn test_attestation_validation_collect_votes_by_shard() {
+    let rig = setup_attestation_validation_test_with_abstainers(10, 3, &[2]);
+
+    /*
+     * A second attestation for the same shard block, signed by attesters 1 and 2.
+     */
+    let message = rig.context.signed_message_for(&rig.attestation).unwrap();
+    let mut overlapping = rig.attestation.clone();
+    overlapping.attester_bitfield = Bitfield::new();
+    overlapping.aggregate_sig = AggregateSignature::new();
+    for i in 1..3 {
+        overlapping.attester_bitfield.set_bit(i, true);
+        overlapping.aggregate_sig.add(&Signature::new(&message, &rig.keypairs[i].sk));
+    }
+
+    /*
+     * An attestation for another shard block with a signature across the original shard block is
+     * invalid, so it is skipped rather than keyed separately.
+     */
+    let mut invalid = rig.attestation.clone();
+    invalid.shard_block_hash = Hash256::from("other_shard_block".as_bytes());
+
+    let records = vec![rig.attestation.clone(), overlapping, invalid];
+    let votes = rig.context.validate_and_collect_votes_by_shard(&records);
+
+    let key = (rig.attestation.slot, rig.attestation.shard_id, rig.attestation.shard_block_hash);
+    let union: HashSet<usize> = [0, 1, 2].iter().cloned().collect();
+    assert_eq!(votes.len(), 1);
+    assert_eq!(votes[&key], union);
+}
+
+#[test]
+fn test_attestation_validation_two_phase() {
+    let mut rig = generic_rig();
+
+    let valid = rig.attestation.clone();
+    let mut bad_justified_hash = rig.attestation.clone();
+    bad_justified_hash.justified_block_hash = Hash256::from("unknown block hash".as_bytes());
+    let mut bad_signature = rig.attestation.clone();
+    bad_signature.aggregate_sig = AggregateSignature::new();
+
+    let records = vec![valid.clone(), bad_justified_hash, valid.clone()];
+    let checks = rig.context.validate_structures_then_collect(&records);
+    assert_eq!(checks[1], Err(AttestationValidationError::InvalidJustifiedBlockHash));
+    let results = rig.context.verify_collected_signatures(checks);
+    assert_eq!(results, rig.context.validate_attestations(&records));
+
+    /*
+     * One signature is invalid, so only that attestation is rejected.
+     */
+    let records = vec![valid.clone(), bad_signature, valid];
+    let checks = rig.context.validate_structures_then_collect(&records);
+    assert!(checks.iter().all(|check| check.is_ok()));
+    let results = rig.context.verify_collected_signatures(checks);
+    assert_eq!(results.len(), records.len());
+    assert_eq!(results[0].as_ref().unwrap().len(), 2);
+    assert_eq!(results[1], Err(AttestationValidationError::BadAggregateSignature));
+    assert_eq!(results[2], results[0]);
+
+    /*
+     * Each collected signature is verified exactly once.
+     */
+    let scheme = Arc::new(RecordingScheme::default());
+    rig.context.signature_scheme = scheme.clone();
+    let checks = rig.context.validate_structures_then_collect(&records);
+    let messages: Vec<Vec<u8>> = checks.iter()
+        .map(|check| check.as_ref().unwrap().message.clone())
+        .collect();
+    rig.context.verify_collected_signatures(checks);
+    assert_eq!(*scheme.messages.lock().unwrap(), messages);
+}
+
+#[test]
+fn test_attestation_validation_batch_bad_attester_map() {
+    let mut rig = generic_rig();
+
+    rig.context.attester_map = Arc::new(AttesterMap::new());
+
+    let records = vec![rig.attestation.clone(), rig.attestation.clone()];
+    let results = rig.context.validate_attestations(&records);
+    assert_eq!(results.len(), 2);
+    for result in results {
+        assert_eq!(result, Err(AttestationValidationError::BadAttesterMap {
+            slot: rig.attestation.slot,
+            shard_id: rig.attestation.shard_id,
+        }));
+    }
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn test_attestation_validation_parallel() {
+    let rig = setup_attestation_validation_test(10, 64);
+
+    let records: Vec<AttestationRecord> = (0..64)
+        .map(|_| rig.attestation.clone())
+        .collect();
+
+    let serial_results: Vec<_> = records.iter()
+        .map(|a| rig.context.validate_attestation(a))
+        .collect();
+    let parallel_results = rig.context.validate_attestations_parallel(&records);
+
+    assert_eq!(parallel_results, serial_results);
+    assert!(parallel_results.iter().all(|r| r.as_ref().unwrap().len() == 64));
+}
+
+/*
+ * Timings are only meaningful for an optimized build on a machine with more than one core, run
+ * with `cargo test --release --features parallel -- --ignored`.
+ */
+#[test]
+#[ignore]
+#[cfg(feature = "parallel")]
+fn test_attestation_validation_parallel_speedup() {
+    use std::time::Instant;
+
+    let rig = setup_attestation_validation_test(10, 64);
+
+    let records: Vec<AttestationRecord> = (0..256)
+        .map(|_| rig.attestation.clone())
+        .collect();
+
+    let start = Instant::now();
+    for a in &records {
+        rig.context.validate_attestation(a).unwrap();
+    }
+    let serial_duration = start.elapsed();
+
+    let start = Instant::now();
+    let parallel_results = rig.context.validate_attestations_parallel(&records);
+    let parallel_duration = start.elapsed();
+
+    println!("Validated {} attestations. Serial: {:?}, parallel: {:?}",
+             records.len(), serial_duration, parallel_duration);
+
+    assert!(parallel_results.iter().all(|r| r.is_ok()));
+    assert!(parallel_duration < serial_duration);
+}
+
+#[test]
+fn test_attestation_validation_parent_hashes_cache() {
+    let mut rig = generic_rig();
+
+    /*
+     * 50 attestations sharing 5 distinct sets of oblique parent hashes.
+     */
+    let oblique_sets: Vec<Vec<Hash256>> = (0..5)
+        .map(|i| (0..i * 8).map(|j| Hash256::from(1000 + j as u64)).collect())
+        .collect();
+    let records: Vec<AttestationRecord> = (0..50)
+        .map(|i| {
+            let mut a = rig.attestation.clone();
+            a.oblique_parent_hashes = oblique_sets[i % oblique_sets.len()].clone();
+            a
+        })
+        .collect();
+
+    let uncached: Vec<_> = records.iter()
+        .map(|a| rig.context.derive_parent_hashes(a.slot, &a.oblique_parent_hashes).unwrap())
+        .collect();
+
+    rig.context.parent_hashes_cache = Some(Mutex::new(HashMap::new()));
+    let cached: Vec<_> = records.iter()
+        .map(|a| rig.context.derive_parent_hashes(a.slot, &a.oblique_parent_hashes).unwrap())
+        .collect();
+
+    assert_eq!(cached, uncached);
+    assert_eq!(rig.context.parent_hashes_cache.as_ref().unwrap().lock().unwrap().len(),
+               oblique_sets.len());
+
+    /*
+     * A clone starts with an empty cache.
+     */
+    let context = rig.context.clone();
+    assert!(context.parent_hashes_cache.unwrap().lock().unwrap().is_empty());
+
+    /*
+     * Parent hashes cached for one block slot are not returned for another.
+     */
+    rig.context.block_slot += 1;
+    let a = &records[1];
+    let cached = rig.context.derive_parent_hashes(a.slot, &a.oblique_parent_hashes);
+    let cache = rig.context.parent_hashes_cache.take();
+    assert_eq!(cached, rig.context.derive_parent_hashes(a.slot, &a.oblique_parent_hashes));
+    assert_ne!(cached.unwrap(), uncached[1]);
+    assert_eq!(cache.unwrap().lock().unwrap().len(), oblique_sets.len() + 1);
+}
+
+#[test]
+fn test_attestation_validation_derive_parent_hashes() {
+    let rig = generic_rig();
+
+    let parent_hashes = rig.context
+        .derive_parent_hashes(rig.attestation.slot, &rig.attestation.oblique_parent_hashes)
+        .unwrap();
+
+    /*
+     * The attestation is for the slot prior to the block, so the hashes should be the cycle
+     * which ends at the second-last parent hash.
+     */
+    let cycle_length = usize::from(rig.context.cycle_length);
+    let end = rig.context.parent_hashes.len() - 1;
+    assert_eq!(parent_hashes.len(), cycle_length);
+    assert_eq!(&parent_hashes[..],
+               &rig.context.parent_hashes.get_range(end - cycle_length, cycle_length).unwrap()[..]);
+
+    let obliques = vec![Hash256::from("oblique".as_bytes())];
+    let parent_hashes = rig.context
+        .derive_parent_hashes(rig.attestation.slot, &obliques)
+        .unwrap();
+    assert_eq!(parent_hashes.len(), cycle_length);
+    assert_eq!(parent_hashes.last(), obliques.last());
+
+    let result = rig.context.derive_parent_hashes(rig.context.block_slot, &[]);
+    assert_eq!(result, Err(AttestationValidationError::BlockSlotTooHigh));
+}
+
+#[test]
+fn test_attestation_validation_oblique_hashes_fixed_position() {
+    let mut rig = generic_rig();
+    let cycle_length = usize::from(rig.context.cycle_length);
+
+    /*
+     * The attestation is for the slot prior to the block, so the oldest hash of its cycle is of a
+     * slot prior to the cycle preceding the block slot and may not be replaced.
+     */
+    let obliques: Vec<Hash256> = (0..cycle_length as u64).map(Hash256::from).collect();
+    let result = rig.context.derive_parent_hashes(rig.attestation.slot, &obliques[1..]);
+    assert_eq!(&result.unwrap()[1..], &obliques[1..]);
+    let result = rig.context.derive_parent_hashes(rig.attestation.slot, &obliques);
+    assert_eq!(result, Err(AttestationValidationError::BadObliqueHashes));
+
+    /*
+     * Validation rejects the same attestation by the count of its oblique hashes before they
+     * are applied.
+     */
+    rig.attestation.oblique_parent_hashes = obliques;
+    assert_eq!(rig.context.validate_attestation(&rig.attestation),
+               Err(AttestationValidationError::ObliqueHashesExceedSlotDistance {
+                   oblique_hashes: cycle_length,
+                   max_oblique_hashes: cycle_length - 1,
+               }));
+}
+
+#[test]
+fn test_attestation_validation_structure() {
+    let mut rig = generic_rig();
+
+    assert_eq!(rig.context.validate_attestation_structure(&rig.attestation), Ok(()));
+
+    /*
+     * A bad signature passes structural validation.
+     */
+    rig.attestation.aggregate_sig = AggregateSignature::new();
+    assert_eq!(rig.context.validate_attestation_structure(&rig.attestation), Ok(()));
+    assert_eq!(rig.context.validate_attestation(&rig.attestation),
+               Err(AttestationValidationError::BadAggregateSignature));
+
+    /*
+     * A structural failure is detected.
+     */
+    rig.attestation.justified_block_hash = Hash256::from("unknown block hash".as_bytes());
+    assert_eq!(rig.context.validate_attestation_structure(&rig.attestation),
+               Err(AttestationValidationError::InvalidJustifiedBlockHash));
+}
+
+#[test]
+fn test_attestation_validation_context_builder() {
+    let rig = generic_rig();
+    let parent_hashes = rig.context.parent_hashes
+        .get_range(0, rig.context.parent_hashes.len())
+        .unwrap()
+        .to_vec();
+
+    let builder = || {
+        AttestationValidationContextBuilder::new()
+            .block_slot(rig.context.block_slot)
+            .parent_block_slot(rig.context.parent_block_slot)
+            .cycle_length(rig.context.cycle_length)
+            .last_justified_slot(rig.context.last_justified_slot)
+            .parent_hashes(parent_hashes.clone())
+            .block_store(rig.stores.block.clone())
+            .validator_store(rig.stores.validator.clone())
+    };
+
+    let context = builder()
+        .attester_map(rig.context.attester_map.clone())
+        .build()
+        .unwrap();
+    assert_eq!(context.max_future_slots, 0);
+    assert_eq!(context.validate_attestation(&rig.attestation),
+               rig.context.validate_attestation(&rig.attestation));
+
+    let result = builder().build();
+    assert_eq!(result.err(), Some(BuildError::MissingField("attester_map")));
+}
+
+#[test]
+fn test_attestation_validation_caching_block_store() {
+    let rig = generic_rig();
+
+    let context = AttestationValidationContextBuilder::default()
+        .block_slot(rig.context.block_slot)
+        .parent_block_slot(rig.context.parent_block_slot)
+        .cycle_length(rig.context.cycle_length)
+        .last_justified_slot(rig.context.last_justified_slot)
+        .parent_hash_provider(rig.context.parent_hashes.clone())
+        .block_store(CachingBlockStore::new(rig.stores.block.clone(), 16, 16))
+        .validator_store(rig.stores.validator.clone())
+        .attester_map(rig.context.attester_map.clone())
+        .build()
+        .unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(context.validate_attestation(&rig.attestation),
+                   rig.context.validate_attestation(&rig.attestation));
+    }
+}
+
+#[test]
+fn test_attestation_validation_batch_block_existence_not_persisted() {
+    let mut rig = generic_rig();
+
+    let records = vec![rig.attestation.clone(), rig.attestation.clone()];
+
+    /*
+     * Swap in an empty block store so the justified block is unknown.
+     */
+    let block_store = rig.context.block_store.clone();
+    rig.context.block_store = Arc::new(BlockStore::new(Arc::new(MemoryDB::open())));
+    let results = rig.context.validate_attestations(&records);
+    assert_eq!(results, vec![
+        Err(AttestationValidationError::InvalidJustifiedBlockHash),
+        Err(AttestationValidationError::InvalidJustifiedBlockHash),
+    ]);
+
+    /*
+     * Once the block is known, a later batch must see it.
+     */
+    rig.context.block_store = block_store;
+    let results = rig.context.validate_attestations(&records);
+    assert!(results.iter().all(|r| r.is_ok()));
+}
+
+#[test]
+fn test_attestation_validation_metrics() {
+    let mut rig = generic_rig();
+    let metrics = Arc::new(TallyMetrics::default());
+    rig.context.metrics = Some(metrics.clone());
+
+    let valid = rig.attestation.clone();
+    let mut unknown_shard = rig.attestation.clone();
+    unknown_shard.shard_id += 1;
+    let mut bad_signature = rig.attestation.clone();
+    bad_signature.aggregate_sig = AggregateSignature::new();
+
+    rig.context.validate_attestation(&valid).unwrap();
+    rig.context.validate_attestations(&[valid, unknown_shard.clone(), unknown_shard]);
+    assert!(rig.context.validate_attestation(&bad_signature).is_err());
+
+    /*
+     * Structural validation does not produce an outcome.
+     */
+    rig.context.validate_attestation_structure(&bad_signature).unwrap();
+
+    assert_eq!(*metrics.accepted.lock().unwrap(), 2);
+    let rejected = metrics.rejected.lock().unwrap();
+    assert_eq!(rejected.len(), 2);
+    assert_eq!(rejected["BadAttesterMap"], 2);
+    assert_eq!(rejected["BadAggregateSignature"], 1);
+}
+
+#[test]
+fn test_attestation_validation_mock_signature_scheme() {
+    let mut rig = generic_rig();
+
+    let mut unsigned = rig.attestation.clone();
+    unsigned.aggregate_sig = AggregateSignature::new();
+
+    /*
+     * A scheme which accepts everything allows an unsigned attestation through.
+     */
+    rig.context.signature_scheme = Arc::new(MockScheme(true));
+    let voters = rig.context.validate_attestation(&unsigned).unwrap();
+    assert_eq!(voters.len(), 2);
+
+    /*
+     * A scheme which rejects everything rejects a correctly signed attestation.
+     */
+    rig.context.signature_scheme = Arc::new(MockScheme(false));
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::BadAggregateSignature));
+}
+
+#[test]
+fn test_attestation_validation_signed_message_for() {
+    let mut rig = generic_rig();
+
+    let scheme = Arc::new(RecordingScheme::default());
+    rig.context.signature_scheme = scheme.clone();
+    rig.context.validate_attestation(&rig.attestation).unwrap();
+
+    let message = rig.context.signed_message_for(&rig.attestation).unwrap();
+    assert_eq!(*scheme.messages.lock().unwrap(), vec![message]);
+
+    /*
+     * The slot checks required to derive the parent hashes are still performed.
+     */
+    rig.attestation.slot = rig.context.parent_block_slot + 1;
+    assert_eq!(rig.context.signed_message_for(&rig.attestation),
+               Err(AttestationValidationError::ParentSlotTooHigh {
+                   attestation_slot: rig.attestation.slot,
+                   parent_block_slot: rig.context.parent_block_slot,
+               }));
+}
+
+#[test]
+fn test_attestation_validation_invalid_attestation_domain() {
+    let mut rig = generic_rig();
+    let message = rig.context.signed_message_for(&rig.attestation).unwrap();
+
+    /*
+     * The attestation was signed without a domain, so it is not valid in any other domain.
+     */
+    rig.context.attestation_domain = 1;
+    assert_ne!(rig.context.signed_message_for(&rig.attestation).unwrap(), message);
+    let result = rig.context.validate_attestation(&rig.attestation);
+    assert_eq!(result, Err(AttestationValidationError::BadAggregateSignature));
+}
+
+#[test]
+fn test_attestation_validation_error_category() {
+    use self::AttestationValidationError::*;
+    use self::ValidationErrorCategory::*;
+
+    let cases = vec![
+        (ParentSlotTooHigh { attestation_slot: 2, parent_block_slot: 1 }, SlotOutOfRange),
+        (ParentSlotTooLow { attestation_slot: 1, min_slot: 2 }, SlotOutOfRange),
+        (BlockSlotTooHigh, SlotOutOfRange),
+        (BlockSlotTooLow, SlotOutOfRange),
+        (JustifiedSlotIncorrect, SlotOutOfRange),
+        (InvalidJustifiedBlockHash, UnknownReference),
+        (JustifiedBlockSlotMismatch { justified_slot: 1, block_slot: 2 }, UnknownReference),
+        (TooManyObliqueHashes, Malformed),
+        (ObliqueHashesForbidden, Malformed),
+        (ObliqueHashesExceedSlotDistance { oblique_hashes: 2, max_oblique_hashes: 1 }, Malformed),
+        (ObliqueHashesNotPermittedAtHead, Malformed),
+        (DuplicateObliqueHashes, Malformed),
+        (BadCurrentHashes, Internal),
+        (BadObliqueHashes, Malformed),
+        (BadAttesterMap { slot: 1, shard_id: 1 }, UnknownReference),
+        (EmptyCommittee { slot: 1, shard_id: 1 }, UnknownReference),
+        (ShardIdOutOfRange { shard_id: 2, shard_count: 1 }, Malformed),
+        (TooManyAttesters { attesters: 2, max_attesters: 1 }, Malformed),
+        (CommitteeTooLarge { shard_id: 1, size: 2, max: 1 }, Malformed),
+        (ValidatorIndexOutOfBounds(1), Malformed),
+        (InactiveValidator(1), UnknownReference),
+        (BlacklistedValidator(1), UnknownReference),
+        (LowParticipation { voters: 1, attesters: 2 }, Malformed),
+        (IntWrapping, Malformed),
+        (CommitteeExceedsMaxBits(1), Internal),
+        (PublicKeyCorrupt(1), Internal),
+        (NoPublicKeyForValidator, Internal),
+        (BadBitfieldLength, Malformed),
+        (InvalidBitfield, Malformed),
+        (InvalidBitfieldEndBits, Malformed),
+        (NoSignatures, Malformed),
+        (NonZeroTrailingBits, Malformed),
+        (MalformedAggregateSignature, Malformed),
+        (BadAggregateSignature, SignatureInvalid),
+        (DBError(super::db::DBError::new(DBErrorKind::Io, "disk".to_string())), Internal),
+    ];
+
+    for (err, category) in cases {
+        assert_eq!(err.category(), category, "{:?}", err);
+    }
+}
+
+#[test]
+fn test_attestation_validation_error_display() {
+    let mut rig = generic_rig();
+
+    rig.attestation.slot = rig.context.parent_block_slot + 2;
+    let err = rig.context.validate_attestation(&rig.attestation).unwrap_err();
+    assert_eq!(err.to_string(), format!("attestation slot {} exceeds parent block slot {}",
+                                        rig.attestation.slot, rig.context.parent_block_slot));
+
+    let db_error = DBError::new(DBErrorKind::Io, "disk".to_string());
+    let err = AttestationValidationError::DBError(db_error);
+    assert_eq!(err.to_string(), "database error: disk");
+}
+
+#[test]
+fn test_attestation_validation_replay() {
+    let rig = generic_rig();
+    let valid = rig.attestation.clone();
+
+    let mut bad_signature = valid.clone();
+    bad_signature.aggregate_sig = AggregateSignature::new();
+    let mut future_slot = valid.clone();
+    future_slot.slot = rig.context.parent_block_slot + 1;
+
+    let records = vec![valid.clone(), bad_signature, valid, future_slot];
+    let mut context_slots = vec![];
+    let report = replay_attestations(
+        |slot| {
+            context_slots.push(slot);
+            rig.context.clone()
+        },
+        &records,
+        1);
+
+    assert_eq!(report.accepted, 2);
+    assert_eq!(report.rejected_count(), 2);
+    assert_eq!(report.rejected.get(&ValidationErrorCategory::SignatureInvalid), Some(&1));
+    assert_eq!(report.rejected.get(&ValidationErrorCategory::SlotOutOfRange), Some(&1));
+    assert_eq!(report.failures, vec![(1, AttestationValidationError::BadAggregateSignature)]);
+
+    /*
+     * Consecutive records at the same slot share a context.
+     */
+    assert_eq!(context_slots, vec![records[0].slot, records[3].slot]);
+}
+
+#[test]
+fn test_attestation_validation_worker() {
+    let rig = generic_rig();
+    let valid = rig.attestation.clone();
+
+    let mut bad_signature = valid.clone();
+    bad_signature.aggregate_sig = AggregateSignature::new();
+    let mut future_slot = valid.clone();
+    future_slot.slot = rig.context.parent_block_slot + 1;
+    let records = vec![valid.clone(), bad_signature, valid, future_slot];
+
+    /*
+     * Channels with room for a single record, so the producer and the worker must alternate.
+     */
+    let (record_tx, record_rx) = mpsc::sync_channel(1);
+    let (outcome_tx, outcome_rx) = mpsc::sync_channel(1);
+    let context_slots = Arc::new(Mutex::new(vec![]));
+    let worker = {
+        let context = rig.context.clone();
+        let context_slots = context_slots.clone();
+        spawn_validation_worker(
+            move |slot| {
+                context_slots.lock().unwrap().push(slot);
+                context.clone()
+            },
+            record_rx,
+            outcome_tx)
+    };
+
+    let producer = {
+        let records = records.clone();
+        std::thread::spawn(move || {
+            for a in records {
+                record_tx.send((a.slot, a)).unwrap();
+            }
+        })
+    };
+
+    /*
+     * The worker shuts down once the producer drops its sender, closing the outcome channel.
+     */
+    let outcomes: Vec<_> = outcome_rx.iter().collect();
+    producer.join().unwrap();
+    worker.join().unwrap();
+
+    assert_eq!(outcomes.len(), records.len());
+    for (i, (a, result)) in outcomes.into_iter().enumerate() {
+        assert_eq!(a, records[i]);
+        assert_eq!(result, rig.context.validate_attestation(&records[i]));
+    }
+    assert_eq!(*context_slots.lock().unwrap(), vec![records[0].slot, records[3].slot]);
+}
+
+#[test]
+fn test_attestation_validation_diagnose_signature_failure() {
+    let shard_id = 10;
+    let validator_count = 5;
+    let mut rig =
+        setup_attestation_validation_test_with_abstainers(shard_id, validator_count, &[3]);
+
+    /*
+     * Every voter signs the attestation message except validator 2, which signs another message.
+     */
+    let message = rig.context.signed_message_for(&rig.attestation).unwrap();
+    let mut signatures: HashMap<usize, Signature> = [0, 1, 2, 4].iter()
+        .map(|&i| (i, Signature::new(&message, &rig.keypairs[i].sk)))
+        .collect();
+    signatures.insert(2, Signature::new("other_message".as_bytes(), &rig.keypairs[2].sk));
+
+    rig.attestation.aggregate_sig = AggregateSignature::new();
+    for sig in signatures.values() {
+        rig.attestation.aggregate_sig.add(sig);
+    }
+    assert_eq!(rig.context.validate_attestation(&rig.attestation),
+               Err(AttestationValidationError::BadAggregateSignature));
+
+    let bad_signers = rig.context.diagnose_signature_failure(&rig.attestation, &signatures);
+    assert_eq!(bad_signers, Ok(vec![2].into_iter().collect()));
+
+    /*
+     * A voter whose signature is not supplied cannot be cleared.
+     */
+    signatures.remove(&4);
+    let bad_signers = rig.context.diagnose_signature_failure(&rig.attestation, &signatures);
+    assert_eq!(bad_signers, Ok(vec![2, 4].into_iter().collect()));
+
+    /*
+     * Invalid structure is reported rather than diagnosed.
+     */
+    rig.attestation.shard_id = shard_id + 1;
+    assert!(rig.context.diagnose_signature_failure(&rig.attestation, &signatures).is_err());
 }
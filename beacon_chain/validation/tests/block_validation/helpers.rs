@@ -19,6 +19,7 @@ use super::types::{
     AttestationRecord,
     AttesterMap,
     Block,
+    CycleLength,
     Hash256,
     ProposerMap,
 };
@@ -150,7 +151,7 @@ pub fn setup_block_validation_scenario(params: &BlockTestParams)
                attesters.push(i);
                i += 1;
             }
-            attester_map.insert((attestation_slot, shard), attesters);
+            attester_map.insert(attestation_slot, shard, attesters);
 
             let attestation = generate_attestation(
                 shard,
@@ -219,7 +220,7 @@ pub fn run_block_validation_scenario<F>(
 
     let context = BlockValidationContext {
         present_slot: params.validation_context_slot,
-        cycle_length: params.cycle_length,
+        cycle_length: CycleLength::new(params.cycle_length).unwrap(),
         last_justified_slot: params.validation_context_justified_slot,
         last_justified_block_hash: params.validation_context_justified_block_hash,
         last_finalized_slot: params.validation_context_finalized_slot,
@@ -8,6 +8,7 @@ use super::helpers::{
     serialize_block,
 };
 use super::types::{
+    AttestationRecord,
     Block,
     Hash256,
     ProposerMap,
@@ -16,6 +17,7 @@ use super::ssz_helpers::ssz_block::SszBlock;
 use super::validation::block_validation::{
     SszBlockValidationError,
     BlockStatus,
+    decode_attestation,
 };
 use super::validation::attestation_validation::{
     AttestationValidationError,
@@ -259,3 +261,42 @@ fn test_block_validation_invalid_2nd_attestation_signature() {
     assert_eq!(status, Err(SszBlockValidationError::AttestationValidationError(
                 AttestationValidationError::BadAggregateSignature)));
 }
+
+#[test]
+fn test_block_validation_malformed_attestation_signature() {
+    let attestation = AttestationRecord::zero();
+    let ssz = attestation.ssz_encode();
+    let sig_len = attestation.aggregate_sig.to_bytes().len();
+    let sig_len_index = ssz.len() - sig_len - 4;
+
+    /*
+     * A well-formed signature decodes, even if it would not verify.
+     */
+    assert_eq!(decode_attestation(&ssz), Ok(attestation));
+
+    /*
+     * Truncate the signature by one byte, updating its length prefix to match.
+     */
+    let mut truncated = ssz[..ssz.len() - 1].to_vec();
+    truncated[sig_len_index + 3] -= 1;
+    assert_eq!(decode_attestation(&truncated),
+               Err(SszBlockValidationError::AttestationValidationError(
+                   AttestationValidationError::MalformedAggregateSignature)));
+
+    /*
+     * A signature of the correct length which is not a valid point.
+     */
+    let mut not_a_point = ssz.clone();
+    for byte in &mut not_a_point[sig_len_index + 4..] {
+        *byte = 0xff;
+    }
+    assert_eq!(decode_attestation(&not_a_point),
+               Err(SszBlockValidationError::AttestationValidationError(
+                   AttestationValidationError::MalformedAggregateSignature)));
+
+    /*
+     * A record which ends before its signature is not valid SSZ.
+     */
+    assert_eq!(decode_attestation(&ssz[..sig_len_index]),
+               Err(SszBlockValidationError::BadAttestationSsz));
+}
@@ -1,8 +1,17 @@
+/*
+ * The integration tests require the database and BLS.
+ */
+#![cfg(feature = "std")]
+
 extern crate validation;
 
 extern crate bls;
 extern crate db;
+#[cfg(feature = "tokio")]
+extern crate futures;
 extern crate hashing;
+#[macro_use]
+extern crate proptest;
 extern crate ssz;
 extern crate ssz_helpers;
 extern crate types;
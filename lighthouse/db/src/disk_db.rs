@@ -10,7 +10,8 @@ use super::rocksdb::Error as RocksError;
 use super::{
     ClientDB,
     DBValue,
-    DBError
+    DBError,
+    DBErrorKind,
 };
 
 /// A on-disk database which implements the ClientDB trait.
@@ -75,7 +76,7 @@ impl DiskDB {
 
 impl From<RocksError> for DBError {
     fn from(e: RocksError) -> Self {
-        Self { message: e.to_string() }
+        Self::new(DBErrorKind::Io, e.to_string())
     }
 }
 
@@ -89,7 +90,7 @@ impl ClientDB for DiskDB {
         -> Result<Option<DBValue>, DBError>
     {
         match self.db.cf_handle(col) {
-            None => Err(DBError{ message: "Unknown column".to_string() }),
+            None => Err(DBError::new(DBErrorKind::NotFound, "Unknown column".to_string())),
             Some(handle) => {
                 match self.db.get_cf(handle, key)? {
                     None => Ok(None),
@@ -108,7 +109,7 @@ impl ClientDB for DiskDB {
         -> Result<(), DBError>
     {
         match self.db.cf_handle(col) {
-            None => Err(DBError{ message: "Unknown column".to_string() }),
+            None => Err(DBError::new(DBErrorKind::NotFound, "Unknown column".to_string())),
             Some(handle) => self.db.put_cf(handle, key, val).map_err(|e| e.into())
         }
     }
@@ -123,7 +124,7 @@ impl ClientDB for DiskDB {
          * copy some data, but I could be wrong.
          */
         match self.db.cf_handle(col) {
-            None => Err(DBError{ message: "Unknown column".to_string() }),
+            None => Err(DBError::new(DBErrorKind::NotFound, "Unknown column".to_string())),
             Some(handle) => Ok(self.db.get_cf(handle, key)?.is_some())
         }
     }
@@ -13,6 +13,7 @@ pub use self::disk_db::DiskDB;
 pub use self::memory_db::MemoryDB;
 pub use self::traits::{
     DBError,
+    DBErrorKind,
     DBValue,
     ClientDB,
 };
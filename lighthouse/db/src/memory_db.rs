@@ -5,7 +5,8 @@ use super::COLUMNS;
 use super::{
     ClientDB,
     DBValue,
-    DBError
+    DBError,
+    DBErrorKind,
 };
 
 type DBHashMap = HashMap<Vec<u8>, Vec<u8>>;
@@ -14,7 +15,8 @@ type ColumnHashSet = HashSet<String>;
 /// An in-memory database implementing the ClientDB trait.
 ///
 /// It is not particularily optimized, it exists for ease and speed of testing. It's not expected
-/// this DB would be used outside of tests.
+/// this DB would be used outside of tests, however it is public so that other crates may build
+/// stores (e.g., `BlockStore`, `ValidatorStore`) in their own tests.
 pub struct MemoryDB {
     db: RwLock<DBHashMap>,
     known_columns: RwLock<ColumnHashSet>
@@ -26,9 +28,15 @@ impl MemoryDB {
     /// All columns must be supplied initially, you will get an error if you try to access a column
     /// that was not declared here. This condition is enforced artificially to simulate RocksDB.
     pub fn open() -> Self {
+        Self::open_with_columns(&COLUMNS)
+    }
+
+    /// Open the in-memory database with some set of columns other than those used by the stores
+    /// in this crate.
+    pub fn open_with_columns(columns: &[&str]) -> Self {
         let db: DBHashMap = HashMap::new();
         let mut known_columns: ColumnHashSet = HashSet::new();
-        for col in &COLUMNS {
+        for col in columns {
             known_columns.insert(col.to_string());
         }
         Self {
@@ -56,7 +64,7 @@ impl ClientDB for MemoryDB {
             let column_key = MemoryDB::get_key_for_col(col, key);
             Ok(db.get(&column_key).and_then(|val| Some(val.clone())))
         } else {
-            Err(DBError{ message: "Unknown column".to_string() })
+            Err(DBError::new(DBErrorKind::NotFound, "Unknown column".to_string()))
         }
     }
 
@@ -73,7 +81,7 @@ impl ClientDB for MemoryDB {
             db.insert(column_key, val.to_vec());
             Ok(())
         } else {
-            Err(DBError{ message: "Unknown column".to_string() })
+            Err(DBError::new(DBErrorKind::NotFound, "Unknown column".to_string()))
         }
     }
 
@@ -89,7 +97,7 @@ impl ClientDB for MemoryDB {
             let column_key = MemoryDB::get_key_for_col(col, key);
             Ok(db.contains_key(&column_key))
         } else {
-            Err(DBError{ message: "Unknown column".to_string() })
+            Err(DBError::new(DBErrorKind::NotFound, "Unknown column".to_string()))
 
         }
     }
@@ -142,6 +150,20 @@ mod tests {
 
         assert!(db.get(col_a, "cats".as_bytes()).is_ok());
         assert!(db.get(col_x, "cats".as_bytes()).is_err());
+
+        assert_eq!(db.exists(col_x, "cats".as_bytes()).unwrap_err().kind, DBErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_memorydb_custom_columns() {
+        let col_a: &str = "ColumnA";
+        let col_b: &str = BLOCKS_DB_COLUMN;
+
+        let db = MemoryDB::open_with_columns(&[col_a]);
+
+        db.put(col_a, "cats".as_bytes(), "lol".as_bytes()).unwrap();
+        assert_eq!(db.get(col_a, "cats".as_bytes()).unwrap().unwrap(), "lol".as_bytes());
+        assert_eq!(db.exists(col_b, "cats".as_bytes()).unwrap_err().kind, DBErrorKind::NotFound);
     }
 
     #[test]
@@ -0,0 +1,243 @@
+extern crate bytes;
+extern crate types;
+
+use self::bytes::{
+    BufMut,
+    BytesMut,
+};
+use self::types::AttestationRecord;
+use std::sync::Arc;
+use super::{
+    ClientDB,
+    DBError,
+    DBErrorKind,
+};
+use super::ATTESTATIONS_DB_COLUMN as DB_COLUMN;
+
+#[derive(Debug, PartialEq)]
+enum KeyPrefixes {
+    Attestation,
+    SlotCount,
+}
+
+/// Stores AttestationRecords keyed by slot.
+///
+/// The ClientDB trait does not provide iteration, so the number of attestations stored at each
+/// slot is stored alongside them and each attestation is keyed by its slot and its position
+/// within that slot.
+pub struct AttestationStore<T>
+    where T: ClientDB
+{
+    db: Arc<T>,
+}
+
+impl<T: ClientDB> AttestationStore<T> {
+    pub fn new(db: Arc<T>) -> Self {
+        Self {
+            db,
+        }
+    }
+
+    fn prefix_bytes(&self, key_prefix: &KeyPrefixes)
+        -> Vec<u8>
+    {
+        match key_prefix {
+            KeyPrefixes::Attestation => b"att".to_vec(),
+            KeyPrefixes::SlotCount => b"attcount".to_vec(),
+        }
+    }
+
+    fn get_db_key_for_attestation(&self, slot: u64, position: u64)
+        -> Vec<u8>
+    {
+        let mut buf = BytesMut::with_capacity(3 + 8 + 8);
+        buf.put(self.prefix_bytes(&KeyPrefixes::Attestation));
+        buf.put_u64_be(slot);
+        buf.put_u64_be(position);
+        buf.take().to_vec()
+    }
+
+    fn get_db_key_for_slot_count(&self, slot: u64)
+        -> Vec<u8>
+    {
+        let mut buf = BytesMut::with_capacity(8 + 8);
+        buf.put(self.prefix_bytes(&KeyPrefixes::SlotCount));
+        buf.put_u64_be(slot);
+        buf.take().to_vec()
+    }
+
+    /// Return the number of attestations stored at the given slot.
+    pub fn attestation_count_at_slot(&self, slot: u64)
+        -> Result<u64, DBError>
+    {
+        let key = self.get_db_key_for_slot_count(slot);
+        match self.db.get(DB_COLUMN, &key[..])? {
+            None => Ok(0),
+            Some(val) => {
+                if val.len() != 8 {
+                    return Err(DBError::new(
+                        DBErrorKind::Corruption,
+                        "attestation count is corrupt".to_string()));
+                }
+                Ok(val.iter().fold(0, |acc, byte| (acc << 8) | u64::from(*byte)))
+            }
+        }
+    }
+
+    /// Store an attestation after any others already stored at its slot.
+    ///
+    /// The attestation and the count for its slot are written separately, so concurrent writes
+    /// to the same slot are not supported.
+    pub fn put_attestation(&self, attestation: &AttestationRecord)
+        -> Result<(), DBError>
+    {
+        let position = self.attestation_count_at_slot(attestation.slot)?;
+        let key = self.get_db_key_for_attestation(attestation.slot, position);
+        self.db.put(DB_COLUMN, &key[..], &attestation.ssz_encode()[..])?;
+
+        let count_key = self.get_db_key_for_slot_count(attestation.slot);
+        let mut count = BytesMut::with_capacity(8);
+        count.put_u64_be(position + 1);
+        self.db.put(DB_COLUMN, &count_key[..], &count.take()[..])
+    }
+
+    fn get_attestation(&self, slot: u64, position: u64)
+        -> Result<AttestationRecord, DBError>
+    {
+        let key = self.get_db_key_for_attestation(slot, position);
+        let ssz = self.db.get(DB_COLUMN, &key[..])?
+            .ok_or_else(|| DBError::new(
+                DBErrorKind::Corruption,
+                "attestation count exceeds stored attestations".to_string()))?;
+        AttestationRecord::ssz_decode_exact(&ssz)
+            .map(|(attestation, _)| attestation)
+            .map_err(|_| DBError::new(
+                DBErrorKind::Serialization,
+                "unable to decode attestation".to_string()))
+    }
+
+    /// Iterate over the attestations stored at slots from `start_slot` up to (but not including)
+    /// `end_slot`.
+    ///
+    /// Attestations are read lazily and are yielded in slot order, then in the order they were
+    /// stored. Iteration ends after the first error.
+    pub fn attestations_in_slot_range<'a>(&'a self, start_slot: u64, end_slot: u64)
+        -> impl Iterator<Item = Result<AttestationRecord, DBError>> + 'a
+    {
+        AttestationIter {
+            store: self,
+            slot: start_slot,
+            end_slot,
+            position: 0,
+            count: None,
+            failed: false,
+        }
+    }
+}
+
+/// Lazily reads the attestations in a slot range from an AttestationStore.
+struct AttestationIter<'a, T>
+    where T: ClientDB + 'a
+{
+    store: &'a AttestationStore<T>,
+    slot: u64,
+    end_slot: u64,
+    position: u64,
+    /// The number of attestations at `slot`, if it has been read.
+    count: Option<u64>,
+    failed: bool,
+}
+
+impl<'a, T: ClientDB> Iterator for AttestationIter<'a, T> {
+    type Item = Result<AttestationRecord, DBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.failed && self.slot < self.end_slot {
+            let count = match self.count {
+                Some(count) => count,
+                None => match self.store.attestation_count_at_slot(self.slot) {
+                    Ok(count) => {
+                        self.count = Some(count);
+                        count
+                    }
+                    Err(e) => {
+                        self.failed = true;
+                        return Some(Err(e));
+                    }
+                },
+            };
+
+            if self.position < count {
+                let result = self.store.get_attestation(self.slot, self.position);
+                self.position += 1;
+                self.failed = result.is_err();
+                return Some(result);
+            }
+
+            self.slot += 1;
+            self.position = 0;
+            self.count = None;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::MemoryDB;
+
+    fn attestation(slot: u64, shard_id: u16) -> AttestationRecord {
+        let mut attestation = AttestationRecord::zero();
+        attestation.slot = slot;
+        attestation.shard_id = shard_id;
+        attestation
+    }
+
+    #[test]
+    fn test_attestation_store_slot_range() {
+        let db = Arc::new(MemoryDB::open());
+        let store = AttestationStore::new(db);
+
+        /*
+         * Store out of slot order, with several attestations at some slots and none at others.
+         */
+        let stored = vec![
+            attestation(5, 0),
+            attestation(2, 0),
+            attestation(5, 1),
+            attestation(7, 0),
+            attestation(2, 1),
+            attestation(9, 0),
+        ];
+        for a in &stored {
+            store.put_attestation(a).unwrap();
+        }
+        assert_eq!(store.attestation_count_at_slot(5).unwrap(), 2);
+        assert_eq!(store.attestation_count_at_slot(6).unwrap(), 0);
+
+        let read: Vec<(u64, u16)> = store.attestations_in_slot_range(2, 9)
+            .map(|a| a.map(|a| (a.slot, a.shard_id)))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read, vec![(2, 0), (2, 1), (5, 0), (5, 1), (7, 0)]);
+
+        assert_eq!(store.attestations_in_slot_range(10, 100).count(), 0);
+        assert_eq!(store.attestations_in_slot_range(9, 2).count(), 0);
+    }
+
+    #[test]
+    fn test_attestation_store_corrupt_attestation() {
+        let db = Arc::new(MemoryDB::open());
+        let store = AttestationStore::new(db.clone());
+
+        store.put_attestation(&attestation(1, 0)).unwrap();
+        store.put_attestation(&attestation(1, 1)).unwrap();
+        let key = store.get_db_key_for_attestation(1, 0);
+        db.put(DB_COLUMN, &key[..], b"cats").unwrap();
+
+        let read: Vec<_> = store.attestations_in_slot_range(0, 2).collect();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].as_ref().unwrap_err().kind, DBErrorKind::Serialization);
+    }
+}
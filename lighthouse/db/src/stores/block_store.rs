@@ -1,23 +1,45 @@
+extern crate ssz;
 extern crate ssz_helpers;
+extern crate types;
 
+use self::ssz::{
+    Decodable,
+    SszStream,
+};
 use self::ssz_helpers::ssz_block::{
     SszBlock,
     SszBlockError,
 };
+use self::types::{
+    Block,
+    Hash256,
+};
 use std::sync::Arc;
 use super::{
     ClientDB,
     DBError,
+    DBErrorKind,
 };
 use super::BLOCKS_DB_COLUMN as DB_COLUMN;
 
+/// The key under which the highest slot of any stored block is kept.
+///
+/// Blocks are keyed by their 32 byte hash, so a key of any other length cannot collide with a
+/// block.
+const LATEST_BLOCK_SLOT_KEY: &[u8] = b"latest_block_slot";
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum BlockAtSlotError {
     UnknownBlock,
     InvalidBlock,
-    DBError(String),
+    DBError(DBError),
 }
 
+/// Stores serialized blocks.
+///
+/// Each block is stored in the blocks column with its hash (without any prefix) as the key and
+/// its SSZ encoding as the value. The highest slot of any stored block is also kept in the blocks
+/// column, see `latest_block_slot`.
 pub struct BlockStore<T>
     where T: ClientDB
 {
@@ -34,7 +56,41 @@ impl<T: ClientDB> BlockStore<T> {
     pub fn put_serialized_block(&self, hash: &[u8], ssz: &[u8])
         -> Result<(), DBError>
     {
-        self.db.put(DB_COLUMN, hash, ssz)
+        self.db.put(DB_COLUMN, hash, ssz)?;
+
+        /*
+         * A value too short to contain a slot is stored, but cannot affect the latest block slot.
+         */
+        if let Ok((slot, _)) = u64::ssz_decode(ssz, 32) {
+            if self.latest_block_slot()? < Some(slot) {
+                let mut ssz_stream = SszStream::new();
+                ssz_stream.append(&slot);
+                self.db.put(DB_COLUMN, LATEST_BLOCK_SLOT_KEY, &ssz_stream.drain())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the highest slot of any block written to this store, or `None` if no block has
+    /// been written.
+    ///
+    /// The slot is maintained as blocks are written, it is not lowered if a block is removed or
+    /// replaced. Concurrent writers may race when updating it, so it should only be relied upon
+    /// when blocks are written from a single thread.
+    pub fn latest_block_slot(&self)
+        -> Result<Option<u64>, DBError>
+    {
+        match self.db.get(DB_COLUMN, LATEST_BLOCK_SLOT_KEY)? {
+            None => Ok(None),
+            Some(ssz) => {
+                match u64::ssz_decode(&ssz, 0) {
+                    Ok((slot, 8)) => Ok(Some(slot)),
+                    _ => Err(DBError::new(
+                        DBErrorKind::Corruption,
+                        "latest block slot is corrupt".to_string())),
+                }
+            }
+        }
     }
 
     pub fn get_serialized_block(&self, hash: &[u8])
@@ -43,6 +99,34 @@ impl<T: ClientDB> BlockStore<T> {
         self.db.get(DB_COLUMN, hash)
     }
 
+    /// Serialize a block with SSZ and store it under the given hash.
+    ///
+    /// The hash is not checked against the block.
+    pub fn put_block(&self, hash: &Hash256, block: &Block)
+        -> Result<(), DBError>
+    {
+        let mut ssz_stream = SszStream::new();
+        ssz_stream.append(block);
+        self.put_serialized_block(&hash[..], &ssz_stream.drain())
+    }
+
+    /// Retrieve and deserialize the block stored under the given hash, if any.
+    pub fn get_block(&self, hash: &Hash256)
+        -> Result<Option<Block>, DBError>
+    {
+        match self.get_serialized_block(&hash[..])? {
+            None => Ok(None),
+            Some(ssz) => {
+                match Block::ssz_decode(&ssz, 0) {
+                    Ok((block, i)) if i == ssz.len() => Ok(Some(block)),
+                    _ => Err(DBError::new(
+                        DBErrorKind::Serialization,
+                        "unable to decode block".to_string())),
+                }
+            }
+        }
+    }
+
     pub fn block_exists(&self, hash: &[u8])
         -> Result<bool, DBError>
     {
@@ -55,6 +139,55 @@ impl<T: ClientDB> BlockStore<T> {
         // TODO: implement logic for canonical chain
         self.db.exists(DB_COLUMN, hash)
     }
+}
+
+/// The interface used to read blocks during validation.
+///
+/// Implemented by `BlockStore` and by wrappers around it (e.g., `CachingBlockStore`), allowing
+/// either to be used by a validation context.
+pub trait BlockReader {
+    fn get_serialized_block(&self, hash: &[u8])
+        -> Result<Option<Vec<u8>>, DBError>;
+
+    fn block_exists(&self, hash: &[u8])
+        -> Result<bool, DBError>;
+
+    /// Return the slot of the block with the given hash, if it is known.
+    ///
+    /// The slot is decoded directly from its fixed position in the serialized block, the rest of
+    /// the block is not decoded or validated.
+    fn get_block_slot(&self, hash: &[u8])
+        -> Result<Option<u64>, DBError>
+    {
+        match self.get_serialized_block(hash)? {
+            None => Ok(None),
+            Some(ssz) => {
+                /*
+                 * The slot immediately follows the 32 byte parent hash.
+                 */
+                let (slot, _) = u64::ssz_decode(&ssz, 32)
+                    .map_err(|_| DBError::new(
+                        DBErrorKind::Corruption,
+                        "stored block is too short to contain a slot".to_string()))?;
+                Ok(Some(slot))
+            }
+        }
+    }
+
+    /// Check the existence of each of the given block hashes.
+    ///
+    /// The returned vec is index-aligned with `hashes`.
+    fn block_exists_batch<H>(&self, hashes: &[H])
+        -> Result<Vec<bool>, DBError>
+        where H: AsRef<[u8]>
+    {
+        /*
+         * The ClientDB trait does not provide a multi-get, so each hash is checked in turn.
+         */
+        hashes.iter()
+            .map(|hash| self.block_exists(hash.as_ref()))
+            .collect()
+    }
 
     /// Retrieve the block at a slot given a "head_hash" and a slot.
     ///
@@ -65,7 +198,7 @@ impl<T: ClientDB> BlockStore<T> {
     /// slot number. If the slot is skipped, the function will return None.
     ///
     /// If a block is found, a tuple of (block_hash, serialized_block) is returned.
-    pub fn block_at_slot(&self, head_hash: &[u8], slot: u64)
+    fn block_at_slot(&self, head_hash: &[u8], slot: u64)
         -> Result<Option<(Vec<u8>, Vec<u8>)>, BlockAtSlotError>
     {
         match self.get_serialized_block(head_hash)? {
@@ -83,9 +216,23 @@ impl<T: ClientDB> BlockStore<T> {
     }
 }
 
+impl<T: ClientDB> BlockReader for BlockStore<T> {
+    fn get_serialized_block(&self, hash: &[u8])
+        -> Result<Option<Vec<u8>>, DBError>
+    {
+        BlockStore::get_serialized_block(self, hash)
+    }
+
+    fn block_exists(&self, hash: &[u8])
+        -> Result<bool, DBError>
+    {
+        BlockStore::block_exists(self, hash)
+    }
+}
+
 impl From<DBError> for BlockAtSlotError {
     fn from(e: DBError) -> Self {
-        BlockAtSlotError::DBError(e.message)
+        BlockAtSlotError::DBError(e)
     }
 }
 
@@ -143,6 +290,89 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_block_store_put_get_block() {
+        let db = Arc::new(MemoryDB::open());
+        let store = BlockStore::new(db);
+
+        let mut block = Block::zero();
+        block.slot_number = 42;
+        block.parent_hash = Hash256::from("parent".as_bytes());
+        block.attestations.push(AttestationRecord::zero());
+        let hash = Hash256::from("block".as_bytes());
+
+        assert_eq!(store.get_block(&hash).unwrap(), None);
+        store.put_block(&hash, &block).unwrap();
+        assert_eq!(store.get_block(&hash).unwrap(), Some(block));
+        assert_eq!(store.get_block_slot(&hash[..]).unwrap(), Some(42));
+
+        /*
+         * A stored block which cannot be decoded is an error.
+         */
+        store.put_serialized_block(&hash[..], &[42]).unwrap();
+        assert!(store.get_block(&hash).is_err());
+    }
+
+    #[test]
+    fn test_block_store_latest_block_slot() {
+        let db = Arc::new(MemoryDB::open());
+        let store = BlockStore::new(db);
+        assert_eq!(store.latest_block_slot().unwrap(), None);
+
+        for (i, slot) in [5, 10, 7].iter().enumerate() {
+            let mut block = Block::zero();
+            block.slot_number = *slot;
+            block.attestations.push(AttestationRecord::zero());
+            store.put_block(&Hash256::from(i as u64), &block).unwrap();
+        }
+        assert_eq!(store.latest_block_slot().unwrap(), Some(10));
+
+        /*
+         * A value too short to be a block does not affect the latest slot.
+         */
+        store.put_serialized_block(&[1], &[42]).unwrap();
+        assert_eq!(store.latest_block_slot().unwrap(), Some(10));
+    }
+
+    #[test]
+    fn test_block_exists_batch() {
+        let db = Arc::new(MemoryDB::open());
+        let bs = Arc::new(BlockStore::new(db.clone()));
+
+        let known = Hash256::from("known".as_bytes());
+        let unknown = Hash256::from("unknown".as_bytes());
+        bs.put_serialized_block(&known.to_vec(), &vec![42]).unwrap();
+
+        assert_eq!(bs.block_exists_batch::<Hash256>(&[]).unwrap(), vec![]);
+        assert_eq!(
+            bs.block_exists_batch(&[known, unknown, known]).unwrap(),
+            vec![true, false, true]);
+    }
+
+    #[test]
+    fn test_get_block_slot() {
+        let db = Arc::new(MemoryDB::open());
+        let bs = Arc::new(BlockStore::new(db.clone()));
+
+        let mut block = Block::zero();
+        block.attestations.push(AttestationRecord::zero());
+        block.slot_number = 42;
+        let mut s = SszStream::new();
+        s.append(&block);
+        let hash = Hash256::from("block".as_bytes());
+        bs.put_serialized_block(&hash, &s.drain()).unwrap();
+
+        assert_eq!(bs.get_block_slot(&hash).unwrap(), Some(42));
+        assert_eq!(bs.get_block_slot(&Hash256::from("unknown".as_bytes())).unwrap(), None);
+
+        /*
+         * A stored value too short to contain a slot is corrupt.
+         */
+        let short = Hash256::from("short".as_bytes());
+        bs.put_serialized_block(&short, &[0; 39]).unwrap();
+        assert_eq!(bs.get_block_slot(&short).unwrap_err().kind, DBErrorKind::Corruption);
+    }
+
     #[test]
     fn test_block_at_slot() {
         let db = Arc::new(MemoryDB::open());
@@ -0,0 +1,283 @@
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use super::{
+    BlockReader,
+    BlockStore,
+    ClientDB,
+    DBError,
+};
+
+/// A map of block hash to some value which holds at most `capacity` entries, evicting the least
+/// recently used entry when full.
+///
+/// Each use of an entry stamps it with the next tick of a counter, so the least recently used
+/// entry is that with the lowest tick. Reads, writes and evictions are O(log n).
+struct LruCache<V> {
+    capacity: usize,
+    /// Each value and the tick at which it was last used.
+    map: HashMap<Vec<u8>, (V, u64)>,
+    /// Keys by the tick at which they were last used, from least to most recently used.
+    order: BTreeMap<u64, Vec<u8>>,
+    next_tick: u64,
+}
+
+impl<V: Clone> LruCache<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            map: HashMap::new(),
+            order: BTreeMap::new(),
+            next_tick: 0,
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        tick
+    }
+
+    fn get(&mut self, key: &[u8]) -> Option<V> {
+        let tick = self.tick();
+        let (val, last_used) = self.map.get_mut(key)?;
+        let old_tick = *last_used;
+        *last_used = tick;
+        let val = val.clone();
+        if let Some(key) = self.order.remove(&old_tick) {
+            self.order.insert(tick, key);
+        }
+        Some(val)
+    }
+
+    fn insert(&mut self, key: &[u8], val: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let tick = self.tick();
+        if let Some((_, old_tick)) = self.map.insert(key.to_vec(), (val, tick)) {
+            self.order.remove(&old_tick);
+        }
+        self.order.insert(tick, key.to_vec());
+
+        if self.map.len() > self.capacity {
+            let oldest = self.order.keys().next().cloned();
+            if let Some(evicted) = oldest.and_then(|tick| self.order.remove(&tick)) {
+                self.map.remove(&evicted);
+            }
+        }
+    }
+}
+
+/// A read-through cache over a BlockStore.
+///
+/// Block existence results and recently read blocks are held in bounded LRU caches, misses are
+/// read from the inner store.
+///
+/// Blocks must be written via `put_serialized_block` on this store (not the inner store),
+/// otherwise a cached `false` existence result may become stale.
+pub struct CachingBlockStore<T>
+    where T: ClientDB
+{
+    inner: Arc<BlockStore<T>>,
+    exists_cache: Mutex<LruCache<bool>>,
+    block_cache: Mutex<LruCache<Vec<u8>>>,
+}
+
+impl<T: ClientDB> CachingBlockStore<T> {
+    /// Wrap `inner`, caching at most `exists_capacity` existence results and at most
+    /// `block_capacity` serialized blocks.
+    pub fn new(inner: Arc<BlockStore<T>>, exists_capacity: usize, block_capacity: usize)
+        -> Self
+    {
+        Self {
+            inner,
+            exists_cache: Mutex::new(LruCache::new(exists_capacity)),
+            block_cache: Mutex::new(LruCache::new(block_capacity)),
+        }
+    }
+
+    pub fn put_serialized_block(&self, hash: &[u8], ssz: &[u8])
+        -> Result<(), DBError>
+    {
+        self.inner.put_serialized_block(hash, ssz)?;
+        // Panic if the cache locks are poisoned.
+        self.exists_cache.lock().unwrap().insert(hash, true);
+        self.block_cache.lock().unwrap().insert(hash, ssz.to_vec());
+        Ok(())
+    }
+}
+
+impl<T: ClientDB> BlockReader for CachingBlockStore<T> {
+    fn get_serialized_block(&self, hash: &[u8])
+        -> Result<Option<Vec<u8>>, DBError>
+    {
+        // Panic if the cache locks are poisoned.
+        if let Some(ssz) = self.block_cache.lock().unwrap().get(hash) {
+            return Ok(Some(ssz));
+        }
+        let ssz = self.inner.get_serialized_block(hash)?;
+        if let Some(ref ssz) = ssz {
+            self.block_cache.lock().unwrap().insert(hash, ssz.clone());
+            self.exists_cache.lock().unwrap().insert(hash, true);
+        }
+        Ok(ssz)
+    }
+
+    fn block_exists(&self, hash: &[u8])
+        -> Result<bool, DBError>
+    {
+        // Panic if the cache lock is poisoned.
+        if let Some(exists) = self.exists_cache.lock().unwrap().get(hash) {
+            return Ok(exists);
+        }
+        let exists = self.inner.block_exists(hash)?;
+        self.exists_cache.lock().unwrap().insert(hash, exists);
+        Ok(exists)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::{
+        DBValue,
+        MemoryDB,
+    };
+    use std::sync::atomic::{
+        AtomicUsize,
+        Ordering,
+    };
+
+    /// A MemoryDB which counts the number of reads it has served.
+    struct CountingDB {
+        db: MemoryDB,
+        reads: AtomicUsize,
+    }
+
+    impl CountingDB {
+        fn new() -> Self {
+            Self {
+                db: MemoryDB::open(),
+                reads: AtomicUsize::new(0),
+            }
+        }
+
+        fn reads(&self) -> usize {
+            self.reads.load(Ordering::SeqCst)
+        }
+    }
+
+    impl ClientDB for CountingDB {
+        fn get(&self, col: &str, key: &[u8])
+            -> Result<Option<DBValue>, DBError>
+        {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.db.get(col, key)
+        }
+
+        fn put(&self, col: &str, key: &[u8], val: &[u8])
+            -> Result<(), DBError>
+        {
+            self.db.put(col, key, val)
+        }
+
+        fn exists(&self, col: &str, key: &[u8])
+            -> Result<bool, DBError>
+        {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.db.exists(col, key)
+        }
+    }
+
+    #[test]
+    fn test_caching_block_store_block_exists() {
+        let db = Arc::new(CountingDB::new());
+        let inner = Arc::new(BlockStore::new(db.clone()));
+        inner.put_serialized_block(b"known", &[42]).unwrap();
+        let store = CachingBlockStore::new(inner, 8, 8);
+
+        assert!(store.block_exists(b"known").unwrap());
+        assert!(!store.block_exists(b"unknown").unwrap());
+        assert_eq!(db.reads(), 2);
+
+        /*
+         * Repeated checks are served from the cache.
+         */
+        assert!(store.block_exists(b"known").unwrap());
+        assert!(!store.block_exists(b"unknown").unwrap());
+        assert_eq!(db.reads(), 2);
+
+        /*
+         * Writing through the cache updates existence.
+         */
+        store.put_serialized_block(b"unknown", &[43]).unwrap();
+        assert!(store.block_exists(b"unknown").unwrap());
+        assert_eq!(store.get_serialized_block(b"unknown").unwrap(), Some(vec![43]));
+        assert_eq!(db.reads(), 2);
+    }
+
+    #[test]
+    fn test_lru_cache_eviction() {
+        let mut cache = LruCache::new(4);
+        for (i, key) in [b"a", b"b", b"c", b"d"].iter().enumerate() {
+            cache.insert(&key[..], i);
+        }
+
+        /*
+         * Using "a" and overwriting "b" leaves "c" then "d" as the least recently used.
+         */
+        assert_eq!(cache.get(b"a"), Some(0));
+        cache.insert(b"b", 10);
+        cache.insert(b"e", 4);
+        assert_eq!(cache.get(b"c"), None);
+        cache.insert(b"f", 5);
+        assert_eq!(cache.get(b"d"), None);
+
+        assert_eq!(cache.get(b"a"), Some(0));
+        assert_eq!(cache.get(b"b"), Some(10));
+        assert_eq!(cache.get(b"e"), Some(4));
+        assert_eq!(cache.get(b"f"), Some(5));
+        assert_eq!(cache.map.len(), 4);
+        assert_eq!(cache.order.len(), 4);
+
+        /*
+         * A missed read does not change the order, so "a" is evicted next.
+         */
+        assert_eq!(cache.get(b"z"), None);
+        cache.insert(b"g", 6);
+        assert_eq!(cache.get(b"a"), None);
+        assert_eq!(cache.get(b"b"), Some(10));
+    }
+
+    #[test]
+    fn test_caching_block_store_eviction() {
+        let db = Arc::new(CountingDB::new());
+        let inner = Arc::new(BlockStore::new(db.clone()));
+        for key in &[b"a", b"b", b"c"] {
+            inner.put_serialized_block(&key[..], &key[..]).unwrap();
+        }
+        let store = CachingBlockStore::new(inner, 2, 2);
+
+        store.get_serialized_block(b"a").unwrap();
+        store.get_serialized_block(b"b").unwrap();
+        assert_eq!(db.reads(), 2);
+
+        /*
+         * Reading "a" makes "b" the least recently used, so it is evicted by "c".
+         */
+        store.get_serialized_block(b"a").unwrap();
+        store.get_serialized_block(b"c").unwrap();
+        assert_eq!(db.reads(), 3);
+
+        assert_eq!(store.get_serialized_block(b"a").unwrap(), Some(b"a".to_vec()));
+        assert_eq!(db.reads(), 3);
+        assert_eq!(store.get_serialized_block(b"b").unwrap(), Some(b"b".to_vec()));
+        assert_eq!(db.reads(), 4);
+    }
+}
@@ -1,16 +1,22 @@
 use super::{
     ClientDB,
     DBError,
+    DBErrorKind,
 };
 
+mod attestation_store;
 mod block_store;
+mod caching_block_store;
 mod pow_chain_store;
 mod validator_store;
 
+pub use self::attestation_store::AttestationStore;
 pub use self::block_store::{
+    BlockReader,
     BlockStore,
     BlockAtSlotError,
 };
+pub use self::caching_block_store::CachingBlockStore;
 pub use self::pow_chain_store::PoWChainStore;
 pub use self::validator_store::{
     ValidatorStore,
@@ -19,12 +25,48 @@ pub use self::validator_store::{
 
 use super::bls;
 
+/*
+ * Each store reads and writes only its own column, so the key spaces of the stores never collide.
+ * All stores may therefore wrap the same `Arc<ClientDB>` (e.g., a single `DiskDB` opened with
+ * `COLUMNS`).
+ */
+pub const ATTESTATIONS_DB_COLUMN: &str = "attestations";
 pub const BLOCKS_DB_COLUMN: &str = "blocks";
 pub const POW_CHAIN_DB_COLUMN: &str = "powchain";
 pub const VALIDATOR_DB_COLUMN: &str = "validator";
 
-pub const COLUMNS: [&str; 3] = [
+pub const COLUMNS: [&str; 4] = [
+    ATTESTATIONS_DB_COLUMN,
     BLOCKS_DB_COLUMN,
     POW_CHAIN_DB_COLUMN,
     VALIDATOR_DB_COLUMN,
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::MemoryDB;
+    use super::bls::Keypair;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_stores_share_db() {
+        let db = Arc::new(MemoryDB::open());
+        let validator_store = ValidatorStore::new(db.clone());
+        let block_store = BlockStore::new(db.clone());
+
+        let keypair = Keypair::random();
+        validator_store.put_public_key_by_index(0, &keypair.pk).unwrap();
+
+        /*
+         * Store a block under exactly the key used for the public key of validator 0.
+         */
+        let mut key = b"pubkey".to_vec();
+        key.extend_from_slice(&[0; 8]);
+        block_store.put_serialized_block(&key, &[42]).unwrap();
+
+        assert_eq!(validator_store.get_public_key_by_index(0).unwrap(), Some(keypair.pk));
+        assert_eq!(block_store.get_serialized_block(&key).unwrap(), Some(vec![42]));
+        assert_eq!(validator_store.validator_count().unwrap(), 1);
+    }
+}
@@ -8,25 +8,41 @@ use std::sync::Arc;
 use super::{
     ClientDB,
     DBError,
+    DBErrorKind,
 };
 use super::VALIDATOR_DB_COLUMN as DB_COLUMN;
 use super::bls::PublicKey;
 
 #[derive(Debug, PartialEq)]
 pub enum ValidatorStoreError {
-    DBError(String),
-    DecodeError,
+    DBError(DBError),
+    /// The stored public key for the validator at this index could not be decoded.
+    DecodeError(usize),
 }
 
 impl From<DBError> for ValidatorStoreError {
     fn from(error: DBError) -> Self {
-        ValidatorStoreError::DBError(error.message)
+        ValidatorStoreError::DBError(error)
     }
 }
 
+/// The version of the snapshot format written by `ValidatorStore::export`.
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+/// Set in the flags of a snapshot entry if the validator has a public key.
+const SNAPSHOT_HAS_PUBLIC_KEY: u8 = 0b01;
+/// Set in the flags of a snapshot entry if the validator has recorded activity.
+const SNAPSHOT_HAS_ACTIVITY: u8 = 0b10;
+
+/// The length of a stored validator activity (the activation and exit slots).
+const ACTIVITY_BYTES: usize = 16;
+
 #[derive(Debug, PartialEq)]
 enum KeyPrefixes {
     PublicKey,
+    PublicKeyIndex,
+    ValidatorCount,
+    Activity,
 }
 
 pub struct ValidatorStore<T>
@@ -47,25 +63,167 @@ impl<T: ClientDB> ValidatorStore<T> {
     {
         match key_prefix {
             KeyPrefixes::PublicKey => b"pubkey".to_vec(),
+            KeyPrefixes::PublicKeyIndex => b"pkindex".to_vec(),
+            KeyPrefixes::ValidatorCount => b"valcount".to_vec(),
+            KeyPrefixes::Activity => b"activity".to_vec(),
         }
     }
 
     fn get_db_key_for_index(&self, key_prefix: &KeyPrefixes, index: usize)
         -> Vec<u8>
     {
-        let mut buf = BytesMut::with_capacity(6 + 8);
-        buf.put(self.prefix_bytes(key_prefix));
-        buf.put_u64_be(index as u64);
-        buf.take().to_vec()
+        /*
+         * Prefixes differ in length, so the key is built in a growable vec.
+         */
+        let mut key = self.prefix_bytes(key_prefix);
+        key.put_u64_be(index as u64);
+        key
+    }
+
+    fn get_db_key_for_public_key(&self, public_key_bytes: &[u8])
+        -> Vec<u8>
+    {
+        let mut key = self.prefix_bytes(&KeyPrefixes::PublicKeyIndex);
+        key.extend_from_slice(public_key_bytes);
+        key
     }
 
     pub fn put_public_key_by_index(&self, index: usize, public_key: &PublicKey)
         -> Result<(), ValidatorStoreError>
+    {
+        Ok(self.put_public_key(index, public_key)?)
+    }
+
+    fn put_public_key(&self, index: usize, public_key: &PublicKey)
+        -> Result<(), DBError>
+    {
+        self.put_public_key_bytes(index, &public_key.as_bytes())
+    }
+
+    fn put_public_key_bytes(&self, index: usize, val: &[u8])
+        -> Result<(), DBError>
     {
         let key = self.get_db_key_for_index(&KeyPrefixes::PublicKey, index);
-        let val = public_key.as_bytes();
-        self.db.put(DB_COLUMN, &key[..], &val[..])
-                    .map_err(ValidatorStoreError::from)
+        self.db.put(DB_COLUMN, &key[..], val)?;
+
+        /*
+         * Maintain the reverse mapping of public key to index.
+         */
+        let index_key = self.get_db_key_for_public_key(val);
+        let mut index_bytes = BytesMut::with_capacity(8);
+        index_bytes.put_u64_be(index as u64);
+        self.db.put(DB_COLUMN, &index_key[..], &index_bytes.take()[..])?;
+
+        /*
+         * Keep the validator count up-to-date so it can be read without scanning the store.
+         */
+        if index >= self.validator_count()? {
+            self.put_validator_count(index + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Register a validator with the given public key at the next index (i.e., the validator
+    /// count), returning that index.
+    ///
+    /// Registering a public key which is already in the store returns its existing index and
+    /// writes nothing. Registration is not atomic, concurrent registrations must be serialized
+    /// by the caller.
+    pub fn register_validator(&self, public_key: PublicKey)
+        -> Result<usize, DBError>
+    {
+        if let Some(index) = self.index_for_public_key(&public_key)? {
+            return Ok(index);
+        }
+        let index = self.validator_count()?;
+        self.put_public_key(index, &public_key)?;
+        Ok(index)
+    }
+
+    /// Return the index of the validator with the given public key, if any.
+    ///
+    /// This is an indexed lookup (two reads), not a scan of all validators. The reverse mapping
+    /// is written by `put_public_key_by_index`.
+    pub fn index_for_public_key(&self, public_key: &PublicKey)
+        -> Result<Option<usize>, DBError>
+    {
+        let public_key_bytes = public_key.as_bytes();
+        let index_key = self.get_db_key_for_public_key(&public_key_bytes[..]);
+        let index = match self.db.get(DB_COLUMN, &index_key[..])? {
+            None => return Ok(None),
+            Some(val) => decode_usize(&val, "public key index")?,
+        };
+
+        /*
+         * If the public key at the index has since been replaced, the reverse mapping is stale.
+         */
+        let key = self.get_db_key_for_index(&KeyPrefixes::PublicKey, index);
+        match self.db.get(DB_COLUMN, &key[..])? {
+            Some(ref val) if *val == public_key_bytes => Ok(Some(index)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Return the number of validators registered in the store.
+    ///
+    /// Validators are indexed from zero, so this is one more than the highest index with a
+    /// public key in the store. It is maintained by `put_public_key_by_index`.
+    pub fn validator_count(&self)
+        -> Result<usize, DBError>
+    {
+        let key = self.prefix_bytes(&KeyPrefixes::ValidatorCount);
+        match self.db.get(DB_COLUMN, &key[..])? {
+            None => Ok(0),
+            Some(val) => decode_usize(&val, "validator count"),
+        }
+    }
+
+    fn put_validator_count(&self, count: usize)
+        -> Result<(), DBError>
+    {
+        let key = self.prefix_bytes(&KeyPrefixes::ValidatorCount);
+        let mut buf = BytesMut::with_capacity(8);
+        buf.put_u64_be(count as u64);
+        self.db.put(DB_COLUMN, &key[..], &buf.take()[..])
+    }
+
+    /// Record the slot at which the validator at `index` was activated and the slot at which it
+    /// exited (if it has exited).
+    ///
+    /// A validator is active at slots from `activation_slot` up to, but excluding, `exit_slot`.
+    pub fn put_validator_activity(
+        &self,
+        index: usize,
+        activation_slot: u64,
+        exit_slot: Option<u64>)
+        -> Result<(), DBError>
+    {
+        let key = self.get_db_key_for_index(&KeyPrefixes::Activity, index);
+        let mut buf = BytesMut::with_capacity(16);
+        buf.put_u64_be(activation_slot);
+        buf.put_u64_be(exit_slot.unwrap_or_else(u64::max_value));
+        self.db.put(DB_COLUMN, &key[..], &buf.take()[..])
+    }
+
+    /// Return true if the validator at `index` was active at `slot`.
+    ///
+    /// A validator without activity recorded by `put_validator_activity` is never active.
+    pub fn is_active_at_slot(&self, index: usize, slot: u64)
+        -> Result<bool, DBError>
+    {
+        let key = self.get_db_key_for_index(&KeyPrefixes::Activity, index);
+        match self.db.get(DB_COLUMN, &key[..])? {
+            None => Ok(false),
+            Some(val) => {
+                if val.len() != 16 {
+                    return Err(DBError::new(DBErrorKind::Corruption,
+                                            "validator activity is corrupt".to_string()));
+                }
+                let activation_slot = decode_u64(&val[0..8], "activation slot")?;
+                let exit_slot = decode_u64(&val[8..16], "exit slot")?;
+                Ok(activation_slot <= slot && slot < exit_slot)
+            }
+        }
     }
 
     pub fn get_public_key_by_index(&self, index: usize)
@@ -78,11 +236,183 @@ impl<T: ClientDB> ValidatorStore<T> {
             Some(val) => {
                 match PublicKey::from_bytes(&val) {
                     Ok(key) => Ok(Some(key)),
-                    Err(_) => Err(ValidatorStoreError::DecodeError),
+                    Err(_) => Err(ValidatorStoreError::DecodeError(index)),
                 }
             }
         }
     }
+
+    /// Serialize the public key and activity of every validator below `validator_count()` into a
+    /// snapshot which may be restored with `import`.
+    ///
+    /// The snapshot format is (all integers big-endian):
+    ///
+    /// - The format version, one byte (`SNAPSHOT_VERSION`).
+    /// - The validator count, eight bytes.
+    /// - For each index from zero up to the validator count, in order:
+    ///   - Flags, one byte: `0b01` if a public key follows, `0b10` if activity follows.
+    ///   - If flagged, the length of the public key (eight bytes) followed by the public key as
+    ///     stored.
+    ///   - If flagged, the activation slot and exit slot (eight bytes each), where an exit slot of
+    ///     `u64::max_value()` means the validator has not exited.
+    ///
+    /// A reader must reject a snapshot with a version it does not know.
+    pub fn export(&self) -> Result<Vec<u8>, DBError> {
+        let count = self.validator_count()?;
+        let mut buf = Vec::with_capacity(9 + count);
+        buf.put_u8(SNAPSHOT_VERSION);
+        buf.put_u64_be(count as u64);
+
+        for index in 0..count {
+            let key = self.get_db_key_for_index(&KeyPrefixes::PublicKey, index);
+            let public_key = self.db.get(DB_COLUMN, &key[..])?;
+            let key = self.get_db_key_for_index(&KeyPrefixes::Activity, index);
+            let activity = self.db.get(DB_COLUMN, &key[..])?;
+
+            let mut flags = 0;
+            if public_key.is_some() {
+                flags |= SNAPSHOT_HAS_PUBLIC_KEY;
+            }
+            if activity.is_some() {
+                flags |= SNAPSHOT_HAS_ACTIVITY;
+            }
+            buf.put_u8(flags);
+            if let Some(val) = public_key {
+                buf.put_u64_be(val.len() as u64);
+                buf.put(val);
+            }
+            if let Some(val) = activity {
+                if val.len() != ACTIVITY_BYTES {
+                    return Err(DBError::new(DBErrorKind::Corruption,
+                                            "validator activity is corrupt".to_string()));
+                }
+                buf.put(val);
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Restore a snapshot written by `export`, as if each public key and activity in it were put
+    /// at its index.
+    ///
+    /// This is intended for an empty store: validators already in the store but not in the
+    /// snapshot are kept, and the validator count never decreases. The snapshot is decoded in
+    /// full before anything is written, so a malformed snapshot (returned as a `Serialization`
+    /// error) leaves the store unchanged. Public keys are not decoded.
+    pub fn import(&self, bytes: &[u8]) -> Result<(), DBError> {
+        let mut bytes = bytes;
+        let version = take_bytes(&mut bytes, 1, "snapshot version")?[0];
+        if version != SNAPSHOT_VERSION {
+            return Err(DBError::new(DBErrorKind::Serialization,
+                                    format!("unsupported snapshot version {}", version)));
+        }
+        let count = take_u64(&mut bytes, "validator count")? as usize;
+
+        /*
+         * Each entry is at least one byte, so a count larger than the remaining bytes is invalid
+         * and must not be used to size an allocation.
+         */
+        if count > bytes.len() {
+            return Err(snapshot_error("validator count"));
+        }
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let flags = take_bytes(&mut bytes, 1, "entry flags")?[0];
+            if flags & !(SNAPSHOT_HAS_PUBLIC_KEY | SNAPSHOT_HAS_ACTIVITY) != 0 {
+                return Err(snapshot_error("entry flags"));
+            }
+            let public_key = if flags & SNAPSHOT_HAS_PUBLIC_KEY != 0 {
+                let len = take_u64(&mut bytes, "public key length")? as usize;
+                Some(take_bytes(&mut bytes, len, "public key")?)
+            } else {
+                None
+            };
+            let activity = if flags & SNAPSHOT_HAS_ACTIVITY != 0 {
+                Some(take_bytes(&mut bytes, ACTIVITY_BYTES, "validator activity")?)
+            } else {
+                None
+            };
+            entries.push((public_key, activity));
+        }
+        if !bytes.is_empty() {
+            return Err(snapshot_error("trailing bytes"));
+        }
+
+        for (index, (public_key, activity)) in entries.into_iter().enumerate() {
+            if let Some(val) = public_key {
+                self.put_public_key_bytes(index, val)?;
+            }
+            if let Some(val) = activity {
+                let key = self.get_db_key_for_index(&KeyPrefixes::Activity, index);
+                self.db.put(DB_COLUMN, &key[..], val)?;
+            }
+        }
+
+        /*
+         * Trailing validators may have activity but no public key, which does not update the
+         * count.
+         */
+        if count > self.validator_count()? {
+            self.put_validator_count(count)?;
+        }
+        Ok(())
+    }
+
+    /// Retrieve the public keys for each of the given validator indices.
+    ///
+    /// The returned vec is index-aligned with `indices`. A validator without a public key in the
+    /// store is represented by `None`.
+    pub fn get_public_keys(&self, indices: &[usize])
+        -> Result<Vec<Option<PublicKey>>, ValidatorStoreError>
+    {
+        /*
+         * The ClientDB trait does not provide a multi-get, so each key is read in turn.
+         */
+        indices.iter()
+            .map(|index| self.get_public_key_by_index(*index))
+            .collect()
+    }
+}
+
+fn snapshot_error(name: &str) -> DBError {
+    DBError::new(DBErrorKind::Serialization, format!("snapshot {} is invalid", name))
+}
+
+/// Remove and return the first `len` bytes of `bytes`, describing them as `name` in any error.
+fn take_bytes<'a>(bytes: &mut &'a [u8], len: usize, name: &str)
+    -> Result<&'a [u8], DBError>
+{
+    if bytes.len() < len {
+        return Err(snapshot_error(name));
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+
+/// Remove and decode a value written with `put_u64_be` from the start of `bytes`.
+fn take_u64(bytes: &mut &[u8], name: &str)
+    -> Result<u64, DBError>
+{
+    let val = take_bytes(bytes, 8, name)?;
+    Ok(val.iter().fold(0, |acc, byte| (acc << 8) | u64::from(*byte)))
+}
+
+/// Decode a value written with `put_u64_be`, describing it as `name` in any error.
+fn decode_usize(val: &[u8], name: &str)
+    -> Result<usize, DBError>
+{
+    decode_u64(val, name).map(|val| val as usize)
+}
+
+/// Decode a value written with `put_u64_be`, describing it as `name` in any error.
+fn decode_u64(val: &[u8], name: &str)
+    -> Result<u64, DBError>
+{
+    if val.len() != 8 {
+        return Err(DBError::new(DBErrorKind::Corruption, format!("{} is corrupt", name)));
+    }
+    Ok(val.iter().fold(0, |acc, byte| (acc << 8) | u64::from(*byte)))
 }
 
 #[cfg(test)]
@@ -124,6 +454,220 @@ mod tests {
                 .unwrap().is_none());
     }
 
+    #[test]
+    fn test_validator_store_get_public_keys() {
+        let db = Arc::new(MemoryDB::open());
+        let store = ValidatorStore::new(db);
+
+        let keys = vec![
+            Keypair::random(),
+            Keypair::random(),
+            Keypair::random(),
+        ];
+
+        for i in 0..keys.len() {
+            store.put_public_key_by_index(i, &keys[i].pk).unwrap();
+        }
+
+        let retrieved = store.get_public_keys(&[2, 42, 0]).unwrap();
+        assert_eq!(retrieved, vec![
+            Some(keys[2].pk.clone()),
+            None,
+            Some(keys[0].pk.clone()),
+        ]);
+    }
+
+    #[test]
+    fn test_validator_store_validator_count() {
+        let db = Arc::new(MemoryDB::open());
+        let store = ValidatorStore::new(db);
+        assert_eq!(store.validator_count().unwrap(), 0);
+
+        let keypair = Keypair::random();
+        store.put_public_key_by_index(0, &keypair.pk).unwrap();
+        assert_eq!(store.validator_count().unwrap(), 1);
+
+        store.put_public_key_by_index(4, &keypair.pk).unwrap();
+        assert_eq!(store.validator_count().unwrap(), 5);
+
+        /*
+         * Replacing a lower index does not change the count.
+         */
+        store.put_public_key_by_index(2, &keypair.pk).unwrap();
+        assert_eq!(store.validator_count().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_validator_store_index_for_public_key() {
+        let db = Arc::new(MemoryDB::open());
+        let store = ValidatorStore::new(db);
+
+        let keys = vec![
+            Keypair::random(),
+            Keypair::random(),
+            Keypair::random(),
+        ];
+
+        for (i, keypair) in keys.iter().enumerate() {
+            store.put_public_key_by_index(i, &keypair.pk).unwrap();
+        }
+
+        for (i, keypair) in keys.iter().enumerate() {
+            assert_eq!(store.index_for_public_key(&keypair.pk).unwrap(), Some(i));
+        }
+        assert_eq!(store.index_for_public_key(&Keypair::random().pk).unwrap(), None);
+
+        /*
+         * Replacing the public key at an index removes the old public key.
+         */
+        let replacement = Keypair::random();
+        store.put_public_key_by_index(1, &replacement.pk).unwrap();
+        assert_eq!(store.index_for_public_key(&keys[1].pk).unwrap(), None);
+        assert_eq!(store.index_for_public_key(&replacement.pk).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn test_validator_store_register_validator() {
+        let db = Arc::new(MemoryDB::open());
+        let store = ValidatorStore::new(db);
+
+        let keys = [
+            Keypair::random(),
+            Keypair::random(),
+            Keypair::random(),
+        ];
+
+        for (i, keypair) in keys.iter().enumerate() {
+            assert_eq!(store.register_validator(keypair.pk.clone()).unwrap(), i);
+        }
+        assert_eq!(store.validator_count().unwrap(), 3);
+        for (i, keypair) in keys.iter().enumerate() {
+            assert_eq!(store.get_public_key_by_index(i).unwrap(), Some(keypair.pk.clone()));
+            assert_eq!(store.index_for_public_key(&keypair.pk).unwrap(), Some(i));
+        }
+
+        /*
+         * Registering a public key again returns its existing index.
+         */
+        assert_eq!(store.register_validator(keys[1].pk.clone()).unwrap(), 1);
+        assert_eq!(store.validator_count().unwrap(), 3);
+
+        /*
+         * Registration follows any validators put at explicit indices.
+         */
+        store.put_public_key_by_index(5, &Keypair::random().pk).unwrap();
+        assert_eq!(store.register_validator(Keypair::random().pk).unwrap(), 6);
+    }
+
+    #[test]
+    fn test_validator_store_is_active_at_slot() {
+        let db = Arc::new(MemoryDB::open());
+        let store = ValidatorStore::new(db);
+
+        store.put_validator_activity(0, 10, None).unwrap();
+        store.put_validator_activity(1, 10, Some(20)).unwrap();
+
+        assert!(!store.is_active_at_slot(0, 9).unwrap());
+        assert!(store.is_active_at_slot(0, 10).unwrap());
+        assert!(store.is_active_at_slot(0, u64::max_value() - 1).unwrap());
+
+        assert!(store.is_active_at_slot(1, 19).unwrap());
+        assert!(!store.is_active_at_slot(1, 20).unwrap());
+
+        /*
+         * A validator without recorded activity is not active.
+         */
+        assert!(!store.is_active_at_slot(2, 10).unwrap());
+    }
+
+    #[test]
+    fn test_validator_store_export_import() {
+        let store = ValidatorStore::new(Arc::new(MemoryDB::open()));
+
+        let keys: Vec<Keypair> = (0..1000).map(|_| Keypair::random()).collect();
+        for (i, keypair) in keys.iter().enumerate() {
+            store.put_public_key_by_index(i, &keypair.pk).unwrap();
+            match i % 3 {
+                0 => store.put_validator_activity(i, i as u64, None).unwrap(),
+                1 => store.put_validator_activity(i, i as u64, Some(i as u64 + 10)).unwrap(),
+                _ => (),
+            }
+        }
+        /*
+         * A trailing validator with activity but no public key is kept.
+         */
+        store.put_validator_count(1001).unwrap();
+        store.put_validator_activity(1000, 5, None).unwrap();
+
+        let snapshot = store.export().unwrap();
+        assert_eq!(snapshot[0], SNAPSHOT_VERSION);
+
+        let imported = ValidatorStore::new(Arc::new(MemoryDB::open()));
+        imported.import(&snapshot).unwrap();
+
+        assert_eq!(imported.validator_count().unwrap(), 1001);
+        for (i, keypair) in keys.iter().enumerate() {
+            assert_eq!(imported.get_public_key_by_index(i).unwrap(), Some(keypair.pk.clone()));
+            assert_eq!(imported.index_for_public_key(&keypair.pk).unwrap(), Some(i));
+            for slot in &[i as u64, i as u64 + 10] {
+                assert_eq!(imported.is_active_at_slot(i, *slot).unwrap(),
+                           store.is_active_at_slot(i, *slot).unwrap());
+            }
+        }
+        assert_eq!(imported.get_public_key_by_index(1000).unwrap(), None);
+        assert!(imported.is_active_at_slot(1000, 5).unwrap());
+        assert_eq!(imported.export().unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_validator_store_import_invalid() {
+        let store = ValidatorStore::new(Arc::new(MemoryDB::open()));
+        let keypair = Keypair::random();
+        store.put_public_key_by_index(0, &keypair.pk).unwrap();
+        store.put_validator_activity(0, 1, None).unwrap();
+        let snapshot = store.export().unwrap();
+
+        let imported = ValidatorStore::new(Arc::new(MemoryDB::open()));
+        let mut invalid = vec![
+            vec![],
+            snapshot[..snapshot.len() - 1].to_vec(),
+            [&snapshot[..], &[0]].concat(),
+        ];
+        let mut bad_version = snapshot.clone();
+        bad_version[0] = SNAPSHOT_VERSION + 1;
+        invalid.push(bad_version);
+        let mut bad_flags = snapshot.clone();
+        bad_flags[9] = 0b100;
+        invalid.push(bad_flags);
+        let mut bad_count = snapshot.clone();
+        bad_count[1] = 1;
+        invalid.push(bad_count);
+
+        for bytes in invalid {
+            assert_eq!(imported.import(&bytes).unwrap_err().kind, DBErrorKind::Serialization);
+        }
+
+        /*
+         * Nothing is written by a failed import.
+         */
+        assert_eq!(imported.validator_count().unwrap(), 0);
+        assert_eq!(imported.get_public_key_by_index(0).unwrap(), None);
+        assert!(!imported.is_active_at_slot(0, 1).unwrap());
+    }
+
+    #[test]
+    fn test_validator_store_db_key_for_index() {
+        let store = ValidatorStore::new(Arc::new(MemoryDB::open()));
+
+        let key = store.get_db_key_for_index(&KeyPrefixes::Activity, 258);
+        let mut expected = b"activity".to_vec();
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 2]);
+        assert_eq!(key, expected);
+
+        let key = store.get_db_key_for_index(&KeyPrefixes::PublicKey, 258);
+        assert_eq!(key.len(), b"pubkey".len() + 8);
+    }
+
     #[test]
     fn test_validator_store_bad_key() {
         let db = Arc::new(MemoryDB::open());
@@ -133,6 +677,6 @@ mod tests {
         db.put(DB_COLUMN, &key[..], "cats".as_bytes()).unwrap();
 
         assert_eq!(store.get_public_key_by_index(42),
-            Err(ValidatorStoreError::DecodeError));
+            Err(ValidatorStoreError::DecodeError(42)));
     }
 }
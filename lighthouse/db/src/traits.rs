@@ -1,13 +1,28 @@
 pub type DBValue = Vec<u8>;
 
-#[derive(Debug)]
+/// The category of a DBError, allowing callers to decide how to handle it (e.g., an `Io` error
+/// may be retried whereas a `Corruption` error may not).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DBErrorKind {
+    /// Some required item (e.g., a column) does not exist.
+    NotFound,
+    /// The underlying database failed to perform the operation.
+    Io,
+    /// Data was read from the database but it is invalid.
+    Corruption,
+    /// Data could not be encoded or decoded.
+    Serialization,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct DBError {
+    pub kind: DBErrorKind,
     pub message: String
 }
 
 impl DBError {
-    pub fn new(message: String) -> Self {
-        Self { message }
+    pub fn new(kind: DBErrorKind, message: String) -> Self {
+        Self { kind, message }
     }
 }
 